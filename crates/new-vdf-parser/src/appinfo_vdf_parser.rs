@@ -1,117 +1,1185 @@
 use std::i64;
+use std::collections::HashMap;
+use std::ops::Deref;
 use std::{path::PathBuf, fs};
-use std::io::Read;
+use std::io::{Read, Write};
 
-use serde_json::{Value, Map};
+use memmap2::Mmap;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde_json::{json, Map, Value};
+use sha1::{Digest, Sha1};
 
+use crate::error::VdfError;
+use crate::kv_value::KvMap;
+use crate::kv_value_ref::{KvMapRef, KvValueRef};
 use crate::reader::Reader;
-use crate::vdf_reader::read_entry_map;
+use crate::writer::{write_file_atomic, Writer};
+use crate::vdf_reader::{read_entry_map_ref, visit_entry_map, EntryContext};
+use crate::visitor::{KvVisitor, ProjectionVisitor};
+use crate::vdf_writer::{write_entry_map, StringTable};
 
-/// Opens the appinfo.vdf file and returns the values as JSON.
-pub fn open_appinfo_vdf(path: &PathBuf, filter: Option<bool>) -> Map<String, Value> {
-  let mut file = fs::File::open(path).expect("Path should have existed.");
-  let metadata = fs::metadata(path).expect("Unable to read metadata.");
+/// The magic number of the older appinfo.vdf files, whose app headers don't have a binary SHA-1.
+pub const APPINFO_MAGIC_V27: u32 = 0x07564427;
+/// The magic number of appinfo.vdf files where keys are written inline.
+pub const APPINFO_MAGIC_V28: u32 = 0x07564428;
+/// The magic number of appinfo.vdf files where keys are indices into a string table at the end of the file.
+pub const APPINFO_MAGIC_V29: u32 = 0x07564429;
 
-  let mut buffer = Vec::with_capacity(metadata.len() as usize);
-  file.read_to_end(&mut buffer).expect("Buffer overflow.");
-  
-  let mut reader = Reader::new(&buffer);
+/// The versions of the appinfo.vdf format. The version is the last byte of the file's magic number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppInfoVersion {
+  /// Keys are written inline, and app headers don't have a binary SHA-1.
+  V27,
+  /// Keys are written inline.
+  V28,
+  /// Keys are indices into a string table at the end of the file.
+  V29,
+}
+
+impl AppInfoVersion {
+  /// Gets the version of a magic number. Magic numbers that look like appinfo.vdf, but have a version this crate doesn't know, return `VdfError::UnsupportedVersion`.
+  pub fn from_magic(magic: u32) -> Result<AppInfoVersion, VdfError> {
+    match magic {
+      APPINFO_MAGIC_V27 => return Ok(AppInfoVersion::V27),
+      APPINFO_MAGIC_V28 => return Ok(AppInfoVersion::V28),
+      APPINFO_MAGIC_V29 => return Ok(AppInfoVersion::V29),
+      //? Steam's beta clients bump the version without notice, so report those differently from files that aren't appinfo.vdf at all.
+      _ if magic >> 8 == APPINFO_MAGIC_V29 >> 8 => return Err(VdfError::UnsupportedVersion(magic)),
+      _ => return Err(VdfError::UnknownMagic(magic)),
+    }
+  }
+
+  /// Gets the magic number of this version.
+  pub fn to_magic(self) -> u32 {
+    match self {
+      AppInfoVersion::V27 => return APPINFO_MAGIC_V27,
+      AppInfoVersion::V28 => return APPINFO_MAGIC_V28,
+      AppInfoVersion::V29 => return APPINFO_MAGIC_V29,
+    }
+  }
+
+  /// Gets the length of the app header fields that follow the size, before the app's KeyValues data.
+  pub fn get_header_length(self) -> usize {
+    match self {
+      AppInfoVersion::V27 => return 40,
+      AppInfoVersion::V28 | AppInfoVersion::V29 => return 60,
+    }
+  }
+
+  /// Checks if app headers end with the SHA-1 hash of the app's binary data.
+  pub fn has_binary_sha1(self) -> bool {
+    return self != AppInfoVersion::V27;
+  }
+
+  /// Checks if keys are indices into a string table.
+  pub fn has_string_table(self) -> bool {
+    return self == AppInfoVersion::V29;
+  }
+}
+
+/// The header Steam writes before each app's KeyValues data.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AppInfoHeader {
+  pub appid: u32,
+  /// The size of the app's section, not including the appid and size fields.
+  pub size: u32,
+  pub info_state: u32,
+  pub last_updated: u32,
+  pub pics_token: u64,
+  /// The SHA-1 hash of the app's data in text KeyValues form.
+  pub text_sha1: [u8; 20],
+  pub change_number: u32,
+  /// The SHA-1 hash of the app's binary KeyValues data. Always zero in v27 files, which don't have it.
+  pub binary_sha1: [u8; 20],
+}
+
+/// An app in the appinfo.vdf file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppInfoEntry {
+  pub header: AppInfoHeader,
+  /// The app's data, including the top level "appinfo" map.
+  pub data: KvMap,
+}
+
+/// The full contents of an appinfo.vdf file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppInfoVdf {
+  pub magic: u32,
+  pub universe: u32,
+  pub entries: Vec<AppInfoEntry>,
+}
+
+/// A problem found while verifying an appinfo.vdf file.
+#[derive(Debug)]
+pub enum AppInfoProblem {
+  /// The file ended in the middle of this app, so it and any apps after it are missing.
+  Truncated { appid: u32 },
+  /// The app's binary SHA-1 doesn't match its data.
+  ChecksumMismatch { appid: u32, expected: [u8; 20], actual: [u8; 20] },
+  /// The app's data couldn't be decoded.
+  Corrupt { appid: u32, error: VdfError },
+  /// The file ended before the end of its v29 string table, which starts at `offset`. Only the strings before the end were read.
+  TruncatedStringTable { offset: usize, strings_read: usize },
+}
 
-  return read(&mut reader, filter);
+impl AppInfoProblem {
+  /// Gets the appid of the app with the problem, or `None` for problems with the whole file.
+  pub fn get_appid(&self) -> Option<u32> {
+    match self {
+      AppInfoProblem::Truncated { appid } | AppInfoProblem::ChecksumMismatch { appid, .. } | AppInfoProblem::Corrupt { appid, .. } => return Some(*appid),
+      AppInfoProblem::TruncatedStringTable { .. } => return None,
+    }
+  }
 }
 
-/// Reads the appinfo.vdf file and returns the values as JSON.
-fn read(reader: &mut Reader, filter: Option<bool>) -> Map<String, Value> {
-  let magic = reader.read_uint32(true);
-  let _universe = reader.read_uint32(true); //always 1
+impl std::fmt::Display for AppInfoProblem {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AppInfoProblem::Truncated { appid } => write!(f, "App {} is truncated", appid),
+      AppInfoProblem::ChecksumMismatch { appid, expected, actual } => write!(f, "App {} has binary SHA-1 {} but its data hashes to {}", appid, to_hex(expected), to_hex(actual)),
+      AppInfoProblem::Corrupt { appid, error } => write!(f, "App {} is corrupt: {}", appid, error),
+      AppInfoProblem::TruncatedStringTable { offset, strings_read } => write!(f, "The string table at offset {} is truncated after {} strings", offset, strings_read),
+    }
+  }
+}
+
+/// Chooses which apps are kept when reading appinfo.vdf, and which of their keys.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AppInfoFilter {
+  /// The app types to keep, like "Game" or "Application", compared ignoring case. Apps without a "common"/"type" are dropped.
+  /// Every app is kept if `None`.
+  pub app_types: Option<Vec<String>>,
+  /// The key paths to keep, like "common/name", relative to the "appinfo" map. Each path keeps its whole subtree.
+  /// Every key is kept if `None`.
+  pub keys: Option<Vec<String>>,
+}
+
+impl AppInfoFilter {
+  /// Creates a filter from the app types and key paths to keep.
+  pub fn new(app_types: Option<&[&str]>, keys: Option<&[&str]>) -> AppInfoFilter {
+    return AppInfoFilter {
+      app_types: app_types.map(| app_types | app_types.iter().map(| app_type | app_type.to_string()).collect()),
+      keys: keys.map(| keys | keys.iter().map(| key | key.to_string()).collect()),
+    };
+  }
+
+  /// Creates a filter that keeps every app and key.
+  pub fn all() -> AppInfoFilter {
+    return AppInfoFilter::default();
+  }
+
+  /// Creates a filter that only keeps games.
+  pub fn games() -> AppInfoFilter {
+    return AppInfoFilter::new(Some(&["game"]), None);
+  }
+
+  /// Checks if an app with the provided type passes the filter.
+  pub fn matches_type(&self, app_type: Option<&str>) -> bool {
+    match (&self.app_types, app_type) {
+      (None, _) => return true,
+      (Some(_), None) => return false,
+      (Some(app_types), Some(app_type)) => return app_types.iter().any(| allowed | allowed.eq_ignore_ascii_case(app_type)),
+    }
+  }
 
-  let entries: Vec<Value>;
+  /// Applies the filter to an app's "appinfo" map, returning `None` if the app is filtered out, and only the selected keys if it isn't.
+  pub fn apply<'a>(&self, app: KvMapRef<'a>) -> Option<KvMapRef<'a>> {
+    let app_type = app.get("common").and_then(KvValueRef::as_map).and_then(| common | common.get("type")).and_then(KvValueRef::as_str);
+
+    if !self.matches_type(app_type) {
+      return None;
+    }
+
+    match &self.keys {
+      Some(keys) => {
+        let paths: Vec<&str> = keys.iter().map(String::as_str).collect();
+        return Some(project_map(app, &paths));
+      },
+      None => return Some(app),
+    }
+  }
 
-  if magic == 0x07564429 {
-    let string_table_offset = reader.read_int64(true);
-    let data_offset = reader.get_offset();
-    
-    reader.seek(string_table_offset.try_into().expect("String table offset couldn't be converted to usize"), 0);
+  /// Gets the key paths to read from an app's data when projecting it, or `None` if every key is kept.
+  /// The app's type is always read so the filter can check it, and is dropped again by `apply` if it wasn't asked for.
+  fn get_projection_paths(&self) -> Option<Vec<String>> {
+    let keys = self.keys.as_ref()?;
+    let mut paths: Vec<String> = keys.clone();
 
-    let string_count = reader.read_uint32(true) as usize;
-    let mut strings = Vec::with_capacity(string_count);
-    
-    for _ in 0..string_count {
-      strings.push(reader.read_string(None));
+    if self.app_types.is_some() {
+      paths.push("common/type".to_owned());
     }
-    
-    reader.seek(data_offset, 0);
-    
-    entries = read_app_sections(reader, Some(string_table_offset), Some(magic), &Some(&mut strings), filter);
-  } else if magic == 0x07564428 {
-    entries = read_app_sections(reader, None, None, &None, filter);
-  } else {
-    panic!("Magic header is unknown. Expected 0x07564428 or 0x07564429 but got {magic}");
+
+    //? Steam puts each app under an "appinfo" root, but data without one is read as is, like `get_appinfo_map` does.
+    let rooted: Vec<String> = paths.iter().map(| path | format!("appinfo/{}", path)).collect();
+    paths.extend(rooted);
+
+    return Some(paths);
+  }
+}
+
+/// Converts the old games only flag. `None` and `Some(true)` only keep games, and `Some(false)` keeps every app.
+impl From<Option<bool>> for AppInfoFilter {
+  fn from(filter: Option<bool>) -> AppInfoFilter {
+    if filter.unwrap_or(true) {
+      return AppInfoFilter::games();
+    } else {
+      return AppInfoFilter::all();
+    }
+  }
+}
+
+/// Keeps only the entries of a map on the provided "/" separated key paths.
+fn project_map<'a>(map: KvMapRef<'a>, paths: &[&str]) -> KvMapRef<'a> {
+  return map.into_iter().filter_map(| (key, value) | {
+    let mut sub_paths: Vec<&str> = Vec::new();
+
+    for path in paths {
+      match path.split_once('/') {
+        Some((first, rest)) if first == key => sub_paths.push(rest),
+        //? A path that ends at this key keeps the whole value.
+        None if *path == key => return Some((key, value)),
+        _ => {},
+      }
+    }
+
+    if sub_paths.is_empty() {
+      return None;
+    }
+
+    match value {
+      KvValueRef::Map(sub_map) => return Some((key, KvValueRef::Map(project_map(sub_map, &sub_paths)))),
+      _ => return None,
+    }
+  }).collect();
+}
+
+impl AppInfoHeader {
+  /// Converts the header to JSON. The hashes are written as hex strings.
+  pub fn to_json(&self) -> Value {
+    return json!({
+      "appid": self.appid,
+      "size": self.size,
+      "info_state": self.info_state,
+      "last_updated": self.last_updated,
+      "pics_token": self.pics_token,
+      "text_sha1": to_hex(&self.text_sha1),
+      "change_number": self.change_number,
+      "binary_sha1": to_hex(&self.binary_sha1),
+    });
+  }
+
+  /// Converts JSON written by `to_json` back to a header.
+  pub fn from_json(header: &Map<String, Value>) -> Result<AppInfoHeader, VdfError> {
+    return Ok(AppInfoHeader {
+      appid: get_json_number(header, "appid")? as u32,
+      size: get_json_number(header, "size")? as u32,
+      info_state: get_json_number(header, "info_state")? as u32,
+      last_updated: get_json_number(header, "last_updated")? as u32,
+      pics_token: get_json_number(header, "pics_token")?,
+      text_sha1: from_hex(header.get("text_sha1").and_then(Value::as_str).unwrap_or_default())?,
+      change_number: get_json_number(header, "change_number")? as u32,
+      binary_sha1: from_hex(header.get("binary_sha1").and_then(Value::as_str).unwrap_or_default())?,
+    });
+  }
+}
+
+impl AppInfoVdf {
+  /// Gets the version of the file from its magic number.
+  pub fn get_version(&self) -> Result<AppInfoVersion, VdfError> {
+    return AppInfoVersion::from_magic(self.magic);
+  }
+
+  /// Converts the file to JSON, keeping each app's header so it can be converted back with `from_json`.
+  pub fn to_json(&self) -> Value {
+    let apps: Vec<Value> = self.entries.iter().map(| entry | json!({ "header": entry.header.to_json(), "data": entry.data.to_json() })).collect();
+
+    return json!({ "magic": self.magic, "universe": self.universe, "apps": apps });
+  }
+
+  /// Converts JSON written by `to_json` back to an AppInfoVdf.
+  pub fn from_json(appinfo: &Map<String, Value>) -> Result<AppInfoVdf, VdfError> {
+    let apps = appinfo.get("apps").and_then(Value::as_array).ok_or(VdfError::InvalidData(String::from("\"apps\" should have been an array")))?;
+    let mut entries = Vec::with_capacity(apps.len());
+
+    for app in apps {
+      let header = app.get("header").and_then(Value::as_object).ok_or(VdfError::InvalidData(String::from("App \"header\" should have been an object")))?;
+      let data = app.get("data").and_then(Value::as_object).ok_or(VdfError::InvalidData(String::from("App \"data\" should have been an object")))?;
+
+      entries.push(AppInfoEntry { header: AppInfoHeader::from_json(header)?, data: KvMap::from_json(data)? });
+    }
+
+    return Ok(AppInfoVdf {
+      magic: get_json_number(appinfo, "magic")? as u32,
+      universe: get_json_number(appinfo, "universe")? as u32,
+      entries,
+    });
+  }
+}
+
+/// Gets a number from a JSON object.
+fn get_json_number(map: &Map<String, Value>, key: &str) -> Result<u64, VdfError> {
+  return map.get(key).and_then(Value::as_u64).ok_or(VdfError::InvalidData(format!("\"{}\" should have been a number", key)));
+}
+
+/// Converts a hash to a hex string.
+fn to_hex(hash: &[u8; 20]) -> String {
+  return hash.iter().map(| byte | format!("{:02x}", byte)).collect();
+}
+
+/// Converts a hex string to a hash.
+fn from_hex(hex: &str) -> Result<[u8; 20], VdfError> {
+  let mut hash = [0; 20];
+
+  if hex.len() != 40 || !hex.is_ascii() {
+    return Err(VdfError::InvalidData(format!("\"{}\" is not a 40 character hex hash", hex)));
+  }
+
+  for (index, byte) in hash.iter_mut().enumerate() {
+    *byte = u8::from_str_radix(&hex[(index * 2)..(index * 2 + 2)], 16).map_err(| _ | VdfError::InvalidData(format!("\"{}\" is not a 40 character hex hash", hex)))?;
   }
 
-  let mut res: Map<String, Value> = Map::new();
-  res.insert(String::from("entries"), Value::Array(entries));
+  return Ok(hash);
+}
+
+/// Opens the appinfo.vdf file and returns the "appinfo" map of each app.
+/// The file is memory mapped, and only the apps and keys that pass the filter are copied out of it.
+/// `filter` can be an `AppInfoFilter`, or `Option<bool>` where `None` and `Some(true)` only keep games.
+pub fn open_appinfo_vdf(path: &PathBuf, filter: impl Into<AppInfoFilter>) -> Result<Vec<KvMap>, VdfError> {
+  let index = AppInfoIndex::open(path)?;
+
+  return Ok(index.read_apps_ref(&filter.into())?.into_iter().map(KvMapRef::into_kv_map).collect());
+}
+
+/// Reads appinfo.vdf data from any reader, like a `File` or a `&[u8]`, and returns the "appinfo" map of each app.
+pub fn parse_appinfo(mut reader: impl Read, filter: impl Into<AppInfoFilter>) -> Result<Vec<KvMap>, VdfError> {
+  let mut buffer = Vec::new();
+  reader.read_to_end(&mut buffer)?;
+
+  let index = AppInfoIndex::from_bytes(buffer)?;
+
+  return Ok(index.read_apps_ref(&filter.into())?.into_iter().map(KvMapRef::into_kv_map).collect());
+}
+
+/// Opens the appinfo.vdf file and returns every app with its header, so it can be written back with `write_appinfo_vdf`.
+pub fn open_appinfo_vdf_raw(path: &PathBuf) -> Result<AppInfoVdf, VdfError> {
+  return AppInfoIndex::open(path)?.read_all();
+}
+
+/// Checks every app in the appinfo.vdf file, and returns the ones that are truncated, corrupt or don't match their binary SHA-1.
+/// An empty list means the file is intact.
+pub fn verify_appinfo_vdf(path: &PathBuf) -> Result<Vec<AppInfoProblem>, VdfError> {
+  return Ok(AppInfoIndex::open_partial(path)?.verify());
+}
+
+/// Reads appinfo.vdf data from any reader, keeping the headers and the "appinfo" root of each app.
+pub fn parse_appinfo_raw(mut reader: impl Read) -> Result<AppInfoVdf, VdfError> {
+  let mut buffer = Vec::new();
+  reader.read_to_end(&mut buffer)?;
 
-  return res;
+  return AppInfoIndex::from_bytes(buffer)?.read_all();
 }
 
 struct AppInfoChunk {
+  pub header: AppInfoHeader,
   pub offset: usize,
   pub length: usize,
 }
 
-/// Reads the appinfo.vdf app sections to a JSON array.
-fn read_app_sections(reader: &mut Reader, string_table_offset: Option<i64>, magic: Option<u32>, strings: &Option<&mut Vec<String>>, filter: Option<bool>) -> Vec<Value> {
-  let mut id = reader.read_uint32(true);
-  let eof = string_table_offset.unwrap_or(i64::MAX) as usize - 4;
+/// The contents of an appinfo.vdf file, either memory mapped or already in memory.
+enum AppInfoData {
+  Mapped(Mmap),
+  Owned(Vec<u8>),
+}
+
+impl Deref for AppInfoData {
+  type Target = [u8];
+
+  fn deref(&self) -> &[u8] {
+    match self {
+      AppInfoData::Mapped(mmap) => return mmap,
+      AppInfoData::Owned(buffer) => return buffer,
+    }
+  }
+}
+
+/// An index of the apps in an appinfo.vdf file. The app headers are read once when it is created, and apps are only decoded when they are requested.
+/// Apps can be decoded into owned `KvMap`s, or into `KvMapRef`s that borrow their strings from the index instead of copying them.
+pub struct AppInfoIndex {
+  data: AppInfoData,
+  version: AppInfoVersion,
+  universe: u32,
+  strings: Option<Vec<String>>,
+  chunks: Vec<AppInfoChunk>,
+  positions: HashMap<u32, usize>,
+  /// The appid of the app the file ended in, for indexes opened with `open_partial`.
+  truncated: Option<u32>,
+  /// The offset of the v29 string table, if the file ended before its end. Only for indexes opened with `open_partial`.
+  truncated_string_table: Option<usize>,
+}
+
+impl AppInfoIndex {
+  /// Opens the appinfo.vdf file and indexes its apps.
+  /// The file is memory mapped instead of read, so only the pages of the apps that are decoded are loaded.
+  pub fn open(path: &PathBuf) -> Result<AppInfoIndex, VdfError> {
+    return AppInfoIndex::from_data(map_appinfo_file(path)?, false);
+  }
+
+  /// Opens the appinfo.vdf file like `open`, but keeps the apps before the one the file ends in if it is truncated, instead of failing.
+  /// Use `get_truncated` to check if the file was truncated.
+  /// v29 files keep their string table at the end, so a truncated one only has the strings before the end of the file, and apps that use the missing ones fail to decode.
+  pub fn open_partial(path: &PathBuf) -> Result<AppInfoIndex, VdfError> {
+    return AppInfoIndex::from_data(map_appinfo_file(path)?, true);
+  }
+
+  /// Indexes the apps in the contents of an appinfo.vdf file.
+  pub fn from_bytes(data: Vec<u8>) -> Result<AppInfoIndex, VdfError> {
+    return AppInfoIndex::from_data(AppInfoData::Owned(data), false);
+  }
+
+  /// Indexes the apps in the contents of an appinfo.vdf file like `from_bytes`, but keeps the apps before the one the file ends in if it is truncated, like `open_partial`.
+  pub fn from_bytes_partial(data: Vec<u8>) -> Result<AppInfoIndex, VdfError> {
+    return AppInfoIndex::from_data(AppInfoData::Owned(data), true);
+  }
+
+  /// Indexes the apps in the file's data. If `partial` is true, a truncated app ends the index instead of failing.
+  fn from_data(data: AppInfoData, partial: bool) -> Result<AppInfoIndex, VdfError> {
+    let mut reader = Reader::new(&data);
+
+    let version = AppInfoVersion::from_magic(reader.read_uint32(true)?)?;
+    let universe = reader.read_uint32(true)?; //always 1
+
+    let strings: Option<Vec<String>>;
+    let chunks: Vec<AppInfoChunk>;
+    let truncated: Option<u32>;
+    let mut truncated_string_table: Option<usize> = None;
+
+    if version.has_string_table() {
+      let string_table_offset = reader.read_int64(true)?;
+      let data_offset = reader.get_offset();
+
+      let table_offset: usize = string_table_offset.try_into().map_err(| _ | VdfError::InvalidData(format!("String table offset {} is negative", string_table_offset)))?;
+      reader.seek(table_offset, 0);
+
+      let (table, is_complete) = read_string_table(&mut reader, partial)?;
+
+      if !is_complete {
+        truncated_string_table = Some(table_offset);
+      }
+
+      reader.seek(data_offset, 0);
+
+      (chunks, truncated) = read_app_chunks(&mut reader, version, Some(string_table_offset), partial)?;
+      strings = Some(table);
+    } else {
+      (chunks, truncated) = read_app_chunks(&mut reader, version, None, partial)?;
+      strings = None;
+    }
+
+    let positions = chunks.iter().enumerate().map(| (position, chunk) | (chunk.header.appid, position)).collect();
+
+    return Ok(AppInfoIndex { data, version, universe, strings, chunks, positions, truncated, truncated_string_table });
+  }
+
+  /// Gets the magic number of the file.
+  pub fn get_magic(&self) -> u32 { return self.version.to_magic(); }
+  /// Gets the version of the file.
+  pub fn get_version(&self) -> AppInfoVersion { return self.version; }
+  /// Gets the appid of the app the file ended in, if it was opened with `open_partial` and is truncated.
+  pub fn get_truncated(&self) -> Option<u32> { return self.truncated; }
+  /// Gets the universe of the file.
+  pub fn get_universe(&self) -> u32 { return self.universe; }
+  /// Gets the number of apps in the file.
+  pub fn len(&self) -> usize { return self.chunks.len(); }
+  /// Checks if the file has no apps.
+  pub fn is_empty(&self) -> bool { return self.chunks.is_empty(); }
+
+  /// Iterates over the app headers in file order.
+  pub fn headers(&self) -> impl Iterator<Item = &AppInfoHeader> {
+    return self.chunks.iter().map(| chunk | &chunk.header);
+  }
+
+  /// Iterates over the appids in file order.
+  pub fn appids(&self) -> impl Iterator<Item = u32> + '_ {
+    return self.chunks.iter().map(| chunk | chunk.header.appid);
+  }
+
+  /// Checks if the file has an app with the provided appid.
+  pub fn contains(&self, appid: u32) -> bool {
+    return self.positions.contains_key(&appid);
+  }
+
+  /// Gets the header of the app with the provided appid.
+  pub fn get_header(&self, appid: u32) -> Option<&AppInfoHeader> {
+    return self.positions.get(&appid).map(| position | &self.chunks[*position].header);
+  }
+
+  /// Decodes the app with the provided appid. Returns `None` if the file has no app with that appid.
+  pub fn get(&self, appid: u32) -> Result<Option<AppInfoEntry>, VdfError> {
+    match self.positions.get(&appid) {
+      Some(position) => return self.read_chunk(&self.chunks[*position]).map(Some),
+      None => return Ok(None),
+    }
+  }
+
+  /// Decodes the app with the provided appid without copying its strings. Returns `None` if the file has no app with that appid.
+  /// The data includes the "appinfo" root.
+  pub fn get_ref(&self, appid: u32) -> Result<Option<KvMapRef<'_>>, VdfError> {
+    match self.positions.get(&appid) {
+      Some(position) => return self.read_chunk_ref(&self.chunks[*position]).map(Some),
+      None => return Ok(None),
+    }
+  }
+
+  /// Decodes the "appinfo" map of the app with the provided appid, if it passes the filter. Returns `None` if the file has no app with that appid or it was filtered out.
+  /// When the filter has key paths, keys that aren't on them are skipped without being decoded.
+  pub fn get_filtered_ref(&self, appid: u32, filter: &AppInfoFilter) -> Result<Option<KvMapRef<'_>>, VdfError> {
+    match self.positions.get(&appid) {
+      Some(position) => return self.read_chunk_filtered(&self.chunks[*position], filter),
+      None => return Ok(None),
+    }
+  }
+
+  /// Visits the data of the app with the provided appid, including the "appinfo" root. Returns `false` if the file has no app with that appid.
+  pub fn visit<'a, V: KvVisitor<'a>>(&'a self, appid: u32, visitor: &mut V) -> Result<bool, VdfError> {
+    match self.positions.get(&appid) {
+      Some(position) => {
+        self.visit_chunk(&self.chunks[*position], visitor)?;
+        return Ok(true);
+      },
+      None => return Ok(false),
+    }
+  }
+
+  /// Decodes every app in the file.
+  pub fn read_all(&self) -> Result<AppInfoVdf, VdfError> {
+    let entries = self.chunks.par_iter().map(| chunk | self.read_chunk(chunk)).collect::<Result<Vec<AppInfoEntry>, VdfError>>()?;
+
+    return Ok(AppInfoVdf { magic: self.version.to_magic(), universe: self.universe, entries });
+  }
+
+  /// Decodes the "appinfo" map of every app that passes the filter, without copying its strings.
+  pub fn read_apps_ref(&self, filter: &AppInfoFilter) -> Result<Vec<KvMapRef<'_>>, VdfError> {
+    let apps = self.chunks.par_iter()
+      .map(| chunk | self.read_chunk_filtered(chunk, filter))
+      .collect::<Result<Vec<Option<KvMapRef>>, VdfError>>()?;
+
+    return Ok(apps.into_iter().flatten().collect());
+  }
+
+  /// Checks every app's data against its binary SHA-1, and that it can be decoded. Returns the apps with problems, including the one the file ends in if it is truncated.
+  /// v27 files don't have a binary SHA-1, so only decoding is checked for them. A truncated v29 string table is reported along with the apps it affects.
+  pub fn verify(&self) -> Vec<AppInfoProblem> {
+    let mut problems: Vec<AppInfoProblem> = self.chunks.par_iter().filter_map(| chunk | self.verify_chunk(chunk)).collect();
+
+    if let Some(appid) = self.truncated {
+      problems.push(AppInfoProblem::Truncated { appid });
+    }
+
+    if let Some(offset) = self.truncated_string_table {
+      problems.push(AppInfoProblem::TruncatedStringTable { offset, strings_read: self.strings.as_ref().map(Vec::len).unwrap_or_default() });
+    }
+
+    return problems;
+  }
+
+  /// Checks an app's data against its binary SHA-1, and that it can be decoded.
+  fn verify_chunk(&self, chunk: &AppInfoChunk) -> Option<AppInfoProblem> {
+    let appid = chunk.header.appid;
+    let stored = &self.data[chunk.offset..chunk.offset + chunk.length];
+
+    //? Apps that use strings past the end of a truncated string table can't be decoded, but their stored data can still be checked against the hash of it.
+    if self.truncated_string_table.is_some() && self.version.has_binary_sha1() && Sha1::digest(stored)[..] == chunk.header.binary_sha1[..] {
+      return None;
+    }
+
+    let data = match self.read_chunk_ref(chunk) {
+      Ok(data) => data,
+      Err(error) => return Some(AppInfoProblem::Corrupt { appid, error }),
+    };
+
+    if !self.version.has_binary_sha1() {
+      return None;
+    }
+
+    match get_binary_sha1(self.version, stored, &data.into_kv_map()) {
+      Ok(actual) if actual.contains(&chunk.header.binary_sha1) => return None,
+      Ok(actual) => return Some(AppInfoProblem::ChecksumMismatch { appid, expected: chunk.header.binary_sha1, actual: actual[0] }),
+      Err(error) => return Some(AppInfoProblem::Corrupt { appid, error }),
+    }
+  }
+
+  /// Decodes an app's data.
+  fn read_chunk(&self, chunk: &AppInfoChunk) -> Result<AppInfoEntry, VdfError> {
+    let data: KvMap = self.read_chunk_ref(chunk)?.into_kv_map();
+
+    return Ok(AppInfoEntry { header: chunk.header.clone(), data });
+  }
+
+  /// Decodes an app's data, borrowing its strings from the file's data and string table.
+  fn read_chunk_ref(&self, chunk: &AppInfoChunk) -> Result<KvMapRef<'_>, VdfError> {
+    let reader = Reader::new(&self.data);
+    let magic = if self.strings.is_some() { Some(self.version.to_magic()) } else { None };
+    let mut context = EntryContext::new(Some(chunk.header.appid), &[]);
+
+    let mut chunk_reader = reader.slice(chunk.offset, chunk.length).map_err(| err | context.wrap(err, chunk.offset))?;
+
+    return read_entry_map_ref(&mut chunk_reader, magic, self.strings.as_deref(), &mut context);
+  }
+
+  /// Decodes an app's "appinfo" map and applies the filter to it, only decoding the keys it keeps.
+  fn read_chunk_filtered(&self, chunk: &AppInfoChunk, filter: &AppInfoFilter) -> Result<Option<KvMapRef<'_>>, VdfError> {
+    let entry = match filter.get_projection_paths() {
+      Some(paths) => {
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        let mut visitor = ProjectionVisitor::new(&paths);
+
+        self.visit_chunk(chunk, &mut visitor)?;
+        visitor.into_map()
+      },
+      None => self.read_chunk_ref(chunk)?,
+    };
+
+    return Ok(filter.apply(get_appinfo_map(entry)));
+  }
+
+  /// Visits an app's data with the provided visitor.
+  fn visit_chunk<'a, V: KvVisitor<'a>>(&'a self, chunk: &AppInfoChunk, visitor: &mut V) -> Result<bool, VdfError> {
+    let reader = Reader::new(&self.data);
+    let magic = if self.strings.is_some() { Some(self.version.to_magic()) } else { None };
+    let mut context = EntryContext::new(Some(chunk.header.appid), &[]);
+
+    let mut chunk_reader = reader.slice(chunk.offset, chunk.length).map_err(| err | context.wrap(err, chunk.offset))?;
+
+    return visit_entry_map(&mut chunk_reader, magic, self.strings.as_deref(), &mut context, visitor);
+  }
+}
+
+/// Memory maps an appinfo.vdf file.
+fn map_appinfo_file(path: &PathBuf) -> Result<AppInfoData, VdfError> {
+  let file = fs::File::open(path)?;
+
+  //? Mapping an empty file fails on some platforms, so let parsing report it as truncated instead.
+  if file.metadata()?.len() == 0 {
+    return Ok(AppInfoData::Owned(Vec::new()));
+  }
+
+  //? SAFETY: The map is only valid while the file isn't truncated by another process, like Steam rewriting it.
+  //? Every read is bounds checked against the length it had when mapped, and indexes are meant to be short lived, so drop the index once you're done with it.
+  let mmap = unsafe { Mmap::map(&file)? };
+
+  return Ok(AppInfoData::Mapped(mmap));
+}
+
+/// Gets the binary SHA-1 hashes an app's data can have, with the one this crate writes first.
+///
+/// In v28 files the binary SHA-1 is the hash of the app's stored data, where keys are written inline.
+/// Valve hasn't documented what v29 files hash now that keys are string table indices, so two candidates are accepted:
+/// - The data with its keys written inline, like v28 stores it. This is what Steam gets from its servers, and is written for apps whose hash doesn't match either.
+/// - The data exactly as it is stored, with string table indices. Its indices depend on the order apps are written in, so it changes when other apps do.
+///
+/// `write_appinfo` keeps whichever one an app already has if it still matches, so files Steam wrote are written back unchanged either way.
+fn get_binary_sha1(version: AppInfoVersion, stored: &[u8], data: &KvMap) -> Result<Vec<[u8; 20]>, VdfError> {
+  let stored_sha1: [u8; 20] = Sha1::digest(stored).into();
+
+  if !version.has_string_table() {
+    return Ok(vec![stored_sha1]);
+  }
+
+  let mut inline_writer = Writer::new(Vec::new());
+  write_entry_map(&mut inline_writer, data, None)?;
+  let inline_sha1: [u8; 20] = Sha1::digest(inline_writer.into_inner()).into();
+
+  return Ok(vec![inline_sha1, stored_sha1]);
+}
+
+/// Reads the v29 string table at the reader's offset, and returns it along with whether it was complete.
+/// If `partial` is true, a table the file ends in keeps the strings before the end instead of failing.
+fn read_string_table(reader: &mut Reader, partial: bool) -> Result<(Vec<String>, bool), VdfError> {
+  let string_count = match reader.read_uint32(true) {
+    Ok(string_count) => string_count as usize,
+    Err(VdfError::UnexpectedEof { .. }) if partial => return Ok((Vec::new(), false)),
+    Err(err) => return Err(err),
+  };
+
+  let mut table = Vec::with_capacity(string_count.min(reader.remaining() as usize));
+
+  for _ in 0..string_count {
+    match reader.read_string(None) {
+      Ok(string) => table.push(string),
+      Err(VdfError::UnexpectedEof { .. }) if partial => return Ok((table, false)),
+      Err(err) => return Err(err),
+    }
+  }
+
+  return Ok((table, true));
+}
+
+/// Unwraps the "appinfo" root of an app's data, if it has one.
+fn get_appinfo_map(mut entry: KvMapRef) -> KvMapRef {
+  if let Some(KvValueRef::Map(appinfo)) = entry.remove("appinfo") {
+    return appinfo;
+  }
+
+  return entry;
+}
+
+/// Reads the header of an app, after its appid.
+fn read_app_header(reader: &mut Reader, appid: u32, version: AppInfoVersion) -> Result<AppInfoHeader, VdfError> {
+  let size = reader.read_uint32(true)?;
+  let header_length = version.get_header_length();
+
+  if (size as usize) < header_length {
+    return Err(VdfError::InvalidData(format!("App size {} is smaller than its {} byte header", size, header_length)));
+  }
+
+  let info_state = reader.read_uint32(true)?;
+  let last_updated = reader.read_uint32(true)?;
+  let pics_token = reader.read_uint64(true)?;
+
+  let mut text_sha1 = [0; 20];
+  text_sha1.copy_from_slice(reader.read_bytes(20)?);
+
+  let change_number = reader.read_uint32(true)?;
+
+  let mut binary_sha1 = [0; 20];
+  if version.has_binary_sha1() {
+    binary_sha1.copy_from_slice(reader.read_bytes(20)?);
+  }
+
+  return Ok(AppInfoHeader { appid, size, info_state, last_updated, pics_token, text_sha1, change_number, binary_sha1 });
+}
+
+/// Reads the header of each app section, and where its data is.
+/// If `partial` is true, an app that runs past the end of the file ends the list instead of failing, and its appid is returned alongside it.
+fn read_app_chunks(reader: &mut Reader, version: AppInfoVersion, string_table_offset: Option<i64>, partial: bool) -> Result<(Vec<AppInfoChunk>, Option<u32>), VdfError> {
+  let mut id = reader.read_uint32(true)?;
+  let eof = (string_table_offset.unwrap_or(i64::MAX) as usize).saturating_sub(4);
 
-  let filter_only_games = filter.unwrap_or(true);
   let mut chunks = Vec::new();
 
   while id != 0 && reader.get_offset() < eof {
-    let chunk_size = reader.read_uint32(true);
+    let context = EntryContext::new(Some(id), &[]);
+    let header_offset = reader.get_offset();
+
+    let header = match read_app_header(reader, id, version) {
+      Ok(header) => header,
+      Err(VdfError::UnexpectedEof { .. }) if partial => return Ok((chunks, Some(id))),
+      Err(err) => return Err(context.wrap(err, header_offset)),
+    };
+
     let offset = reader.get_offset();
-    let chunk_length: usize = chunk_size.try_into().unwrap();
+    let chunk_length: usize = header.size as usize - version.get_header_length();
+
+    if partial && offset + chunk_length > reader.get_data().len() {
+      return Ok((chunks, Some(id)));
+    }
 
-    chunks.push(AppInfoChunk { offset, length: chunk_length });
+    chunks.push(AppInfoChunk { header, offset, length: chunk_length });
 
+    //? If the next id can't be read, this app's chunk ran past the end of the file.
     reader.seek(offset + chunk_length, 0);
-    id = reader.read_uint32(true);
+    id = match reader.read_uint32(true) {
+      Ok(id) => id,
+      //? The app itself is whole here, but the file ends before the id that should follow it.
+      Err(_) if partial => return Ok((chunks, Some(id))),
+      Err(err) => return Err(context.wrap(err, offset)),
+    };
+  }
+
+  return Ok((chunks, None));
+}
+
+/// Writes the appinfo.vdf file, using the layout of its magic number.
+/// Each app's size and binary SHA-1 are recalculated from its data, and the rest of its header is written as is.
+/// In v29 files an app keeps its binary SHA-1 if it matches either hash `get_binary_sha1` accepts, and gets the hash of its data with keys inline otherwise.
+/// The text SHA-1 is kept, since it hashes Valve's text KeyValues formatting, which can't be reproduced exactly.
+pub fn write_appinfo_vdf(path: &PathBuf, appinfo: &AppInfoVdf) -> Result<(), VdfError> {
+  return write_file_atomic(path, | sink | write_appinfo(sink, appinfo));
+}
+
+/// Writes appinfo.vdf data to any sink, like a `Vec<u8>` or a `BufWriter`.
+pub fn write_appinfo<W: Write>(sink: W, appinfo: &AppInfoVdf) -> Result<(), VdfError> {
+  let version = appinfo.get_version()?;
+  let header_length = version.get_header_length();
+  let mut string_table = if version.has_string_table() { Some(StringTable::new()) } else { None };
+
+  //? Apps are serialized up front so the string table, and its offset, are known before anything is written.
+  let mut app_buffers: Vec<Vec<u8>> = Vec::with_capacity(appinfo.entries.len());
+
+  for entry in appinfo.entries.iter() {
+    let mut data_writer = Writer::new(Vec::new());
+    write_entry_map(&mut data_writer, &entry.data, string_table.as_mut())?;
+
+    app_buffers.push(data_writer.into_inner());
+  }
+
+  let mut writer = Writer::new(sink);
+
+  writer.write_uint32(appinfo.magic, true)?;
+  writer.write_uint32(appinfo.universe, true)?;
+
+  if string_table.is_some() {
+    //? magic, universe and the offset itself, then each app's id, size, header and data, then the terminating 0.
+    let table_offset = 16 + app_buffers.iter().map(| buffer | 8 + header_length + buffer.len()).sum::<usize>() + 4;
+    writer.write_int64(table_offset as i64, true)?;
   }
 
+  for (entry, data_buffer) in appinfo.entries.iter().zip(app_buffers.iter()) {
+    let header = &entry.header;
 
-  let entries: Vec<Value> = chunks.par_iter().filter_map(| chunk | {
-    let mut chunk_reader = reader.slice(chunk.offset, chunk.length);
-    chunk_reader.seek(60, 1);
+    //? Hashes that still match the app's data are kept, so apps that weren't changed are written byte for byte.
+    let binary_sha1 = if version.has_binary_sha1() {
+      let hashes = get_binary_sha1(version, data_buffer, &entry.data)?;
+      if hashes.contains(&header.binary_sha1) { header.binary_sha1 } else { hashes[0] }
+    } else {
+      header.binary_sha1
+    };
 
-    let mut entry: Map<String, Value> = read_entry_map(&mut chunk_reader, magic, strings);
+    let size = u32::try_from(header_length + data_buffer.len()).map_err(| _ | VdfError::InvalidData(format!("App {} is too large to write", header.appid)))?;
 
-    if entry.contains_key("appinfo") {
-      let appinfo_val: &Value = entry.get("appinfo").expect("Should have been able to get \"appinfo\".");
-      let appinfo = appinfo_val.as_object().expect("appinfo should have been an object.");
-      
-      entry = appinfo.clone();
+    writer.write_uint32(header.appid, true)?;
+    writer.write_uint32(size, true)?;
+    writer.write_uint32(header.info_state, true)?;
+    writer.write_uint32(header.last_updated, true)?;
+    writer.write_uint64(header.pics_token, true)?;
+    writer.write_bytes(&header.text_sha1)?;
+    writer.write_uint32(header.change_number, true)?;
+
+    if version.has_binary_sha1() {
+      writer.write_bytes(&binary_sha1)?;
     }
 
-    if filter_only_games && entry.contains_key("common") {
-      let common_val: &Value = entry.get("common").expect("Should have been able to get \"common\".");
-      let common = common_val.as_object().expect("Common should have been an object.");
-      
-      let type_val: &Value = common.get("type").expect("Should have been able to get \"common\".\"type\".");
-      let type_str: &str = type_val.as_str().expect("Should have been able to convert type to str");
+    writer.write_bytes(data_buffer)?;
+  }
 
-      if type_str == "Game" || type_str == "game" {
-        return Some(Value::Object(entry));
-      }
+  writer.write_uint32(0, true)?;
+
+  if let Some(string_table) = string_table {
+    string_table.write(&mut writer)?;
+  }
+
+  return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::kv_value::KvValue;
+
+  /// Builds an app's data, with the "appinfo" root Steam writes.
+  fn get_app_data(appid: u32, name: &str, app_type: &str) -> KvMap {
+    let mut common = KvMap::new();
+    common.push(String::from("name"), KvValue::String(name.to_owned()));
+    common.push(String::from("type"), KvValue::String(app_type.to_owned()));
+    common.push(String::from("gameid"), KvValue::UInt64(appid as u64));
+
+    let mut appinfo = KvMap::new();
+    appinfo.push(String::from("appid"), KvValue::Int32(appid as i32));
+    appinfo.push(String::from("common"), KvValue::Map(common));
+
+    let mut data = KvMap::new();
+    data.push(String::from("appinfo"), KvValue::Map(appinfo));
+
+    return data;
+  }
+
+  /// Builds a file with two apps, in the layout of the provided magic number.
+  fn get_appinfo(magic: u32) -> AppInfoVdf {
+    let entries = [(10, "Counter-Strike", "Game"), (20, "Team Fortress Classic", "Game")].iter().map(| (appid, name, app_type) | AppInfoEntry {
+      header: AppInfoHeader { appid: *appid, info_state: 2, last_updated: 1700000000, pics_token: 5, text_sha1: [*appid as u8; 20], change_number: 42, ..AppInfoHeader::default() },
+      data: get_app_data(*appid, name, app_type),
+    }).collect();
+
+    return AppInfoVdf { magic, universe: 1, entries };
+  }
+
+  /// Writes a file to bytes.
+  fn to_bytes(appinfo: &AppInfoVdf) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_appinfo(&mut bytes, appinfo).unwrap();
+
+    return bytes;
+  }
+
+  /// Writes a file, reads it back, and checks that it reads the same and rewrites to the same bytes.
+  fn check_round_trip(magic: u32) -> (Vec<u8>, AppInfoVdf) {
+    let appinfo = get_appinfo(magic);
+    let bytes = to_bytes(&appinfo);
+
+    let index = AppInfoIndex::from_bytes(bytes.clone()).unwrap();
+    assert_eq!(index.get_magic(), magic);
+    assert_eq!(index.get_universe(), 1);
+    assert_eq!(index.appids().collect::<Vec<u32>>(), vec![10, 20]);
+
+    let read = index.read_all().unwrap();
+
+    for (written, read) in appinfo.entries.iter().zip(read.entries.iter()) {
+      assert_eq!(read.data, written.data);
+      assert_eq!(read.header.text_sha1, written.header.text_sha1);
+      assert_eq!(read.header.change_number, written.header.change_number);
+      assert_eq!(read.header.pics_token, written.header.pics_token);
     }
 
-    if !filter_only_games {
-      return Some(Value::Object(entry));
+    assert_eq!(to_bytes(&read), bytes);
+
+    return (bytes, read);
+  }
+
+  /// Checks that each app's size is its header length plus the length of its data.
+  fn check_header_sizes(bytes: &[u8], appinfo: &AppInfoVdf, data_start: usize) {
+    let header_length = appinfo.get_version().unwrap().get_header_length();
+    let mut offset = data_start;
+
+    for entry in appinfo.entries.iter() {
+      let appid = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+      let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+
+      assert_eq!(appid, entry.header.appid);
+      assert_eq!(size, entry.header.size as usize);
+      assert!(size > header_length);
+
+      offset += 8 + size;
     }
 
-    return None;
-  }).collect();
+    assert_eq!(&bytes[offset..offset + 4], &[0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn header_lengths() {
+    assert_eq!(AppInfoVersion::V27.get_header_length(), 40);
+    assert_eq!(AppInfoVersion::V28.get_header_length(), 60);
+    assert_eq!(AppInfoVersion::V29.get_header_length(), 60);
+  }
+
+  #[test]
+  fn v27_round_trip() {
+    let (bytes, read) = check_round_trip(APPINFO_MAGIC_V27);
+
+    check_header_sizes(&bytes, &read, 8);
+    assert!(read.entries.iter().all(| entry | entry.header.binary_sha1 == [0; 20]));
+  }
+
+  #[test]
+  fn v28_round_trip() {
+    let (bytes, read) = check_round_trip(APPINFO_MAGIC_V28);
+
+    check_header_sizes(&bytes, &read, 8);
+    assert!(read.entries.iter().all(| entry | entry.header.binary_sha1 != [0; 20]));
+  }
+
+  #[test]
+  fn v29_round_trip() {
+    let (bytes, read) = check_round_trip(APPINFO_MAGIC_V29);
+
+    check_header_sizes(&bytes, &read, 16);
+  }
+
+  #[test]
+  fn v29_keys_are_written_to_the_string_table() {
+    let bytes = to_bytes(&get_appinfo(APPINFO_MAGIC_V29));
+
+    let table_offset = i64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+    let mut reader = Reader::new(&bytes);
+    reader.seek(table_offset, 0);
+
+    let string_count = reader.read_uint32(true).unwrap();
+    let strings: Vec<String> = (0..string_count).map(| _ | reader.read_string(None).unwrap()).collect();
+
+    //? Each key is only stored once, in the order it was first written, and values stay inline.
+    assert_eq!(strings, vec!["appinfo", "appid", "common", "name", "type", "gameid"]);
+    assert_eq!(reader.remaining(), 0);
+    assert!(!bytes[..table_offset].windows(7).any(| window | window == b"common\0"));
+    assert!(bytes[..table_offset].windows(15).any(| window | window == b"Counter-Strike\0"));
+  }
+
+  #[test]
+  fn v28_and_v29_read_the_same_data() {
+    let (_, v28) = check_round_trip(APPINFO_MAGIC_V28);
+    let (_, v29) = check_round_trip(APPINFO_MAGIC_V29);
+
+    for (v28_entry, v29_entry) in v28.entries.iter().zip(v29.entries.iter()) {
+      assert_eq!(v28_entry.data, v29_entry.data);
+      assert_ne!(v28_entry.header.size, v29_entry.header.size);
+    }
+  }
+
+  #[test]
+  fn rejects_unknown_versions() {
+    let mut appinfo = get_appinfo(APPINFO_MAGIC_V29);
+    appinfo.magic = 0x0756442A;
+
+    assert!(matches!(write_appinfo(Vec::new(), &appinfo), Err(VdfError::UnsupportedVersion(0x0756442A))));
+    assert!(matches!(AppInfoIndex::from_bytes(0x0756442Au32.to_le_bytes().to_vec()), Err(VdfError::UnsupportedVersion(0x0756442A))));
+  }
+
+  /// Gets the SHA-1 of each app's data as it is stored in the file.
+  fn get_stored_sha1s(bytes: &[u8]) -> Vec<[u8; 20]> {
+    let index = AppInfoIndex::from_bytes(bytes.to_vec()).unwrap();
+
+    return index.chunks.iter().map(| chunk | Sha1::digest(&index.data[chunk.offset..chunk.offset + chunk.length]).into()).collect();
+  }
+
+  #[test]
+  fn written_hashes_verify() {
+    for magic in [APPINFO_MAGIC_V27, APPINFO_MAGIC_V28, APPINFO_MAGIC_V29] {
+      let index = AppInfoIndex::from_bytes(to_bytes(&get_appinfo(magic))).unwrap();
+
+      assert!(index.verify().is_empty());
+    }
+  }
+
+  #[test]
+  fn v28_hashes_the_stored_data() {
+    let bytes = to_bytes(&get_appinfo(APPINFO_MAGIC_V28));
+    let read = AppInfoIndex::from_bytes(bytes.clone()).unwrap().read_all().unwrap();
+
+    let headers: Vec<[u8; 20]> = read.entries.iter().map(| entry | entry.header.binary_sha1).collect();
+    assert_eq!(headers, get_stored_sha1s(&bytes));
+  }
+
+  #[test]
+  fn v29_writes_the_inline_hash() {
+    let v28 = AppInfoIndex::from_bytes(to_bytes(&get_appinfo(APPINFO_MAGIC_V28))).unwrap().read_all().unwrap();
+    let v29_bytes = to_bytes(&get_appinfo(APPINFO_MAGIC_V29));
+    let v29 = AppInfoIndex::from_bytes(v29_bytes.clone()).unwrap().read_all().unwrap();
 
-  return entries;
+    for (v28_entry, v29_entry) in v28.entries.iter().zip(v29.entries.iter()) {
+      assert_eq!(v29_entry.header.binary_sha1, v28_entry.header.binary_sha1);
+    }
+
+    assert!(get_stored_sha1s(&v29_bytes).iter().zip(v29.entries.iter()).all(| (stored, entry) | *stored != entry.header.binary_sha1));
+  }
+
+  #[test]
+  fn v29_accepts_and_keeps_the_stored_hash() {
+    let bytes = to_bytes(&get_appinfo(APPINFO_MAGIC_V29));
+    let mut appinfo = AppInfoIndex::from_bytes(bytes.clone()).unwrap().read_all().unwrap();
+
+    for (entry, stored) in appinfo.entries.iter_mut().zip(get_stored_sha1s(&bytes)) {
+      entry.header.binary_sha1 = stored;
+    }
+
+    let rewritten = to_bytes(&appinfo);
+    assert!(AppInfoIndex::from_bytes(rewritten.clone()).unwrap().verify().is_empty());
+
+    let reread = AppInfoIndex::from_bytes(rewritten).unwrap().read_all().unwrap();
+    for (written, read) in appinfo.entries.iter().zip(reread.entries.iter()) {
+      assert_eq!(read.header.binary_sha1, written.header.binary_sha1);
+    }
+  }
+
+  #[test]
+  fn changed_data_is_a_checksum_mismatch() {
+    for magic in [APPINFO_MAGIC_V28, APPINFO_MAGIC_V29] {
+      let mut bytes = to_bytes(&get_appinfo(magic));
+      let name_offset = bytes.windows(14).position(| window | window == b"Counter-Strike").unwrap();
+      bytes[name_offset + 13] = b'f';
+
+      let index = AppInfoIndex::from_bytes(bytes).unwrap();
+      let read = index.read_all().unwrap();
+      let chunk = &index.chunks[0];
+      let expected = read.entries[0].header.binary_sha1;
+      let actual = get_binary_sha1(index.version, &index.data[chunk.offset..chunk.offset + chunk.length], &read.entries[0].data).unwrap()[0];
+
+      let problems = index.verify();
+      assert_eq!(problems.len(), 1);
+      assert!(matches!(problems[0], AppInfoProblem::ChecksumMismatch { appid: 10, expected: problem_expected, actual: problem_actual } if problem_expected == expected && problem_actual == actual));
+    }
+  }
+
+  #[test]
+  fn truncated_apps_are_problems() {
+    for magic in [APPINFO_MAGIC_V27, APPINFO_MAGIC_V28] {
+      let bytes = to_bytes(&get_appinfo(magic));
+      let name_offset = bytes.windows(21).position(| window | window == b"Team Fortress Classic").unwrap();
+
+      let index = AppInfoIndex::from_bytes_partial(bytes[..name_offset].to_vec()).unwrap();
+      let problems = index.verify();
+
+      assert_eq!(problems.len(), 1);
+      assert_eq!(problems[0].get_appid(), Some(20));
+      assert!(matches!(problems[0], AppInfoProblem::Truncated { appid: 20 }));
+    }
+  }
+
+  #[test]
+  fn v29_truncated_string_table_is_a_problem() {
+    let bytes = to_bytes(&get_appinfo(APPINFO_MAGIC_V29));
+    let table_offset = i64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+    //? The string count and "appinfo", then part of "appid".
+    let truncated = bytes[..table_offset + 4 + 8 + 2].to_vec();
+
+    assert!(matches!(AppInfoIndex::from_bytes(truncated.clone()), Err(VdfError::UnexpectedEof { .. })));
+
+    let index = AppInfoIndex::from_bytes_partial(truncated).unwrap();
+    assert_eq!(index.get_truncated(), None);
+
+    //? The apps use keys past the end of the table, so they can't be decoded to check their inline hash.
+    let mut problems = index.verify();
+    problems.sort_by_key(AppInfoProblem::get_appid);
+
+    assert_eq!(problems.len(), 3);
+    assert!(matches!(problems[0], AppInfoProblem::TruncatedStringTable { offset, strings_read: 1 } if offset == table_offset));
+    assert!(matches!(problems[1], AppInfoProblem::Corrupt { appid: 10, .. }));
+    assert!(matches!(problems[2], AppInfoProblem::Corrupt { appid: 20, .. }));
+  }
+
+  #[test]
+  fn v29_truncated_string_table_still_checks_stored_hashes() {
+    let bytes = to_bytes(&get_appinfo(APPINFO_MAGIC_V29));
+    let mut appinfo = AppInfoIndex::from_bytes(bytes.clone()).unwrap().read_all().unwrap();
+
+    for (entry, stored) in appinfo.entries.iter_mut().zip(get_stored_sha1s(&bytes)) {
+      entry.header.binary_sha1 = stored;
+    }
+
+    let mut rewritten = to_bytes(&appinfo);
+    let table_offset = i64::from_le_bytes(rewritten[8..16].try_into().unwrap()) as usize;
+    rewritten.truncate(table_offset + 2);
+
+    let problems = AppInfoIndex::from_bytes_partial(rewritten.clone()).unwrap().verify();
+    assert_eq!(problems.len(), 1);
+    assert!(matches!(problems[0], AppInfoProblem::TruncatedStringTable { strings_read: 0, .. }));
+    assert_eq!(problems[0].get_appid(), None);
+
+    let name_offset = rewritten.windows(14).position(| window | window == b"Counter-Strike").unwrap();
+    rewritten[name_offset + 13] = b'f';
+
+    let problems = AppInfoIndex::from_bytes_partial(rewritten).unwrap().verify();
+    assert_eq!(problems.len(), 2);
+    assert!(problems.iter().any(| problem | matches!(problem, AppInfoProblem::Corrupt { appid: 10, .. })));
+  }
+
+  #[test]
+  fn v29_truncated_app_is_a_problem() {
+    let bytes = to_bytes(&get_appinfo(APPINFO_MAGIC_V29));
+    let name_offset = bytes.windows(21).position(| window | window == b"Team Fortress Classic").unwrap();
+
+    let index = AppInfoIndex::from_bytes_partial(bytes[..name_offset].to_vec()).unwrap();
+    let mut problems = index.verify();
+    problems.sort_by_key(AppInfoProblem::get_appid);
+
+    //? The whole string table is past the end, so app 10 can't be decoded either.
+    assert_eq!(problems.len(), 3);
+    assert!(matches!(problems[0], AppInfoProblem::TruncatedStringTable { strings_read: 0, .. }));
+    assert!(matches!(problems[1], AppInfoProblem::Corrupt { appid: 10, .. }));
+    assert!(matches!(problems[2], AppInfoProblem::Truncated { appid: 20 }));
+  }
+
+  #[test]
+  fn type_filters_drop_apps_without_a_type() {
+    let mut appinfo = get_appinfo(APPINFO_MAGIC_V28);
+    appinfo.entries[1].data.get_path_mut("appinfo/common").and_then(KvValue::as_map_mut).unwrap().remove("type");
+    let bytes = to_bytes(&appinfo);
+
+    let games = parse_appinfo(&bytes[..], AppInfoFilter::games()).unwrap();
+    assert_eq!(games.iter().map(| app | app.get_u32_at("appid").unwrap()).collect::<Vec<u32>>(), vec![10]);
+
+    let all = parse_appinfo(&bytes[..], AppInfoFilter::all()).unwrap();
+    assert_eq!(all.len(), 2);
+
+    assert!(!AppInfoFilter::games().matches_type(None));
+    assert!(AppInfoFilter::games().matches_type(Some("game")));
+    assert!(AppInfoFilter::all().matches_type(None));
+  }
 }
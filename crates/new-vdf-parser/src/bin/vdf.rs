@@ -0,0 +1,223 @@
+use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::{env, fs, process};
+
+use serde_json::Value;
+
+use new_vdf_parser::diff::diff_vdf_files;
+use new_vdf_parser::appinfo_vdf_parser::{verify_appinfo_vdf, write_appinfo_vdf, AppInfoIndex, AppInfoVdf, APPINFO_MAGIC_V29};
+use new_vdf_parser::shortcuts_vdf_parser::{open_shortcuts_vdf, write_shortcuts_vdf};
+use new_vdf_parser::text_vdf_parser::{parse_text_vdf, to_text_vdf};
+use new_vdf_parser::{KvMap, KvValue, VdfError};
+
+const USAGE: &str = "Usage:
+  vdf dump <file> [--format json|text] [--appid <appid>]
+      Prints an appinfo.vdf or shortcuts.vdf file as JSON (the default) or text KeyValues.
+      --appid only prints the app, or shortcut, with that appid.
+
+  vdf convert <input> <output>
+      Converts a JSON or text KeyValues file back to binary.
+      .json files are read as JSON, and anything else as text KeyValues.
+      Text KeyValues only has strings, so every value from a text file is written as a string.
+      JSON from dumping a whole appinfo.vdf file is written as appinfo.vdf, and anything else like shortcuts.vdf.
+
+  vdf verify <file>
+      Checks each app in an appinfo.vdf file against its binary SHA-1, and that it can be decoded.
+      Prints the apps that are truncated, corrupt or don't match, and exits with 1 if there are any.
+
+  vdf diff <before> <after>
+      Compares two appinfo.vdf or shortcuts.vdf files, like a backup and the current file.
+      Prints the apps or shortcuts that were added, removed or changed, with the key paths and values that changed.
+      Exits with 1 if the files differ.";
+
+/// The output format of the dump command.
+#[derive(PartialEq)]
+enum Format {
+  Json,
+  Text,
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+
+  let res = match args.first().map(String::as_str) {
+    Some("dump") => dump(&args[1..]),
+    Some("convert") => convert(&args[1..]),
+    Some("verify") => verify(&args[1..]),
+    Some("diff") => diff(&args[1..]),
+    Some("help") | Some("--help") | Some("-h") => {
+      println!("{}", USAGE);
+      Ok(())
+    },
+    _ => Err(format!("Expected a command.\n\n{}", USAGE)),
+  };
+
+  if let Err(err) = res {
+    eprintln!("{}", err);
+    process::exit(1);
+  }
+}
+
+/// Dumps a binary vdf file as JSON or text.
+fn dump(args: &[String]) -> Result<(), String> {
+  let mut path: Option<PathBuf> = None;
+  let mut format = Format::Json;
+  let mut appid: Option<u32> = None;
+
+  let mut args_iter = args.iter();
+
+  while let Some(arg) = args_iter.next() {
+    match arg.as_str() {
+      "--format" => {
+        format = match args_iter.next().map(String::as_str) {
+          Some("json") => Format::Json,
+          Some("text") => Format::Text,
+          other => return Err(format!("Unknown format {:?}. Expected json or text.", other.unwrap_or_default())),
+        };
+      },
+      "--appid" => {
+        let appid_str = args_iter.next().ok_or("Expected an appid after --appid.")?;
+        appid = Some(appid_str.parse().map_err(| _ | format!("{} is not a valid appid.", appid_str))?);
+      },
+      _ if path.is_none() => path = Some(PathBuf::from(arg)),
+      _ => return Err(format!("Unexpected argument {}.\n\n{}", arg, USAGE)),
+    }
+  }
+
+  let path = path.ok_or(format!("Expected a file to dump.\n\n{}", USAGE))?;
+
+  let output = if is_appinfo(&path)? {
+    dump_appinfo(&path, &format, appid)
+  } else {
+    dump_shortcuts(&path, &format, appid)
+  };
+
+  let output = output.map_err(| err | err.to_string())?;
+
+  //? Writing fails when the output is piped to something like head, which isn't worth reporting.
+  let _ = writeln!(io::stdout(), "{}", output);
+  return Ok(());
+}
+
+/// Checks if a file starts with an appinfo.vdf magic number, of any version.
+/// Versions that aren't supported are still treated as appinfo.vdf, so reading them reports the version instead of failing as shortcuts.vdf.
+fn is_appinfo(path: &PathBuf) -> Result<bool, String> {
+  let mut file = fs::File::open(path).map_err(| err | format!("Failed to read {}: {}", path.display(), err))?;
+  let mut magic_bytes = [0; 4];
+
+  if file.read_exact(&mut magic_bytes).is_err() {
+    return Ok(false);
+  }
+
+  let magic = u32::from_le_bytes(magic_bytes);
+  return Ok(magic >> 8 == APPINFO_MAGIC_V29 >> 8);
+}
+
+/// Dumps an appinfo.vdf file, or one of its apps.
+fn dump_appinfo(path: &PathBuf, format: &Format, appid: Option<u32>) -> Result<String, VdfError> {
+  let index = AppInfoIndex::open(path)?;
+
+  if let Some(appid) = appid {
+    let entry = index.get(appid)?.ok_or(VdfError::InvalidData(format!("App {} is not in {}", appid, path.display())))?;
+
+    if *format == Format::Text {
+      return Ok(to_text_vdf(&entry.data));
+    }
+
+    let json = serde_json::json!({ "header": entry.header.to_json(), "data": entry.data.to_json() });
+    return Ok(serde_json::to_string_pretty(&json).unwrap_or_default());
+  }
+
+  let appinfo = index.read_all()?;
+
+  if *format == Format::Text {
+    return Ok(appinfo.entries.iter().map(| entry | to_text_vdf(&entry.data)).collect());
+  }
+
+  return Ok(serde_json::to_string_pretty(&appinfo.to_json()).unwrap_or_default());
+}
+
+/// Dumps a shortcuts.vdf file, or one of its shortcuts.
+fn dump_shortcuts(path: &PathBuf, format: &Format, appid: Option<u32>) -> Result<String, VdfError> {
+  let mut shortcuts_vdf = open_shortcuts_vdf(path)?;
+
+  if let Some(appid) = appid {
+    let shortcuts = shortcuts_vdf.get("shortcuts").and_then(KvValue::as_map).cloned().unwrap_or_default();
+
+    shortcuts_vdf = shortcuts.into_iter()
+      .filter(| (_, shortcut) | shortcut.as_map().and_then(| map | map.get("appid")).and_then(KvValue::as_u32) == Some(appid))
+      .collect();
+
+    if shortcuts_vdf.is_empty() {
+      return Err(VdfError::InvalidData(format!("Shortcut {} is not in {}", appid, path.display())));
+    }
+  }
+
+  if *format == Format::Text {
+    return Ok(to_text_vdf(&shortcuts_vdf));
+  }
+
+  return Ok(serde_json::to_string_pretty(&shortcuts_vdf).unwrap_or_default());
+}
+
+/// Converts a JSON or text file to binary.
+fn convert(args: &[String]) -> Result<(), String> {
+  let (input, output) = match args {
+    [input, output] => (PathBuf::from(input), PathBuf::from(output)),
+    _ => return Err(format!("Expected an input and output file.\n\n{}", USAGE)),
+  };
+
+  let contents = fs::read_to_string(&input).map_err(| err | format!("Failed to read {}: {}", input.display(), err))?;
+  let is_json = input.extension().is_some_and(| extension | extension.eq_ignore_ascii_case("json"));
+
+  let res = if is_json {
+    let json: Value = serde_json::from_str(&contents).map_err(| err | format!("Failed to parse {}: {}", input.display(), err))?;
+    let json_map = json.as_object().ok_or(format!("{} should have been a JSON object.", input.display()))?;
+
+    if json_map.contains_key("magic") && json_map.contains_key("apps") {
+      AppInfoVdf::from_json(json_map).and_then(| appinfo | write_appinfo_vdf(&output, &appinfo))
+    } else {
+      KvMap::from_json(json_map).and_then(| data | write_shortcuts_vdf(&output, &data))
+    }
+  } else {
+    parse_text_vdf(&contents, None, input.parent()).and_then(| data | write_shortcuts_vdf(&output, &data))
+  };
+
+  return res.map_err(| err | format!("Failed to convert {}: {}", input.display(), err));
+}
+
+/// Verifies the apps in an appinfo.vdf file.
+fn verify(args: &[String]) -> Result<(), String> {
+  let path = match args {
+    [path] => PathBuf::from(path),
+    _ => return Err(format!("Expected an appinfo.vdf file to verify.\n\n{}", USAGE)),
+  };
+
+  let problems = verify_appinfo_vdf(&path).map_err(| err | format!("Failed to verify {}: {}", path.display(), err))?;
+
+  if problems.is_empty() {
+    println!("{} is intact.", path.display());
+    return Ok(());
+  }
+
+  let lines: Vec<String> = problems.iter().map(| problem | problem.to_string()).collect();
+  return Err(format!("{}\n{} problem(s) found in {}.", lines.join("\n"), problems.len(), path.display()));
+}
+
+/// Compares two appinfo.vdf or shortcuts.vdf files.
+fn diff(args: &[String]) -> Result<(), String> {
+  let (before, after) = match args {
+    [before, after] => (PathBuf::from(before), PathBuf::from(after)),
+    _ => return Err(format!("Expected two files to compare.\n\n{}", USAGE)),
+  };
+
+  let vdf_diff = diff_vdf_files(&before, &after).map_err(| err | format!("Failed to compare {} and {}: {}", before.display(), after.display(), err))?;
+
+  if vdf_diff.is_empty() {
+    println!("{} and {} have the same contents.", before.display(), after.display());
+    return Ok(());
+  }
+
+  let _ = writeln!(io::stdout(), "{}", vdf_diff);
+  return Err(format!("{} app(s) or shortcut(s) differ.", vdf_diff.entries.len()));
+}
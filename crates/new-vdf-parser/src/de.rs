@@ -0,0 +1,399 @@
+use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+
+use crate::error::VdfError;
+use crate::kv_value::{KvMap, KvValue};
+use crate::reader::Reader;
+use crate::vdf_reader::{read_entry_map, EntryContext};
+
+impl de::Error for VdfError {
+  fn custom<T: std::fmt::Display>(msg: T) -> VdfError {
+    return VdfError::InvalidData(msg.to_string());
+  }
+}
+
+/// Deserializes a type from binary KeyValues, like the contents of shortcuts.vdf.
+/// The top level map is deserialized as `T`, so for shortcuts.vdf `T` should have a `shortcuts` field.
+pub fn from_slice<T: DeserializeOwned>(data: &[u8]) -> Result<T, VdfError> {
+  let mut reader = Reader::new(data);
+  let mut context = EntryContext::new(None, &[]);
+
+  let map = read_entry_map(&mut reader, None, None, &mut context)?;
+
+  return from_kv_map(&map);
+}
+
+/// Deserializes a type from a KvMap.
+pub fn from_kv_map<'a, T: Deserialize<'a>>(map: &'a KvMap) -> Result<T, VdfError> {
+  return T::deserialize(MapDeserializer { map });
+}
+
+/// Deserializes a type from a KvValue.
+pub fn from_kv_value<'a, T: Deserialize<'a>>(value: &'a KvValue) -> Result<T, VdfError> {
+  return T::deserialize(ValueDeserializer { value });
+}
+
+/// Deserializes a KvMap as a map, a struct, or a sequence of its values.
+struct MapDeserializer<'a> {
+  map: &'a KvMap,
+}
+
+impl<'de> de::Deserializer<'de> for MapDeserializer<'de> {
+  type Error = VdfError;
+
+  fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+    return visitor.visit_map(KvMapAccess { entries: Box::new(self.map.iter()), value: None });
+  }
+
+  //? Lists are written as maps with the keys "0", "1", "2"... so they are read back in order.
+  fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+    return visitor.visit_seq(KvSeqAccess { values: Box::new(self.map.values()) });
+  }
+
+  fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, VdfError> {
+    return self.deserialize_seq(visitor);
+  }
+
+  fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, VdfError> {
+    return self.deserialize_seq(visitor);
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+    return visitor.visit_some(self);
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, VdfError> {
+    return visitor.visit_newtype_struct(self);
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, VdfError> {
+    let mut entries = self.map.iter();
+
+    match (entries.next(), entries.next()) {
+      (Some((variant, value)), None) => return visitor.visit_enum(KvEnumAccess { variant, value: Some(value) }),
+      _ => return Err(VdfError::InvalidData(String::from("Expected a map with a single key for an enum"))),
+    }
+  }
+
+  serde::forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf unit unit_struct map struct identifier ignored_any
+  }
+}
+
+/// Deserializes a single KvValue.
+struct ValueDeserializer<'a> {
+  value: &'a KvValue,
+}
+
+impl ValueDeserializer<'_> {
+  /// Gets the value as an unsigned number. Int32 values are reinterpreted as unsigned, so appids with the high bit set fit in a u32.
+  fn as_unsigned(&self) -> Option<u64> {
+    match self.value {
+      KvValue::Int32(number) => return Some(*number as u32 as u64),
+      _ => return self.value.as_u64(),
+    }
+  }
+
+  /// Creates an error for a value that isn't the expected type.
+  fn invalid_type(&self, expected: &str) -> VdfError {
+    return VdfError::InvalidData(format!("Expected {} but got {:?}", expected, self.value.field_type()));
+  }
+}
+
+macro_rules! deserialize_signed {
+  ($method:ident, $visit:ident, $type:ty) => {
+    fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+      let number = self.value.as_i64().ok_or_else(|| self.invalid_type(stringify!($type)))?;
+      let number = <$type>::try_from(number).map_err(| _ | VdfError::InvalidData(format!("{} does not fit in {}", number, stringify!($type))))?;
+      return visitor.$visit(number);
+    }
+  };
+}
+
+macro_rules! deserialize_unsigned {
+  ($method:ident, $visit:ident, $type:ty) => {
+    fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+      let number = self.as_unsigned().ok_or_else(|| self.invalid_type(stringify!($type)))?;
+      let number = <$type>::try_from(number).map_err(| _ | VdfError::InvalidData(format!("{} does not fit in {}", number, stringify!($type))))?;
+      return visitor.$visit(number);
+    }
+  };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+  type Error = VdfError;
+
+  fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+    match self.value {
+      KvValue::Map(map) => return MapDeserializer { map }.deserialize_any(visitor),
+      KvValue::String(string) | KvValue::WideString(string) => return visitor.visit_borrowed_str(string),
+      KvValue::Float32(number) => return visitor.visit_f32(*number),
+      KvValue::Int32(number) => return visitor.visit_i32(*number),
+      KvValue::Int64(number) => return visitor.visit_i64(*number),
+      _ => return visitor.visit_u64(self.value.as_u64().unwrap_or_default()),
+    }
+  }
+
+  //? Steam stores booleans as Int32 0 or 1.
+  fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+    match self.value.as_i64() {
+      Some(number) => return visitor.visit_bool(number != 0),
+      None => return Err(self.invalid_type("bool")),
+    }
+  }
+
+  deserialize_signed!(deserialize_i8, visit_i8, i8);
+  deserialize_signed!(deserialize_i16, visit_i16, i16);
+  deserialize_signed!(deserialize_i32, visit_i32, i32);
+  deserialize_signed!(deserialize_i64, visit_i64, i64);
+  deserialize_unsigned!(deserialize_u8, visit_u8, u8);
+  deserialize_unsigned!(deserialize_u16, visit_u16, u16);
+  deserialize_unsigned!(deserialize_u32, visit_u32, u32);
+  deserialize_unsigned!(deserialize_u64, visit_u64, u64);
+
+  fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+    let number = self.value.as_f64().ok_or_else(|| self.invalid_type("f32"))?;
+    return visitor.visit_f32(number as f32);
+  }
+
+  fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+    let number = self.value.as_f64().ok_or_else(|| self.invalid_type("f64"))?;
+    return visitor.visit_f64(number);
+  }
+
+  fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+    match self.value {
+      KvValue::Map(map) => return MapDeserializer { map }.deserialize_seq(visitor),
+      _ => return Err(self.invalid_type("a map")),
+    }
+  }
+
+  fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, VdfError> {
+    return self.deserialize_seq(visitor);
+  }
+
+  fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, VdfError> {
+    return self.deserialize_seq(visitor);
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, VdfError> {
+    return visitor.visit_some(self);
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, VdfError> {
+    return visitor.visit_newtype_struct(self);
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, VdfError> {
+    match self.value {
+      KvValue::String(variant) | KvValue::WideString(variant) => return visitor.visit_enum(KvEnumAccess { variant, value: None }),
+      KvValue::Map(map) => return MapDeserializer { map }.deserialize_enum(name, variants, visitor),
+      _ => return Err(self.invalid_type("a string or map")),
+    }
+  }
+
+  serde::forward_to_deserialize_any! {
+    i128 u128 char str string bytes byte_buf unit unit_struct map struct identifier ignored_any
+  }
+}
+
+/// Visits the entries of a KvMap.
+struct KvMapAccess<'a> {
+  entries: Box<dyn Iterator<Item = (&'a String, &'a KvValue)> + 'a>,
+  value: Option<&'a KvValue>,
+}
+
+impl<'de> MapAccess<'de> for KvMapAccess<'de> {
+  type Error = VdfError;
+
+  fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, VdfError> {
+    match self.entries.next() {
+      Some((key, value)) => {
+        self.value = Some(value);
+        let key_deserializer: de::value::BorrowedStrDeserializer<VdfError> = de::value::BorrowedStrDeserializer::new(key);
+        return seed.deserialize(key_deserializer).map(Some);
+      },
+      None => return Ok(None),
+    }
+  }
+
+  fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, VdfError> {
+    let value = self.value.take().ok_or(VdfError::InvalidData(String::from("Value was requested before its key")))?;
+    return seed.deserialize(ValueDeserializer { value });
+  }
+}
+
+/// Visits the values of a KvMap in order.
+struct KvSeqAccess<'a> {
+  values: Box<dyn Iterator<Item = &'a KvValue> + 'a>,
+}
+
+impl<'de> SeqAccess<'de> for KvSeqAccess<'de> {
+  type Error = VdfError;
+
+  fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, VdfError> {
+    match self.values.next() {
+      Some(value) => return seed.deserialize(ValueDeserializer { value }).map(Some),
+      None => return Ok(None),
+    }
+  }
+}
+
+/// Visits an enum, written either as its variant name or as a map with the variant name as its only key.
+struct KvEnumAccess<'a> {
+  variant: &'a str,
+  value: Option<&'a KvValue>,
+}
+
+impl<'de> EnumAccess<'de> for KvEnumAccess<'de> {
+  type Error = VdfError;
+  type Variant = KvVariantAccess<'de>;
+
+  fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, KvVariantAccess<'de>), VdfError> {
+    let variant_deserializer: de::value::BorrowedStrDeserializer<VdfError> = de::value::BorrowedStrDeserializer::new(self.variant);
+    let variant = seed.deserialize(variant_deserializer)?;
+    return Ok((variant, KvVariantAccess { value: self.value }));
+  }
+}
+
+/// Visits the contents of an enum variant.
+struct KvVariantAccess<'a> {
+  value: Option<&'a KvValue>,
+}
+
+impl<'a> KvVariantAccess<'a> {
+  /// Gets the variant's value, if it has one.
+  fn get_value(&self) -> Result<&'a KvValue, VdfError> {
+    return self.value.ok_or(VdfError::InvalidData(String::from("Expected a map for an enum variant with data")));
+  }
+}
+
+impl<'de> VariantAccess<'de> for KvVariantAccess<'de> {
+  type Error = VdfError;
+
+  fn unit_variant(self) -> Result<(), VdfError> {
+    return Ok(());
+  }
+
+  fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, VdfError> {
+    let value = self.get_value()?;
+    return seed.deserialize(ValueDeserializer { value });
+  }
+
+  fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, VdfError> {
+    let value = self.get_value()?;
+    return de::Deserializer::deserialize_seq(ValueDeserializer { value }, visitor);
+  }
+
+  fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, VdfError> {
+    let value = self.get_value()?;
+    return de::Deserializer::deserialize_any(ValueDeserializer { value }, visitor);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::BTreeMap;
+
+  use serde::{Deserialize, Serialize};
+
+  use crate::ser::{to_kv_map, to_vec};
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  #[serde(rename_all = "PascalCase")]
+  struct Shortcut {
+    appid: u32,
+    app_name: String,
+    is_hidden: bool,
+    last_play_time: i32,
+    icon: Option<String>,
+    tags: Vec<String>,
+    size: u64,
+    scale: f32,
+    launch: Launch,
+  }
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct Launch {
+    options: BTreeMap<String, String>,
+    delay: Option<u8>,
+  }
+
+  #[derive(Debug, PartialEq, Serialize, Deserialize)]
+  struct ShortcutsVdf {
+    shortcuts: Vec<Shortcut>,
+  }
+
+  /// Builds a shortcut with every kind of field.
+  fn get_shortcut(app_name: &str) -> Shortcut {
+    return Shortcut {
+      appid: 3000000001,
+      app_name: app_name.to_owned(),
+      is_hidden: true,
+      last_play_time: -1,
+      icon: None,
+      tags: vec![String::from("Favorite"), String::from("Sandbox")],
+      size: 1 << 40,
+      scale: 1.5,
+      launch: Launch { options: BTreeMap::from([(String::from("fullscreen"), String::from("1"))]), delay: Some(5) },
+    };
+  }
+
+  #[test]
+  fn structs_round_trip() {
+    let mut shortcut = get_shortcut("Minecraft");
+    assert_eq!(from_slice::<Shortcut>(&to_vec(&shortcut).unwrap()).unwrap(), shortcut);
+
+    shortcut.icon = Some(String::from("icon.png"));
+    shortcut.launch.delay = None;
+    assert_eq!(from_slice::<Shortcut>(&to_vec(&shortcut).unwrap()).unwrap(), shortcut);
+  }
+
+  #[test]
+  fn lists_round_trip_as_indexed_maps() {
+    let shortcuts_vdf = ShortcutsVdf { shortcuts: vec![get_shortcut("Minecraft"), get_shortcut("Terraria")] };
+    let map = to_kv_map(&shortcuts_vdf).unwrap();
+
+    assert_eq!(map.get_path("shortcuts/1/AppName").and_then(KvValue::as_str), Some("Terraria"));
+    assert_eq!(from_slice::<ShortcutsVdf>(&to_vec(&shortcuts_vdf).unwrap()).unwrap(), shortcuts_vdf);
+  }
+
+  #[test]
+  fn missing_fields_are_errors() {
+    let mut map = to_kv_map(&get_shortcut("Minecraft")).unwrap();
+    map.remove("AppName");
+
+    let err = from_kv_map::<Shortcut>(&map).unwrap_err();
+    assert!(matches!(err, VdfError::InvalidData(message) if message.contains("AppName")));
+  }
+
+  #[test]
+  fn wrong_types_are_errors() {
+    let mut map = to_kv_map(&get_shortcut("Minecraft")).unwrap();
+    map.insert(String::from("Appid"), KvValue::String(String::from("3000000001")));
+    assert!(matches!(from_kv_map::<Shortcut>(&map), Err(VdfError::InvalidData(message)) if message == "Expected u32 but got String"));
+
+    let mut map = to_kv_map(&get_shortcut("Minecraft")).unwrap();
+    map.insert(String::from("Tags"), KvValue::Int32(1));
+    assert!(from_kv_map::<Shortcut>(&map).is_err());
+  }
+
+  #[test]
+  fn numbers_that_dont_fit_are_errors() {
+    let mut map = to_kv_map(&get_shortcut("Minecraft")).unwrap();
+    *map.get_path_mut("Launch/delay").unwrap() = KvValue::Int32(300);
+
+    assert!(matches!(from_kv_map::<Shortcut>(&map), Err(VdfError::InvalidData(message)) if message == "300 does not fit in u8"));
+  }
+
+  #[test]
+  fn int32_reads_as_signed_or_unsigned() {
+    let value = KvValue::Int32(-1);
+
+    assert_eq!(from_kv_value::<i32>(&value).unwrap(), -1);
+    assert_eq!(from_kv_value::<u32>(&value).unwrap(), u32::MAX);
+    assert_eq!(from_kv_value::<i64>(&value).unwrap(), -1);
+    assert!(from_kv_value::<bool>(&value).unwrap());
+  }
+}
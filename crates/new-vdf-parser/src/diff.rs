@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::appinfo_vdf_parser::{open_appinfo_vdf_raw, AppInfoVdf, APPINFO_MAGIC_V29};
+use crate::error::VdfError;
+use crate::kv_value::{KvMap, KvValue};
+use crate::shortcuts::get_shortcut_map_appid;
+use crate::shortcuts_vdf_parser::open_shortcuts_vdf;
+
+/// A change to a single key between two KeyValues maps. Paths are "/" separated, like `"common/library_assets/library_capsule"`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KvChange {
+  Added { path: String, value: KvValue },
+  Removed { path: String, value: KvValue },
+  /// The value, or its field type, changed. Maps are never reported as changed, their changed keys are reported instead.
+  Changed { path: String, before: KvValue, after: KvValue },
+}
+
+impl KvChange {
+  /// Gets the key path of the change.
+  pub fn get_path(&self) -> &str {
+    match self {
+      KvChange::Added { path, .. } | KvChange::Removed { path, .. } | KvChange::Changed { path, .. } => return path,
+    }
+  }
+}
+
+impl fmt::Display for KvChange {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      KvChange::Added { path, value } => write!(f, "+ {}: {}", path, format_value(value)),
+      KvChange::Removed { path, value } => write!(f, "- {}: {}", path, format_value(value)),
+      KvChange::Changed { path, before, after } => {
+        //? Values like Int32 1 and String "1" look the same, so show the field types when they are what changed.
+        if before.field_type() != after.field_type() {
+          write!(f, "~ {}: {} ({:?}) -> {} ({:?})", path, format_value(before), before.field_type(), format_value(after), after.field_type())
+        } else {
+          write!(f, "~ {}: {} -> {}", path, format_value(before), format_value(after))
+        }
+      },
+    }
+  }
+}
+
+/// How an app or shortcut differs between two files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+  Added,
+  Removed,
+  Changed,
+}
+
+/// An app or shortcut that was added, removed or changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryDiff {
+  pub appid: u32,
+  /// The app's name, or the shortcut's AppName, from the newer file if it has one.
+  pub name: Option<String>,
+  pub status: EntryStatus,
+  /// The changed keys, relative to the app's or shortcut's map. Empty for added and removed entries.
+  pub changes: Vec<KvChange>,
+}
+
+impl fmt::Display for EntryDiff {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let status = match self.status {
+      EntryStatus::Added => "Added",
+      EntryStatus::Removed => "Removed",
+      EntryStatus::Changed => "Changed",
+    };
+
+    match &self.name {
+      Some(name) => write!(f, "{} {} ({})", status, self.appid, name)?,
+      None => write!(f, "{} {}", status, self.appid)?,
+    }
+
+    for change in &self.changes {
+      write!(f, "\n  {}", change)?;
+    }
+
+    return Ok(());
+  }
+}
+
+/// The differences between two appinfo.vdf or shortcuts.vdf files, in the order of the newer file, followed by removed entries.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VdfDiff {
+  pub entries: Vec<EntryDiff>,
+}
+
+impl VdfDiff {
+  /// Checks if the files have the same apps or shortcuts, with the same data.
+  pub fn is_empty(&self) -> bool {
+    return self.entries.is_empty();
+  }
+
+  /// Gets the entries with the provided status.
+  pub fn with_status(&self, status: EntryStatus) -> impl Iterator<Item = &EntryDiff> {
+    return self.entries.iter().filter(move | entry | entry.status == status);
+  }
+}
+
+impl fmt::Display for VdfDiff {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, entry) in self.entries.iter().enumerate() {
+      if i > 0 {
+        writeln!(f)?;
+      }
+
+      write!(f, "{}", entry)?;
+    }
+
+    return Ok(());
+  }
+}
+
+/// Compares two KeyValues maps and returns the changed keys, in the order of the newer map, followed by removed keys.
+/// Key order is ignored. Duplicate keys are matched by the order they appear in.
+pub fn diff_kv_maps(before: &KvMap, after: &KvMap) -> Vec<KvChange> {
+  let mut changes = Vec::new();
+  diff_kv_maps_at("", before, after, &mut changes);
+
+  return changes;
+}
+
+/// Compares two maps at the provided key path, and adds their changes.
+fn diff_kv_maps_at(path: &str, before: &KvMap, after: &KvMap, changes: &mut Vec<KvChange>) {
+  let mut before_values: HashMap<&str, Vec<&KvValue>> = HashMap::new();
+  let mut after_counts: HashMap<&str, usize> = HashMap::new();
+
+  for (key, value) in before.iter() {
+    before_values.entry(key.as_str()).or_default().push(value);
+  }
+
+  //? The nth duplicate of a key is compared to the nth one in the other map.
+  for (key, after_value) in after.iter() {
+    let occurrence = after_counts.entry(key.as_str()).or_default();
+    let key_path = join_path(path, key);
+
+    match before_values.get(key.as_str()).and_then(| values | values.get(*occurrence)) {
+      Some(before_value) => diff_kv_values(key_path, before_value, after_value, changes),
+      None => changes.push(KvChange::Added { path: key_path, value: after_value.clone() }),
+    }
+
+    *occurrence += 1;
+  }
+
+  let mut before_counts: HashMap<&str, usize> = HashMap::new();
+
+  for (key, before_value) in before.iter() {
+    let occurrence = before_counts.entry(key.as_str()).or_default();
+
+    if *occurrence >= after_counts.get(key.as_str()).copied().unwrap_or_default() {
+      changes.push(KvChange::Removed { path: join_path(path, key), value: before_value.clone() });
+    }
+
+    *occurrence += 1;
+  }
+}
+
+/// Compares two values at the provided key path, and adds their changes.
+fn diff_kv_values(path: String, before: &KvValue, after: &KvValue, changes: &mut Vec<KvChange>) {
+  match (before, after) {
+    (KvValue::Map(before_map), KvValue::Map(after_map)) => diff_kv_maps_at(&path, before_map, after_map, changes),
+    _ if before != after => changes.push(KvChange::Changed { path, before: before.clone(), after: after.clone() }),
+    _ => {},
+  }
+}
+
+/// Compares the apps in two appinfo.vdf files. Apps are matched by appid, and their headers aren't compared.
+pub fn diff_appinfo(before: &AppInfoVdf, after: &AppInfoVdf) -> VdfDiff {
+  let before_apps: Vec<(u32, &KvMap)> = before.entries.iter().map(| entry | (entry.header.appid, &entry.data)).collect();
+  let after_apps: Vec<(u32, &KvMap)> = after.entries.iter().map(| entry | (entry.header.appid, &entry.data)).collect();
+
+  return diff_entries(&before_apps, &after_apps, get_app_name);
+}
+
+/// Compares the shortcuts in two shortcuts.vdf files, including their top level "shortcuts" maps.
+/// Shortcuts are matched by appid rather than by index, so removing one doesn't show every shortcut after it as changed.
+/// Shortcuts without an appid are matched by the one Steam computes for them, or 0 if they don't have an exe and name either.
+pub fn diff_shortcuts(before: &KvMap, after: &KvMap) -> VdfDiff {
+  let before_shortcuts = get_shortcuts(before);
+  let after_shortcuts = get_shortcuts(after);
+
+  return diff_entries(&before_shortcuts, &after_shortcuts, get_shortcut_name);
+}
+
+/// Opens two appinfo.vdf or shortcuts.vdf files and compares them. The type of each file is detected from its contents.
+pub fn diff_vdf_files(before_path: &PathBuf, after_path: &PathBuf) -> Result<VdfDiff, VdfError> {
+  let before_is_appinfo = is_appinfo_file(before_path)?;
+
+  if before_is_appinfo != is_appinfo_file(after_path)? {
+    return Err(VdfError::InvalidData(format!("{} and {} are different kinds of files", before_path.display(), after_path.display())));
+  }
+
+  if before_is_appinfo {
+    return Ok(diff_appinfo(&open_appinfo_vdf_raw(before_path)?, &open_appinfo_vdf_raw(after_path)?));
+  }
+
+  return Ok(diff_shortcuts(&open_shortcuts_vdf(before_path)?, &open_shortcuts_vdf(after_path)?));
+}
+
+/// Checks if a file starts with an appinfo.vdf magic number, of any version.
+fn is_appinfo_file(path: &PathBuf) -> Result<bool, VdfError> {
+  let mut file = fs::File::open(path)?;
+  let mut magic_bytes = [0; 4];
+
+  if file.read_exact(&mut magic_bytes).is_err() {
+    return Ok(false);
+  }
+
+  return Ok(u32::from_le_bytes(magic_bytes) >> 8 == APPINFO_MAGIC_V29 >> 8);
+}
+
+/// Compares two lists of entries matched by appid.
+fn diff_entries(before: &[(u32, &KvMap)], after: &[(u32, &KvMap)], get_name: fn(&KvMap) -> Option<String>) -> VdfDiff {
+  let before_by_appid: HashMap<u32, &KvMap> = before.iter().copied().collect();
+  let after_by_appid: HashMap<u32, &KvMap> = after.iter().copied().collect();
+
+  let mut entries = Vec::new();
+
+  for (appid, after_data) in after {
+    let name = get_name(after_data);
+
+    match before_by_appid.get(appid) {
+      Some(before_data) => {
+        let changes = diff_kv_maps(before_data, after_data);
+
+        if !changes.is_empty() {
+          entries.push(EntryDiff { appid: *appid, name, status: EntryStatus::Changed, changes });
+        }
+      },
+      None => entries.push(EntryDiff { appid: *appid, name, status: EntryStatus::Added, changes: Vec::new() }),
+    }
+  }
+
+  for (appid, before_data) in before {
+    if !after_by_appid.contains_key(appid) {
+      entries.push(EntryDiff { appid: *appid, name: get_name(before_data), status: EntryStatus::Removed, changes: Vec::new() });
+    }
+  }
+
+  return VdfDiff { entries };
+}
+
+/// Gets the shortcuts in a shortcuts.vdf file along with their appids.
+fn get_shortcuts(shortcuts_vdf: &KvMap) -> Vec<(u32, &KvMap)> {
+  let shortcuts = match shortcuts_vdf.get_ignore_case("shortcuts").and_then(KvValue::as_map) {
+    Some(shortcuts) => shortcuts,
+    None => return Vec::new(),
+  };
+
+  return shortcuts.values()
+    .filter_map(KvValue::as_map)
+    .map(| shortcut | (get_shortcut_map_appid(shortcut).unwrap_or_default(), shortcut))
+    .collect();
+}
+
+/// Gets the name of an app from its data.
+fn get_app_name(data: &KvMap) -> Option<String> {
+  let common = data.get("appinfo")?.as_map()?.get("common")?.as_map()?;
+
+  return common.get("name")?.as_str().map(str::to_owned);
+}
+
+/// Gets the name of a shortcut. Older shortcuts.vdf files use "appname" instead of "AppName".
+fn get_shortcut_name(shortcut: &KvMap) -> Option<String> {
+  return shortcut.get_ignore_case("AppName")?.as_str().map(str::to_owned);
+}
+
+/// Joins a key onto a key path.
+fn join_path(path: &str, key: &str) -> String {
+  if path.is_empty() {
+    return key.to_owned();
+  }
+
+  return format!("{}/{}", path, key);
+}
+
+/// Formats a value for a change, as compact JSON.
+fn format_value(value: &KvValue) -> String {
+  return serde_json::to_string(&value.to_json()).unwrap_or_default();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::appinfo_vdf_parser::{AppInfoEntry, AppInfoHeader};
+  use crate::shortcuts::get_shortcut_appid;
+
+  /// Builds a map from its entries, keeping duplicate keys.
+  fn map(entries: Vec<(&str, KvValue)>) -> KvMap {
+    return entries.into_iter().map(| (key, value) | (key.to_owned(), value)).collect();
+  }
+
+  /// Builds a string value.
+  fn string(value: &str) -> KvValue {
+    return KvValue::String(value.to_owned());
+  }
+
+  /// Builds an appinfo.vdf file with apps of the provided appids and names.
+  fn get_appinfo(apps: &[(u32, &str)]) -> AppInfoVdf {
+    let entries = apps.iter().map(| (appid, name) | AppInfoEntry {
+      header: AppInfoHeader { appid: *appid, ..AppInfoHeader::default() },
+      data: map(vec![("appinfo", KvValue::Map(map(vec![
+        ("appid", KvValue::Int32(*appid as i32)),
+        ("common", KvValue::Map(map(vec![("name", string(name))]))),
+      ])))]),
+    }).collect();
+
+    return AppInfoVdf { magic: APPINFO_MAGIC_V29, universe: 1, entries };
+  }
+
+  /// Builds a shortcuts.vdf file with shortcuts of the provided appids and names, indexed in order.
+  fn get_shortcuts_vdf(shortcuts: &[(u32, &str)]) -> KvMap {
+    let shortcuts = shortcuts.iter().enumerate().map(| (index, (appid, name)) | (index.to_string(), KvValue::Map(map(vec![
+      ("appid", KvValue::Int32(*appid as i32)),
+      ("AppName", string(name)),
+    ])))).collect();
+
+    return map(vec![("shortcuts", KvValue::Map(shortcuts))]);
+  }
+
+  #[test]
+  fn finds_added_removed_and_changed_keys() {
+    let before = map(vec![("name", string("Half-Life")), ("removed", KvValue::Int32(1)), ("nested", KvValue::Map(map(vec![("size", KvValue::Int32(10))])))]);
+    let after = map(vec![("nested", KvValue::Map(map(vec![("size", KvValue::Int32(20))]))), ("name", string("Half-Life")), ("added", KvValue::Int32(2))]);
+
+    assert_eq!(diff_kv_maps(&before, &after), vec![
+      KvChange::Changed { path: String::from("nested/size"), before: KvValue::Int32(10), after: KvValue::Int32(20) },
+      KvChange::Added { path: String::from("added"), value: KvValue::Int32(2) },
+      KvChange::Removed { path: String::from("removed"), value: KvValue::Int32(1) },
+    ]);
+    assert!(diff_kv_maps(&before, &before).is_empty());
+  }
+
+  #[test]
+  fn type_changes_are_changes() {
+    let before = map(vec![("size", KvValue::Int32(1))]);
+    let after = map(vec![("size", KvValue::UInt64(1))]);
+
+    let changes = diff_kv_maps(&before, &after);
+    assert_eq!(changes, vec![KvChange::Changed { path: String::from("size"), before: KvValue::Int32(1), after: KvValue::UInt64(1) }]);
+    assert_eq!(changes[0].to_string(), "~ size: 1 (Int32) -> 1 (UInt64)");
+  }
+
+  #[test]
+  fn duplicate_keys_are_matched_in_order() {
+    let before = map(vec![("include", string("a")), ("include", string("b"))]);
+    let after = map(vec![("include", string("a")), ("include", string("c")), ("include", string("d"))]);
+
+    assert_eq!(diff_kv_maps(&before, &after), vec![
+      KvChange::Changed { path: String::from("include"), before: string("b"), after: string("c") },
+      KvChange::Added { path: String::from("include"), value: string("d") },
+    ]);
+    assert_eq!(diff_kv_maps(&after, &before), vec![
+      KvChange::Changed { path: String::from("include"), before: string("c"), after: string("b") },
+      KvChange::Removed { path: String::from("include"), value: string("d") },
+    ]);
+  }
+
+  #[test]
+  fn apps_are_matched_by_appid() {
+    let before = get_appinfo(&[(10, "Counter-Strike"), (20, "Team Fortress Classic"), (30, "Day of Defeat")]);
+    //? Reordering apps isn't a change.
+    let after = get_appinfo(&[(40, "Deathmatch Classic"), (30, "Day of Defeat"), (10, "Counter-Strike: Source")]);
+
+    let diff = diff_appinfo(&before, &after);
+    assert_eq!(diff.entries.iter().map(| entry | (entry.appid, entry.status)).collect::<Vec<(u32, EntryStatus)>>(), vec![
+      (40, EntryStatus::Added),
+      (10, EntryStatus::Changed),
+      (20, EntryStatus::Removed),
+    ]);
+
+    let changed = diff.with_status(EntryStatus::Changed).next().unwrap();
+    assert_eq!(changed.name.as_deref(), Some("Counter-Strike: Source"));
+    assert_eq!(changed.changes, vec![KvChange::Changed { path: String::from("appinfo/common/name"), before: string("Counter-Strike"), after: string("Counter-Strike: Source") }]);
+    assert_eq!(diff.with_status(EntryStatus::Removed).next().unwrap().name.as_deref(), Some("Team Fortress Classic"));
+    assert!(diff_appinfo(&before, &before).is_empty());
+  }
+
+  #[test]
+  fn shortcuts_are_matched_by_appid() {
+    let before = get_shortcuts_vdf(&[(3000000001, "Minecraft"), (3000000002, "Terraria"), (3000000003, "Factorio")]);
+    //? Removing a shortcut renumbers the ones after it, which shouldn't show them as changed.
+    let after = get_shortcuts_vdf(&[(3000000001, "Minecraft"), (3000000003, "Factorio (GOG)")]);
+
+    let diff = diff_shortcuts(&before, &after);
+    assert_eq!(diff.entries.len(), 2);
+    assert_eq!(diff.entries[0].appid, 3000000003);
+    assert_eq!(diff.entries[0].status, EntryStatus::Changed);
+    assert_eq!(diff.entries[0].changes, vec![KvChange::Changed { path: String::from("AppName"), before: string("Factorio"), after: string("Factorio (GOG)") }]);
+    assert_eq!(diff.entries[1].appid, 3000000002);
+    assert_eq!(diff.entries[1].status, EntryStatus::Removed);
+    assert_eq!(diff.entries[1].name.as_deref(), Some("Terraria"));
+
+    assert_eq!(diff.to_string(), "Changed 3000000003 (Factorio (GOG))\n  ~ AppName: \"Factorio\" -> \"Factorio (GOG)\"\nRemoved 3000000002 (Terraria)");
+  }
+
+  #[test]
+  fn shortcuts_without_an_appid_are_matched_by_the_computed_one() {
+    let mut older = KvMap::new();
+    older.push(String::from("appname"), string("Minecraft"));
+    older.push(String::from("exe"), string("\"/usr/bin/minecraft\""));
+    let before = map(vec![("shortcuts", KvValue::Map(map(vec![("0", KvValue::Map(older.clone()))])))]);
+
+    older.push(String::from("LaunchOptions"), string("-fullscreen"));
+    let after = map(vec![("shortcuts", KvValue::Map(map(vec![("0", KvValue::Map(older))])))]);
+
+    let diff = diff_shortcuts(&before, &after);
+    assert_eq!(diff.entries.len(), 1);
+    assert_eq!(diff.entries[0].appid, get_shortcut_appid("\"/usr/bin/minecraft\"", "Minecraft"));
+    assert_eq!(diff.entries[0].status, EntryStatus::Changed);
+  }
+
+  #[test]
+  fn shortcuts_without_a_shortcuts_map_are_empty() {
+    let after = get_shortcuts_vdf(&[(3000000001, "Minecraft")]);
+
+    let diff = diff_shortcuts(&KvMap::new(), &after);
+    assert_eq!(diff.entries.len(), 1);
+    assert_eq!(diff.entries[0].status, EntryStatus::Added);
+  }
+}
@@ -0,0 +1,123 @@
+use std::{fmt, io};
+
+use serde::Serialize;
+
+use crate::field_type::FieldType;
+
+/// Errors that can occur while reading or writing VDF files.
+#[derive(Debug)]
+pub enum VdfError {
+  /// An IO error occured while opening, reading or writing a file.
+  Io(io::Error),
+  /// A read needed more bytes than were left in the buffer.
+  UnexpectedEof { offset: usize, needed: usize },
+  /// The file started with a magic header that isn't supported.
+  UnknownMagic(u32),
+  /// The file is an appinfo.vdf or packageinfo.vdf file, but its version is newer or older than the ones that are supported.
+  UnsupportedVersion(u32),
+  /// An entry field had a type byte that isn't supported.
+  UnexpectedFieldType(u8),
+  /// The shortcuts.vdf file didn't start with the "shortcuts" header.
+  InvalidShortcutsHeader(String),
+  /// A key referenced an index outside of the appinfo.vdf string table.
+  InvalidStringIndex(usize),
+  /// The data was not in the expected shape.
+  InvalidData(String),
+  /// A key path didn't match any value.
+  MissingKey(String),
+  /// The value at a key path wasn't of the expected type.
+  UnexpectedValueType { path: String, expected: &'static str, actual: FieldType },
+  /// Shortcuts data didn't have the shape Steam expects, so it wasn't written.
+  InvalidShortcuts(Vec<ShortcutsProblem>),
+  /// A text KeyValues file couldn't be parsed. Records the 1 based line and column where parsing failed.
+  Syntax { line: usize, column: usize, message: String },
+  /// An error occured while parsing an entry. Records the byte offset of the field being read, the appid of the app being parsed (if any), and the key path that led to it.
+  Entry { offset: usize, appid: Option<u32>, path: String, source: Box<VdfError> },
+}
+
+impl fmt::Display for VdfError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      VdfError::Io(err) => write!(f, "IO error: {}", err),
+      VdfError::UnexpectedEof { offset, needed } => write!(f, "Unexpected end of data: needed {} byte(s) at offset {:#x}", needed, offset),
+      VdfError::UnknownMagic(magic) => write!(f, "Magic header is unknown. Expected 0x07564427 to 0x07564429 for appinfo.vdf, or 0x06565527 to 0x06565528 for packageinfo.vdf, but got {:#010x}", magic),
+      VdfError::UnsupportedVersion(magic) => {
+        let (file_name, supported) = if magic >> 8 == 0x065655 { ("packageinfo.vdf", "0x27 and 0x28") } else { ("appinfo.vdf", "0x27, 0x28 and 0x29") };
+        write!(f, "Unsupported {} version {:#04x} (magic {:#010x}). Supported versions are {}", file_name, magic & 0xFF, magic, supported)
+      },
+      VdfError::UnexpectedFieldType(field_type) => write!(f, "Unexpected field type {}!", field_type),
+      VdfError::InvalidShortcutsHeader(header) => write!(f, "Invalid Shortcuts File! File started with {} instead of \"shortcuts\"", header),
+      VdfError::InvalidStringIndex(index) => write!(f, "String table index {} is out of range", index),
+      VdfError::InvalidData(message) => write!(f, "Invalid data: {}", message),
+      VdfError::MissingKey(path) => write!(f, "Key path \"{}\" was not found", path),
+      VdfError::UnexpectedValueType { path, expected, actual } => write!(f, "Expected \"{}\" to be {}, but it was {:?}", path, expected, actual),
+      VdfError::InvalidShortcuts(problems) => {
+        let messages: Vec<String> = problems.iter().map(| problem | problem.to_string()).collect();
+        write!(f, "Invalid shortcuts: {}", messages.join("; "))
+      },
+      VdfError::Syntax { line, column, message } => write!(f, "Syntax error at line {}, column {}: {}", line, column, message),
+      VdfError::Entry { offset, appid, path, source } => {
+        write!(f, "Error at offset {:#x}", offset)?;
+
+        if let Some(appid) = appid {
+          write!(f, " in app {}", appid)?;
+        }
+        if !path.is_empty() {
+          write!(f, " at \"{}\"", path)?;
+        }
+
+        write!(f, ": {}", source)
+      },
+    }
+  }
+}
+
+impl std::error::Error for VdfError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      VdfError::Io(err) => Some(err),
+      VdfError::Entry { source, .. } => Some(source.as_ref()),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for VdfError {
+  fn from(err: io::Error) -> VdfError {
+    return VdfError::Io(err);
+  }
+}
+
+/// A problem found while validating shortcuts.vdf data. Steam silently discards files with any of these.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ShortcutsProblem {
+  /// The data has no top level "shortcuts" map.
+  MissingShortcuts,
+  /// The data has a top level key other than "shortcuts".
+  UnexpectedKey { key: String },
+  /// A shortcut isn't a map.
+  NotAMap { index: String },
+  /// A shortcut's index isn't the next number in the sequence.
+  UnexpectedIndex { index: String, expected: String },
+  /// A shortcut is missing a required key.
+  MissingKey { index: String, key: String },
+  /// A shortcut's key, or one of its tags, has the wrong type.
+  WrongType { index: String, key: String, expected: String, actual: String },
+  /// A shortcut has the same appid as an earlier one.
+  DuplicateAppid { index: String, appid: u32, original: String },
+}
+
+impl fmt::Display for ShortcutsProblem {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ShortcutsProblem::MissingShortcuts => write!(f, "There is no top level \"shortcuts\" map"),
+      ShortcutsProblem::UnexpectedKey { key } => write!(f, "Unexpected top level key \"{}\"", key),
+      ShortcutsProblem::NotAMap { index } => write!(f, "Shortcut {} is not a map", index),
+      ShortcutsProblem::UnexpectedIndex { index, expected } => write!(f, "Shortcut {} should have index {}", index, expected),
+      ShortcutsProblem::MissingKey { index, key } => write!(f, "Shortcut {} is missing \"{}\"", index, key),
+      ShortcutsProblem::WrongType { index, key, expected, actual } => write!(f, "Shortcut {} has \"{}\" as {} instead of {}", index, key, actual, expected),
+      ShortcutsProblem::DuplicateAppid { index, appid, original } => write!(f, "Shortcut {} has the same appid {} as shortcut {}", index, appid, original),
+    }
+  }
+}
@@ -0,0 +1,47 @@
+use crate::error::VdfError;
+
+/// The type bytes that prefix each field in binary KeyValues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+  Map = 0x00,
+  String = 0x01,
+  Int32 = 0x02,
+  Float32 = 0x03,
+  Pointer = 0x04,
+  WideString = 0x05,
+  Color = 0x06,
+  UInt64 = 0x07,
+  End = 0x08,
+  Int64 = 0x0A,
+  AlternateEnd = 0x0B,
+}
+
+impl FieldType {
+  /// Gets the FieldType for a type byte.
+  pub fn from_byte(byte: u8) -> Result<FieldType, VdfError> {
+    match byte {
+      0x00 => return Ok(FieldType::Map),
+      0x01 => return Ok(FieldType::String),
+      0x02 => return Ok(FieldType::Int32),
+      0x03 => return Ok(FieldType::Float32),
+      0x04 => return Ok(FieldType::Pointer),
+      0x05 => return Ok(FieldType::WideString),
+      0x06 => return Ok(FieldType::Color),
+      0x07 => return Ok(FieldType::UInt64),
+      0x08 => return Ok(FieldType::End),
+      0x0A => return Ok(FieldType::Int64),
+      0x0B => return Ok(FieldType::AlternateEnd),
+      _ => return Err(VdfError::UnexpectedFieldType(byte)),
+    }
+  }
+
+  /// Gets the type byte for this FieldType.
+  pub fn to_byte(self) -> u8 {
+    return self as u8;
+  }
+
+  /// Checks if this FieldType marks the end of a map.
+  pub fn is_end(self) -> bool {
+    return self == FieldType::End || self == FieldType::AlternateEnd;
+  }
+}
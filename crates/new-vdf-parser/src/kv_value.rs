@@ -0,0 +1,399 @@
+use serde::ser::{Serialize, Serializer, SerializeMap};
+use serde_json::{Value, Map, Number};
+
+use crate::error::VdfError;
+use crate::field_type::FieldType;
+
+/// A KeyValues value that keeps the exact field type it was read with, so it can be written back unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KvValue {
+  Map(KvMap),
+  String(String),
+  Int32(i32),
+  Float32(f32),
+  Pointer(u32),
+  WideString(String),
+  Color(u32),
+  UInt64(u64),
+  Int64(i64),
+}
+
+/// An ordered KeyValues map. Keys stay in the order they were inserted, and duplicate keys are allowed like in Valve's formats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KvMap {
+  entries: Vec<(String, KvValue)>,
+  /// The type byte that closed the map in binary KeyValues. Some files close maps with `AlternateEnd` instead of `End`.
+  end_type: FieldType,
+}
+
+impl Default for KvMap {
+  fn default() -> KvMap {
+    return KvMap::new();
+  }
+}
+
+impl KvMap {
+  /// Creates a new, empty KvMap.
+  pub fn new() -> KvMap {
+    return KvMap { entries: Vec::new(), end_type: FieldType::End };
+  }
+
+  /// Gets the type byte that closes the map in binary KeyValues.
+  pub fn get_end_type(&self) -> FieldType {
+    return self.end_type;
+  }
+
+  /// Sets the type byte that closes the map in binary KeyValues. Only `End` and `AlternateEnd` can close a map.
+  pub fn set_end_type(&mut self, end_type: FieldType) -> Result<(), VdfError> {
+    if !end_type.is_end() {
+      return Err(VdfError::UnexpectedFieldType(end_type.to_byte()));
+    }
+
+    self.end_type = end_type;
+    return Ok(());
+  }
+
+  /// Gets the number of entries in the map.
+  pub fn len(&self) -> usize { return self.entries.len(); }
+  /// Checks if the map has no entries.
+  pub fn is_empty(&self) -> bool { return self.entries.is_empty(); }
+
+  /// Gets the first value with the provided key.
+  pub fn get(&self, key: &str) -> Option<&KvValue> {
+    return self.entries.iter().find(| (entry_key, _) | entry_key == key).map(| (_, value) | value);
+  }
+
+  /// Gets the first value with the provided key, ignoring ASCII case like Valve's text KeyValues do.
+  pub fn get_ignore_case(&self, key: &str) -> Option<&KvValue> {
+    return self.entries.iter().find(| (entry_key, _) | entry_key.eq_ignore_ascii_case(key)).map(| (_, value) | value);
+  }
+
+  /// Gets a mutable reference to the first value with the provided key.
+  pub fn get_mut(&mut self, key: &str) -> Option<&mut KvValue> {
+    return self.entries.iter_mut().find(| (entry_key, _) | entry_key == key).map(| (_, value) | value);
+  }
+
+  /// Checks if the map has an entry with the provided key.
+  pub fn contains_key(&self, key: &str) -> bool {
+    return self.get(key).is_some();
+  }
+
+  /// Sets the value of the first entry with the provided key in place, or appends it if the key is new. Returns the previous value, if any.
+  pub fn insert(&mut self, key: String, value: KvValue) -> Option<KvValue> {
+    match self.get_mut(&key) {
+      Some(existing) => return Some(std::mem::replace(existing, value)),
+      None => {
+        self.entries.push((key, value));
+        return None;
+      }
+    }
+  }
+
+  /// Appends an entry, even if an entry with the same key already exists.
+  pub fn push(&mut self, key: String, value: KvValue) {
+    self.entries.push((key, value));
+  }
+
+  /// Removes the first entry with the provided key and returns its value.
+  pub fn remove(&mut self, key: &str) -> Option<KvValue> {
+    let index = self.entries.iter().position(| (entry_key, _) | entry_key == key)?;
+    return Some(self.entries.remove(index).1);
+  }
+
+  /// Keeps only the entries that match the predicate.
+  pub fn retain<F: FnMut(&str, &KvValue) -> bool>(&mut self, mut predicate: F) {
+    self.entries.retain(| (key, value) | predicate(key, value));
+  }
+
+  /// Iterates over the entries in order.
+  pub fn iter(&self) -> impl Iterator<Item = (&String, &KvValue)> {
+    return self.entries.iter().map(| (key, value) | (key, value));
+  }
+
+  /// Iterates mutably over the entries in order.
+  pub fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut KvValue)> {
+    return self.entries.iter_mut().map(| (key, value) | (&*key, value));
+  }
+
+  /// Iterates over the keys in order.
+  pub fn keys(&self) -> impl Iterator<Item = &String> {
+    return self.entries.iter().map(| (key, _) | key);
+  }
+
+  /// Iterates over the values in order.
+  pub fn values(&self) -> impl Iterator<Item = &KvValue> {
+    return self.entries.iter().map(| (_, value) | value);
+  }
+
+  /// Converts the map to a JSON object.
+  pub fn to_json(&self) -> Map<String, Value> {
+    return self.iter().map(| (key, value) | (key.to_owned(), value.to_json())).collect();
+  }
+
+  /// Converts a JSON object to a KvMap. See `KvValue::from_json` for how types are chosen.
+  pub fn from_json(map: &Map<String, Value>) -> Result<KvMap, VdfError> {
+    return KvMap::from_json_at("", map, None);
+  }
+
+  /// Converts a JSON object to a KvMap, taking field types and end markers from `types` where it has a value at the same key path.
+  /// JSON loses which binary type each number had, so converting a map read from a file to JSON and back with the file's map as `types` gives back the same bytes.
+  /// Values `types` doesn't have, or that don't fit its type, are converted like `from_json` does.
+  pub fn from_json_with_types(map: &Map<String, Value>, types: &KvMap) -> Result<KvMap, VdfError> {
+    return KvMap::from_json_at("", map, Some(types));
+  }
+
+  /// Converts a JSON object at the provided key path to a KvMap.
+  fn from_json_at(path: &str, map: &Map<String, Value>, types: Option<&KvMap>) -> Result<KvMap, VdfError> {
+    let mut kv_map = KvMap::new();
+
+    if let Some(types) = types {
+      kv_map.end_type = types.end_type;
+    }
+
+    for (key, value) in map.iter() {
+      let key_path = if path.is_empty() { key.to_owned() } else { format!("{}/{}", path, key) };
+      let key_type = types.and_then(| types | types.get(key));
+
+      kv_map.push(key.to_owned(), KvValue::from_json_at(&key_path, value, key_type)?);
+    }
+
+    return Ok(kv_map);
+  }
+}
+
+impl IntoIterator for KvMap {
+  type Item = (String, KvValue);
+  type IntoIter = std::vec::IntoIter<(String, KvValue)>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    return self.entries.into_iter();
+  }
+}
+
+impl FromIterator<(String, KvValue)> for KvMap {
+  fn from_iter<T: IntoIterator<Item = (String, KvValue)>>(iter: T) -> KvMap {
+    return KvMap { entries: iter.into_iter().collect(), end_type: FieldType::End };
+  }
+}
+
+impl KvValue {
+  /// Gets the binary field type of this value.
+  pub fn field_type(&self) -> FieldType {
+    match self {
+      KvValue::Map(_) => return FieldType::Map,
+      KvValue::String(_) => return FieldType::String,
+      KvValue::Int32(_) => return FieldType::Int32,
+      KvValue::Float32(_) => return FieldType::Float32,
+      KvValue::Pointer(_) => return FieldType::Pointer,
+      KvValue::WideString(_) => return FieldType::WideString,
+      KvValue::Color(_) => return FieldType::Color,
+      KvValue::UInt64(_) => return FieldType::UInt64,
+      KvValue::Int64(_) => return FieldType::Int64,
+    }
+  }
+
+  /// Gets the value as a map, if it is one.
+  pub fn as_map(&self) -> Option<&KvMap> {
+    match self {
+      KvValue::Map(map) => return Some(map),
+      _ => return None,
+    }
+  }
+
+  /// Gets the value as a mutable map, if it is one.
+  pub fn as_map_mut(&mut self) -> Option<&mut KvMap> {
+    match self {
+      KvValue::Map(map) => return Some(map),
+      _ => return None,
+    }
+  }
+
+  /// Gets the value as a str, if it is a string or wide string.
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      KvValue::String(string) | KvValue::WideString(string) => return Some(string),
+      _ => return None,
+    }
+  }
+
+  /// Gets the value as an i64, if it is an integer that fits.
+  pub fn as_i64(&self) -> Option<i64> {
+    match self {
+      KvValue::Int32(number) => return Some(*number as i64),
+      KvValue::Pointer(number) | KvValue::Color(number) => return Some(*number as i64),
+      KvValue::UInt64(number) => return i64::try_from(*number).ok(),
+      KvValue::Int64(number) => return Some(*number),
+      _ => return None,
+    }
+  }
+
+  /// Gets the value as a u64, if it is a non negative integer.
+  pub fn as_u64(&self) -> Option<u64> {
+    match self {
+      KvValue::Int32(number) => return u64::try_from(*number).ok(),
+      KvValue::Pointer(number) | KvValue::Color(number) => return Some(*number as u64),
+      KvValue::UInt64(number) => return Some(*number),
+      KvValue::Int64(number) => return u64::try_from(*number).ok(),
+      _ => return None,
+    }
+  }
+
+  /// Gets the value as a u32, like an appid, if it is an integer that fits.
+  /// Int32 values are reinterpreted as unsigned, since Steam stores appids in them, and shortcut appids always have the high bit set.
+  pub fn as_u32(&self) -> Option<u32> {
+    match self {
+      KvValue::Int32(number) => return Some(*number as u32),
+      KvValue::Pointer(number) | KvValue::Color(number) => return Some(*number),
+      KvValue::UInt64(number) => return u32::try_from(*number).ok(),
+      KvValue::Int64(number) => return u32::try_from(*number).ok(),
+      _ => return None,
+    }
+  }
+
+  /// Gets the value as an f64, if it is a number.
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      KvValue::Float32(number) => return Some(*number as f64),
+      KvValue::Int64(number) => return Some(*number as f64),
+      KvValue::Int32(number) => return Some(*number as f64),
+      _ => return self.as_u64().map(| number | number as f64),
+    }
+  }
+
+  /// Converts the value to JSON.
+  /// Int32 values are written signed, like text KeyValues writes them. Use `as_u32` for the ones that hold appids.
+  pub fn to_json(&self) -> Value {
+    match self {
+      KvValue::Map(map) => return Value::Object(map.to_json()),
+      KvValue::String(string) | KvValue::WideString(string) => return Value::String(string.to_owned()),
+      KvValue::Float32(number) => return Number::from_f64(*number as f64).map(Value::Number).unwrap_or(Value::Null),
+      KvValue::Int32(number) => return Value::Number((*number).into()),
+      KvValue::Int64(number) => return Value::Number((*number).into()),
+      _ => return Value::Number(self.as_u64().unwrap_or_default().into()),
+    }
+  }
+
+  /// Converts JSON to a KvValue.
+  /// Whole numbers become Int32 if they fit in an i32 or u32, and UInt64 or Int64 otherwise. Other numbers become Float32.
+  pub fn from_json(value: &Value) -> Result<KvValue, VdfError> {
+    return KvValue::from_json_at("", value, None);
+  }
+
+  /// Converts JSON at the provided key path to a KvValue, keeping the field type of `existing` if the value fits in it.
+  fn from_json_at(path: &str, value: &Value, existing: Option<&KvValue>) -> Result<KvValue, VdfError> {
+    match (value, existing) {
+      (Value::Object(map), _) => return Ok(KvValue::Map(KvMap::from_json_at(path, map, existing.and_then(KvValue::as_map))?)),
+      (Value::String(string), Some(KvValue::WideString(_))) => return Ok(KvValue::WideString(string.to_owned())),
+      (Value::Number(number), Some(existing)) => {
+        if let Some(typed) = KvValue::number_with_type(number, existing.field_type()) {
+          return Ok(typed);
+        }
+      },
+      _ => {},
+    }
+
+    match value {
+      Value::Object(map) => return Ok(KvValue::Map(KvMap::from_json_at(path, map, None)?)),
+      Value::String(string) => return Ok(KvValue::String(string.to_owned())),
+      Value::Number(number) => {
+        if let Some(unsigned) = number.as_u64() {
+          return Ok(u32::try_from(unsigned).map(| number | KvValue::Int32(number as i32)).unwrap_or(KvValue::UInt64(unsigned)));
+        } else if let Some(signed) = number.as_i64() {
+          return Ok(i32::try_from(signed).map(KvValue::Int32).unwrap_or(KvValue::Int64(signed)));
+        } else {
+          let float = number.as_f64().ok_or(VdfError::InvalidData(format!("Value of \"{}\" was not a valid number.", path)))?;
+          return Ok(KvValue::Float32(float as f32));
+        }
+      },
+      _ => return Err(VdfError::InvalidData(format!("Value of \"{}\" was not an object, number or string!", path))),
+    }
+  }
+  /// Converts a JSON number to a value of the provided field type, if it fits.
+  /// Int32 accepts the whole u32 range as well, since appids are usually given unsigned.
+  fn number_with_type(number: &Number, field_type: FieldType) -> Option<KvValue> {
+    match field_type {
+      FieldType::Int32 => {
+        let signed = number.as_i64()?;
+        return i32::try_from(signed).ok().or(u32::try_from(signed).ok().map(| unsigned | unsigned as i32)).map(KvValue::Int32);
+      },
+      FieldType::Float32 => return number.as_f64().map(| float | KvValue::Float32(float as f32)),
+      FieldType::Pointer => return number.as_u64().and_then(| unsigned | u32::try_from(unsigned).ok()).map(KvValue::Pointer),
+      FieldType::Color => return number.as_u64().and_then(| unsigned | u32::try_from(unsigned).ok()).map(KvValue::Color),
+      FieldType::UInt64 => return number.as_u64().map(KvValue::UInt64),
+      FieldType::Int64 => return number.as_i64().map(KvValue::Int64),
+      _ => return None,
+    }
+  }
+}
+
+impl Serialize for KvMap {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(self.len()))?;
+
+    for (key, value) in self.iter() {
+      map.serialize_entry(key, value)?;
+    }
+
+    return map.end();
+  }
+}
+
+impl Serialize for KvValue {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self {
+      KvValue::Map(map) => return map.serialize(serializer),
+      KvValue::String(string) | KvValue::WideString(string) => return serializer.serialize_str(string),
+      KvValue::Float32(number) => return serializer.serialize_f32(*number),
+      KvValue::Int32(number) => return serializer.serialize_i32(*number),
+      KvValue::Int64(number) => return serializer.serialize_i64(*number),
+      _ => return serializer.serialize_u64(self.as_u64().unwrap_or_default()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  use crate::kv_value_ref::KvValueRef;
+
+  #[test]
+  fn int32_is_signed() {
+    let value = KvValue::Int32(-1);
+
+    assert_eq!(value.as_i64(), Some(-1));
+    assert_eq!(value.as_u64(), None);
+    assert_eq!(value.as_f64(), Some(-1.0));
+    assert_eq!(value.to_json(), json!(-1));
+    assert_eq!(serde_json::to_value(&value).unwrap(), json!(-1));
+    assert_eq!(serde_json::to_value(KvValueRef::Int32(-1)).unwrap(), json!(-1));
+    assert_eq!(KvValue::from_json(&json!(-1)).unwrap(), value);
+  }
+
+  #[test]
+  fn as_u32_reads_appids_with_the_high_bit_set() {
+    let appid = KvValue::Int32(3000000001u32 as i32);
+
+    assert_eq!(appid.as_u32(), Some(3000000001));
+    assert_eq!(KvValueRef::Int32(3000000001u32 as i32).as_u32(), Some(3000000001));
+    assert_eq!(KvValue::UInt64(3000000001).as_u32(), Some(3000000001));
+    assert_eq!(KvValue::UInt64(u64::MAX).as_u32(), None);
+    assert_eq!(KvValue::String(String::from("3000000001")).as_u32(), None);
+  }
+
+  #[test]
+  fn int32_accepts_unsigned_json_with_types() {
+    let mut types = KvMap::new();
+    types.push(String::from("appid"), KvValue::Int32(0));
+    types.push(String::from("LastPlayTime"), KvValue::Int32(0));
+
+    let json = json!({ "appid": 3000000001u32, "LastPlayTime": -5 });
+    let data = KvMap::from_json_with_types(json.as_object().unwrap(), &types).unwrap();
+
+    assert_eq!(data.get("appid"), Some(&KvValue::Int32(3000000001u32 as i32)));
+    assert_eq!(data.get("LastPlayTime"), Some(&KvValue::Int32(-5)));
+    //? Written back out, the appid is signed, like text KeyValues writes it.
+    assert_eq!(Value::Object(data.to_json()), json!({ "appid": -1294967295, "LastPlayTime": -5 }));
+  }
+}
@@ -0,0 +1,233 @@
+use std::borrow::Cow;
+
+use serde::ser::{Serialize, Serializer, SerializeMap};
+
+use crate::field_type::FieldType;
+use crate::kv_value::{KvMap, KvValue};
+
+/// A KeyValues value whose strings are borrowed from the buffer it was read from, instead of being copied.
+/// Strings that aren't valid UTF-8, and wide strings, can't be borrowed and are owned instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KvValueRef<'a> {
+  Map(KvMapRef<'a>),
+  String(Cow<'a, str>),
+  Int32(i32),
+  Float32(f32),
+  Pointer(u32),
+  WideString(Cow<'a, str>),
+  Color(u32),
+  UInt64(u64),
+  Int64(i64),
+}
+
+/// An ordered KeyValues map whose keys and strings are borrowed from the buffer it was read from. Use `to_kv_map` or `into_kv_map` to get an owned `KvMap`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KvMapRef<'a> {
+  entries: Vec<(Cow<'a, str>, KvValueRef<'a>)>,
+  /// The type byte that closed the map in binary KeyValues.
+  end_type: FieldType,
+}
+
+impl Default for KvMapRef<'_> {
+  fn default() -> Self {
+    return KvMapRef::new();
+  }
+}
+
+impl<'a> KvMapRef<'a> {
+  /// Creates a new, empty KvMapRef.
+  pub fn new() -> KvMapRef<'a> {
+    return KvMapRef { entries: Vec::new(), end_type: FieldType::End };
+  }
+
+  /// Gets the type byte that closed the map in binary KeyValues.
+  pub fn get_end_type(&self) -> FieldType {
+    return self.end_type;
+  }
+
+  /// Sets the type byte that closed the map. Anything other than `End` and `AlternateEnd` is ignored.
+  pub(crate) fn set_end_type(&mut self, end_type: FieldType) {
+    if end_type.is_end() {
+      self.end_type = end_type;
+    }
+  }
+
+  /// Gets the number of entries in the map.
+  pub fn len(&self) -> usize { return self.entries.len(); }
+  /// Checks if the map has no entries.
+  pub fn is_empty(&self) -> bool { return self.entries.is_empty(); }
+
+  /// Gets the first value with the provided key.
+  pub fn get(&self, key: &str) -> Option<&KvValueRef<'a>> {
+    return self.entries.iter().find(| (entry_key, _) | entry_key == key).map(| (_, value) | value);
+  }
+
+  /// Gets the first value with the provided key, ignoring ASCII case like Valve's text KeyValues do.
+  pub fn get_ignore_case(&self, key: &str) -> Option<&KvValueRef<'a>> {
+    return self.entries.iter().find(| (entry_key, _) | entry_key.eq_ignore_ascii_case(key)).map(| (_, value) | value);
+  }
+
+  /// Checks if the map has an entry with the provided key.
+  pub fn contains_key(&self, key: &str) -> bool {
+    return self.get(key).is_some();
+  }
+
+  /// Appends an entry, even if an entry with the same key already exists.
+  pub fn push(&mut self, key: Cow<'a, str>, value: KvValueRef<'a>) {
+    self.entries.push((key, value));
+  }
+
+  /// Removes the first entry with the provided key and returns its value.
+  pub fn remove(&mut self, key: &str) -> Option<KvValueRef<'a>> {
+    let index = self.entries.iter().position(| (entry_key, _) | entry_key == key)?;
+    return Some(self.entries.remove(index).1);
+  }
+
+  /// Iterates over the entries in order.
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &KvValueRef<'a>)> {
+    return self.entries.iter().map(| (key, value) | (key.as_ref(), value));
+  }
+
+  /// Iterates over the keys in order.
+  pub fn keys(&self) -> impl Iterator<Item = &str> {
+    return self.entries.iter().map(| (key, _) | key.as_ref());
+  }
+
+  /// Copies the map into an owned KvMap.
+  pub fn to_kv_map(&self) -> KvMap {
+    let mut map: KvMap = self.iter().map(| (key, value) | (key.to_owned(), value.to_kv_value())).collect();
+    map.set_end_type(self.end_type).expect("Should have only ever had an end type.");
+
+    return map;
+  }
+
+  /// Converts the map into an owned KvMap, reusing any strings that are already owned.
+  pub fn into_kv_map(self) -> KvMap {
+    let end_type = self.end_type;
+    let mut map: KvMap = self.entries.into_iter().map(| (key, value) | (key.into_owned(), value.into_kv_value())).collect();
+    map.set_end_type(end_type).expect("Should have only ever had an end type.");
+
+    return map;
+  }
+}
+
+impl<'a> IntoIterator for KvMapRef<'a> {
+  type Item = (Cow<'a, str>, KvValueRef<'a>);
+  type IntoIter = std::vec::IntoIter<(Cow<'a, str>, KvValueRef<'a>)>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    return self.entries.into_iter();
+  }
+}
+
+impl<'a> FromIterator<(Cow<'a, str>, KvValueRef<'a>)> for KvMapRef<'a> {
+  fn from_iter<T: IntoIterator<Item = (Cow<'a, str>, KvValueRef<'a>)>>(iter: T) -> KvMapRef<'a> {
+    return KvMapRef { entries: iter.into_iter().collect(), end_type: FieldType::End };
+  }
+}
+
+impl<'a> KvValueRef<'a> {
+  /// Gets the binary field type of this value.
+  pub fn field_type(&self) -> FieldType {
+    match self {
+      KvValueRef::Map(_) => return FieldType::Map,
+      KvValueRef::String(_) => return FieldType::String,
+      KvValueRef::Int32(_) => return FieldType::Int32,
+      KvValueRef::Float32(_) => return FieldType::Float32,
+      KvValueRef::Pointer(_) => return FieldType::Pointer,
+      KvValueRef::WideString(_) => return FieldType::WideString,
+      KvValueRef::Color(_) => return FieldType::Color,
+      KvValueRef::UInt64(_) => return FieldType::UInt64,
+      KvValueRef::Int64(_) => return FieldType::Int64,
+    }
+  }
+
+  /// Gets the value as a map, if it is one.
+  pub fn as_map(&self) -> Option<&KvMapRef<'a>> {
+    match self {
+      KvValueRef::Map(map) => return Some(map),
+      _ => return None,
+    }
+  }
+
+  /// Gets the value as a str, if it is a string or wide string.
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      KvValueRef::String(string) | KvValueRef::WideString(string) => return Some(string),
+      _ => return None,
+    }
+  }
+
+  /// Gets the value as a u64, if it is a non negative integer.
+  pub fn as_u64(&self) -> Option<u64> {
+    match self {
+      KvValueRef::Int32(number) => return u64::try_from(*number).ok(),
+      KvValueRef::Pointer(number) | KvValueRef::Color(number) => return Some(*number as u64),
+      KvValueRef::UInt64(number) => return Some(*number),
+      KvValueRef::Int64(number) => return u64::try_from(*number).ok(),
+      _ => return None,
+    }
+  }
+
+  /// Gets the value as a u32, like an appid, if it is an integer that fits. Int32 values are reinterpreted as unsigned, like `KvValue::as_u32`.
+  pub fn as_u32(&self) -> Option<u32> {
+    match self {
+      KvValueRef::Int32(number) => return Some(*number as u32),
+      KvValueRef::Pointer(number) | KvValueRef::Color(number) => return Some(*number),
+      KvValueRef::UInt64(number) => return u32::try_from(*number).ok(),
+      KvValueRef::Int64(number) => return u32::try_from(*number).ok(),
+      _ => return None,
+    }
+  }
+
+  /// Copies the value into an owned KvValue.
+  pub fn to_kv_value(&self) -> KvValue {
+    match self {
+      KvValueRef::Map(map) => return KvValue::Map(map.to_kv_map()),
+      KvValueRef::String(string) => return KvValue::String(string.to_string()),
+      KvValueRef::WideString(string) => return KvValue::WideString(string.to_string()),
+      KvValueRef::Int32(number) => return KvValue::Int32(*number),
+      KvValueRef::Float32(number) => return KvValue::Float32(*number),
+      KvValueRef::Pointer(number) => return KvValue::Pointer(*number),
+      KvValueRef::Color(number) => return KvValue::Color(*number),
+      KvValueRef::UInt64(number) => return KvValue::UInt64(*number),
+      KvValueRef::Int64(number) => return KvValue::Int64(*number),
+    }
+  }
+
+  /// Converts the value into an owned KvValue, reusing any strings that are already owned.
+  pub fn into_kv_value(self) -> KvValue {
+    match self {
+      KvValueRef::Map(map) => return KvValue::Map(map.into_kv_map()),
+      KvValueRef::String(string) => return KvValue::String(string.into_owned()),
+      KvValueRef::WideString(string) => return KvValue::WideString(string.into_owned()),
+      other => return other.to_kv_value(),
+    }
+  }
+}
+
+impl Serialize for KvMapRef<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(self.len()))?;
+
+    for (key, value) in self.iter() {
+      map.serialize_entry(key, value)?;
+    }
+
+    return map.end();
+  }
+}
+
+/// Serializes the same way as `KvValue`, so borrowed and owned maps produce the same JSON.
+impl Serialize for KvValueRef<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self {
+      KvValueRef::Map(map) => return map.serialize(serializer),
+      KvValueRef::String(string) | KvValueRef::WideString(string) => return serializer.serialize_str(string),
+      KvValueRef::Float32(number) => return serializer.serialize_f32(*number),
+      KvValueRef::Int32(number) => return serializer.serialize_i32(*number),
+      KvValueRef::Int64(number) => return serializer.serialize_i64(*number),
+      _ => return serializer.serialize_u64(self.as_u64().unwrap_or_default()),
+    }
+  }
+}
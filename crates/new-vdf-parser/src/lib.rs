@@ -1,8 +1,37 @@
+pub mod de;
+pub mod diff;
+pub mod error;
+pub mod field_type;
+pub mod kv_value;
+pub mod kv_value_ref;
+pub mod library_assets;
+pub mod loginusers;
+pub mod query;
 pub mod reader;
+pub mod ser;
+pub mod visitor;
 pub mod writer;
 pub mod appinfo_vdf_parser;
+pub mod packageinfo_vdf_parser;
+pub mod shortcuts;
 pub mod shortcuts_vdf_parser;
+pub mod text_vdf_parser;
 mod vdf_reader;
+mod vdf_writer;
+#[cfg(test)]
+mod test_utils;
 
-pub use appinfo_vdf_parser::open_appinfo_vdf;
-pub use shortcuts_vdf_parser::{open_shortcuts_vdf, write_shortcuts_vdf};
\ No newline at end of file
+pub use de::{from_kv_map, from_kv_value, from_slice};
+pub use diff::{diff_appinfo, diff_kv_maps, diff_shortcuts, diff_vdf_files, EntryDiff, EntryStatus, KvChange, VdfDiff};
+pub use error::{ShortcutsProblem, VdfError};
+pub use kv_value::{KvMap, KvValue};
+pub use kv_value_ref::{KvMapRef, KvValueRef};
+pub use library_assets::{LibraryAssets, LocalizedImage, LogoPosition};
+pub use loginusers::{get_steam_id32, get_steam_id64, list_userdata_users, open_loginusers_vdf, parse_loginusers, read_steam_users, SteamUser};
+pub use ser::{to_kv_map, to_kv_value, to_vec};
+pub use visitor::{visit_slice, KvVisitor, ProjectionVisitor, Visit};
+pub use appinfo_vdf_parser::{open_appinfo_vdf, open_appinfo_vdf_raw, parse_appinfo, parse_appinfo_raw, verify_appinfo_vdf, write_appinfo, write_appinfo_vdf, AppInfoEntry, AppInfoFilter, AppInfoHeader, AppInfoIndex, AppInfoProblem, AppInfoVdf, AppInfoVersion};
+pub use packageinfo_vdf_parser::{open_packageinfo_vdf, open_packageinfo_vdf_raw, parse_packageinfo, parse_packageinfo_raw, BillingType, Package, PackageInfoEntry, PackageInfoHeader};
+pub use shortcuts::{get_shortcut_appid, get_shortcut_gameid, get_shortcut_grid_stem, get_shortcut_map_appid, get_shortcut_run_url, validate_shortcuts_vdf, Shortcut, ShortcutsFile};
+pub use shortcuts_vdf_parser::{open_shortcuts_vdf, parse_shortcuts, write_shortcuts, write_shortcuts_vdf};
+pub use text_vdf_parser::{open_text_vdf, parse_text_vdf, to_text_vdf, write_text_vdf};
\ No newline at end of file
@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::kv_value::{KvMap, KvValue};
+
+/// The language Steam falls back to when an image has no version in the requested language.
+pub const DEFAULT_LANGUAGE: &str = "english";
+
+/// An image with a version per Steam language, like "english" or "schinese". The values are file names relative to the app's librarycache folder.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct LocalizedImage {
+  pub image: BTreeMap<String, String>,
+  /// The high resolution versions of the image, if the app has any.
+  pub image2x: BTreeMap<String, String>,
+}
+
+impl LocalizedImage {
+  /// Gets the image in the provided language, falling back to english, and then to any language.
+  pub fn get(&self, language: &str) -> Option<&str> {
+    return get_localized(&self.image, language);
+  }
+
+  /// Gets the high resolution image in the provided language, with the same fallbacks as `get`.
+  pub fn get_2x(&self, language: &str) -> Option<&str> {
+    return get_localized(&self.image2x, language);
+  }
+
+  /// Reads an image from a map of languages to file names.
+  fn from_languages(languages: &KvMap) -> LocalizedImage {
+    let image = languages.iter().filter_map(| (language, file) | Some((language.to_owned(), file.as_str()?.to_owned()))).collect();
+
+    return LocalizedImage { image, image2x: BTreeMap::new() };
+  }
+
+  /// Reads an image from a "library_assets_full" entry, which has "image" and "image2x" language maps.
+  fn from_asset(asset: &KvMap) -> Option<LocalizedImage> {
+    let mut localized = asset.get("image").and_then(KvValue::as_map).map(LocalizedImage::from_languages)?;
+
+    if let Some(image2x) = asset.get("image2x").and_then(KvValue::as_map) {
+      localized.image2x = LocalizedImage::from_languages(image2x).image;
+    }
+
+    return Some(localized);
+  }
+}
+
+/// Gets the value for a language, falling back to english, and then to the first language.
+fn get_localized<'a>(images: &'a BTreeMap<String, String>, language: &str) -> Option<&'a str> {
+  return images.get(language)
+    .or(images.get(DEFAULT_LANGUAGE))
+    .or(images.values().next())
+    .map(String::as_str);
+}
+
+/// Where an app's logo is pinned on its hero, and how much of the hero it covers.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct LogoPosition {
+  /// One of "BottomLeft", "UpperLeft", "CenterCenter", "UpperCenter" or "BottomCenter".
+  pub pinned_position: String,
+  pub width_pct: f32,
+  pub height_pct: f32,
+}
+
+impl LogoPosition {
+  /// Reads a "logo_position" map. Steam stores the percentages as strings, so both strings and numbers are accepted.
+  fn from_map(map: &KvMap) -> Option<LogoPosition> {
+    let pinned_position = map.get("pinned_position")?.as_str()?.to_owned();
+    let get_pct = | key: &str | map.get(key).and_then(| value | value.as_str().and_then(| pct | pct.parse().ok()).or(value.as_f64().map(| pct | pct as f32)));
+
+    return Some(LogoPosition {
+      pinned_position,
+      width_pct: get_pct("width_pct").unwrap_or(100.0),
+      height_pct: get_pct("height_pct").unwrap_or(100.0),
+    });
+  }
+}
+
+/// The library images and icons of an app, decoded from its "common" section in appinfo.vdf.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct LibraryAssets {
+  /// The 600x900 portrait capsule.
+  pub capsule: Option<LocalizedImage>,
+  /// The 920x430 wide capsule.
+  pub header: Option<LocalizedImage>,
+  pub hero: Option<LocalizedImage>,
+  pub hero_blur: Option<LocalizedImage>,
+  pub logo: Option<LocalizedImage>,
+  pub logo_position: Option<LogoPosition>,
+  /// The store header from "common"/"header_image", which older apps have instead of a library header.
+  pub header_image: Option<LocalizedImage>,
+  /// The hash of the app's icon. The icon is `<hash>.jpg` in the app's librarycache folder.
+  pub icon: Option<String>,
+  /// The hash of the app's desktop shortcut icon.
+  pub clienticon: Option<String>,
+  /// The hash of the app's tga icon, used on Linux.
+  pub clienttga: Option<String>,
+}
+
+impl LibraryAssets {
+  /// Decodes the library assets from an app's "common" map.
+  /// Images only come from "library_assets_full". The older "library_assets" only lists which assets an app has, not their file names, so apps that only have it get no images, and only their logo position is read from it.
+  pub fn from_common(common: &KvMap) -> LibraryAssets {
+    let full = common.get("library_assets_full").and_then(KvValue::as_map);
+    let legacy = common.get("library_assets").and_then(KvValue::as_map);
+
+    let get_asset = | asset_name: &str | full?.get(asset_name).and_then(KvValue::as_map).and_then(LocalizedImage::from_asset);
+
+    let logo_position = full.and_then(| full | full.get("library_logo")).and_then(KvValue::as_map).and_then(| logo | logo.get("logo_position"))
+      .or(legacy.and_then(| legacy | legacy.get("logo_position")))
+      .and_then(KvValue::as_map)
+      .and_then(LogoPosition::from_map);
+
+    let get_hash = | key: &str | common.get(key).and_then(KvValue::as_str).filter(| hash | !hash.is_empty()).map(str::to_owned);
+
+    return LibraryAssets {
+      capsule: get_asset("library_capsule"),
+      header: get_asset("library_header"),
+      hero: get_asset("library_hero"),
+      hero_blur: get_asset("library_hero_blur"),
+      logo: get_asset("library_logo"),
+      logo_position,
+      header_image: common.get("header_image").and_then(KvValue::as_map).map(LocalizedImage::from_languages),
+      icon: get_hash("icon"),
+      clienticon: get_hash("clienticon"),
+      clienttga: get_hash("clienttga"),
+    };
+  }
+
+  /// Gets the wide capsule in the provided language, falling back to the store header for older apps.
+  pub fn get_header(&self, language: &str) -> Option<&str> {
+    return self.header.as_ref().and_then(| header | header.get(language))
+      .or(self.header_image.as_ref().and_then(| header_image | header_image.get(language)));
+  }
+
+  /// Gets the file name of the app's icon in its librarycache folder.
+  pub fn get_icon_file_name(&self) -> Option<String> {
+    return self.icon.as_ref().map(| icon | format!("{}.jpg", icon));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Builds a map from its entries.
+  fn map(entries: Vec<(&str, KvValue)>) -> KvMap {
+    return entries.into_iter().map(| (key, value) | (key.to_owned(), value)).collect();
+  }
+
+  /// Builds a map of languages to file names.
+  fn languages(files: &[(&str, &str)]) -> KvValue {
+    return KvValue::Map(files.iter().map(| (language, file) | (language.to_string(), KvValue::String(file.to_string()))).collect());
+  }
+
+  /// Builds a "common" map with a capsule in english and french, a hero without english, and a logo pinned to the bottom left.
+  fn get_common() -> KvMap {
+    return map(vec![
+      ("name", KvValue::String(String::from("Portal 2"))),
+      ("icon", KvValue::String(String::from("2e478fc6874d06ae5baf0d147f6f21203291aa02"))),
+      ("clienticon", KvValue::String(String::new())),
+      ("header_image", languages(&[("english", "header.jpg")])),
+      ("library_assets_full", KvValue::Map(map(vec![
+        ("library_capsule", KvValue::Map(map(vec![
+          ("image", languages(&[("french", "library_600x900_french.jpg"), ("english", "library_600x900.jpg")])),
+          ("image2x", languages(&[("english", "library_600x900_2x.jpg")])),
+        ]))),
+        ("library_hero", KvValue::Map(map(vec![("image", languages(&[("schinese", "library_hero_schinese.jpg"), ("koreana", "library_hero_koreana.jpg")]))]))),
+        ("library_logo", KvValue::Map(map(vec![
+          ("image", languages(&[("english", "logo.png")])),
+          ("logo_position", KvValue::Map(map(vec![
+            ("pinned_position", KvValue::String(String::from("BottomLeft"))),
+            ("width_pct", KvValue::String(String::from("36.5"))),
+            ("height_pct", KvValue::Float32(50.0)),
+          ]))),
+        ]))),
+      ]))),
+    ]);
+  }
+
+  #[test]
+  fn images_fall_back_to_english_then_the_first_language() {
+    let assets = LibraryAssets::from_common(&get_common());
+    let capsule = assets.capsule.unwrap();
+
+    assert_eq!(capsule.get("french"), Some("library_600x900_french.jpg"));
+    assert_eq!(capsule.get("german"), Some("library_600x900.jpg"));
+
+    //? Languages are sorted, so "koreana" comes before "schinese".
+    assert_eq!(assets.hero.unwrap().get("german"), Some("library_hero_koreana.jpg"));
+    assert_eq!(assets.hero_blur, None);
+  }
+
+  #[test]
+  fn reads_2x_images() {
+    let capsule = LibraryAssets::from_common(&get_common()).capsule.unwrap();
+
+    assert_eq!(capsule.get_2x("french"), Some("library_600x900_2x.jpg"));
+    assert_eq!(LibraryAssets::from_common(&get_common()).logo.unwrap().get_2x("english"), None);
+  }
+
+  #[test]
+  fn reads_logo_positions_from_strings_and_numbers() {
+    let assets = LibraryAssets::from_common(&get_common());
+
+    assert_eq!(assets.logo_position, Some(LogoPosition { pinned_position: String::from("BottomLeft"), width_pct: 36.5, height_pct: 50.0 }));
+  }
+
+  #[test]
+  fn header_falls_back_to_header_image() {
+    let mut common = get_common();
+    assert_eq!(LibraryAssets::from_common(&common).get_header("english"), Some("header.jpg"));
+
+    let full = common.get_mut("library_assets_full").and_then(KvValue::as_map_mut).unwrap();
+    full.push(String::from("library_header"), KvValue::Map(map(vec![("image", languages(&[("english", "library_header.jpg")]))])));
+
+    assert_eq!(LibraryAssets::from_common(&common).get_header("english"), Some("library_header.jpg"));
+  }
+
+  #[test]
+  fn icon_hashes_are_jpg_file_names() {
+    let assets = LibraryAssets::from_common(&get_common());
+
+    assert_eq!(assets.get_icon_file_name().as_deref(), Some("2e478fc6874d06ae5baf0d147f6f21203291aa02.jpg"));
+    assert_eq!(assets.clienticon, None);
+  }
+
+  #[test]
+  fn legacy_assets_have_no_images() {
+    let common = map(vec![("library_assets", KvValue::Map(map(vec![
+      ("library_capsule", KvValue::String(String::from("en"))),
+      ("library_hero", KvValue::String(String::from("en"))),
+      ("logo_position", KvValue::Map(map(vec![("pinned_position", KvValue::String(String::from("CenterCenter")))]))),
+    ])))]);
+
+    let assets = LibraryAssets::from_common(&common);
+    assert_eq!(assets.capsule, None);
+    assert_eq!(assets.hero, None);
+    assert_eq!(assets.get_header("english"), None);
+    assert_eq!(assets.logo_position, Some(LogoPosition { pinned_position: String::from("CenterCenter"), width_pct: 100.0, height_pct: 100.0 }));
+  }
+}
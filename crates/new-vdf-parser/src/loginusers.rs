@@ -0,0 +1,258 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Serializer};
+
+use crate::error::VdfError;
+use crate::kv_value::{KvMap, KvValue};
+use crate::text_vdf_parser::open_text_vdf;
+
+/// The 64 bit id of the individual account with 32 bit id 0. Every user's 64 bit id is their 32 bit id added to this.
+pub const STEAM_ID64_BASE: u64 = 76561197960265728;
+
+/// Gets a user's 32 bit account id from their 64 bit SteamID. This is the name of their userdata dir.
+pub fn get_steam_id32(id64: u64) -> u32 {
+  return id64.wrapping_sub(STEAM_ID64_BASE) as u32;
+}
+
+/// Gets a user's 64 bit SteamID from their 32 bit account id.
+pub fn get_steam_id64(id32: u32) -> u64 {
+  return STEAM_ID64_BASE + id32 as u64;
+}
+
+/// Serializes an id as a string, since 64 bit ids don't fit in a JavaScript number.
+fn serialize_id<T: ToString, S: Serializer>(id: &T, serializer: S) -> Result<S::Ok, S::Error> {
+  return serializer.serialize_str(&id.to_string());
+}
+
+/// A Steam user that has logged in on this computer, from loginusers.vdf.
+/// Fields are serialized with the names Steam uses in the file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SteamUser {
+  #[serde(rename = "id64", serialize_with = "serialize_id")]
+  pub id64: u64,
+  #[serde(rename = "id32", serialize_with = "serialize_id")]
+  pub id32: u32,
+  pub account_name: String,
+  pub persona_name: String,
+  /// If this was the last user to log in.
+  pub most_recent: bool,
+  pub remember_password: bool,
+  /// When the user last logged in, as a unix timestamp.
+  pub timestamp: u64,
+  pub wants_offline_mode: bool,
+  pub skip_offline_mode_warning: bool,
+  pub allow_auto_login: bool,
+}
+
+impl SteamUser {
+  /// Creates a user with only their ids, for when loginusers.vdf can't be read.
+  pub fn from_id32(id32: u32) -> SteamUser {
+    return SteamUser {
+      id64: get_steam_id64(id32),
+      id32,
+      account_name: id32.to_string(),
+      persona_name: id32.to_string(),
+      most_recent: false,
+      remember_password: false,
+      timestamp: 0,
+      wants_offline_mode: false,
+      skip_offline_mode_warning: false,
+      allow_auto_login: false,
+    };
+  }
+
+  /// Reads a user from their block in loginusers.vdf. Keys are matched ignoring case, since Steam has written them with different casings over time.
+  /// Missing or unreadable keys are left empty, zero or false.
+  pub fn from_kv_map(id64: u64, data: &KvMap) -> SteamUser {
+    let get_str = | key: &str | data.get_ignore_case(key).and_then(KvValue::as_str).unwrap_or_default().to_owned();
+    let get_number = | key: &str | data.get_ignore_case(key).and_then(parse_number).unwrap_or_default();
+
+    return SteamUser {
+      id64,
+      id32: get_steam_id32(id64),
+      account_name: get_str("AccountName"),
+      persona_name: get_str("PersonaName"),
+      most_recent: get_number("MostRecent") != 0,
+      remember_password: get_number("RememberPassword") != 0,
+      timestamp: get_number("Timestamp"),
+      wants_offline_mode: get_number("WantsOfflineMode") != 0,
+      skip_offline_mode_warning: get_number("SkipOfflineModeWarning") != 0,
+      allow_auto_login: get_number("AllowAutoLogin") != 0,
+    };
+  }
+}
+
+/// Parses a number from a value. Text vdf files store every number as a string.
+fn parse_number(value: &KvValue) -> Option<u64> {
+  match value.as_str() {
+    Some(string) => return string.trim().parse().ok(),
+    None => return value.as_u64(),
+  }
+}
+
+/// Reads the users from the contents of a loginusers.vdf file, in file order.
+/// Entries whose key isn't a 64 bit SteamID, or that aren't blocks, are skipped.
+pub fn parse_loginusers(loginusers_vdf: &KvMap) -> Result<Vec<SteamUser>, VdfError> {
+  let users = match loginusers_vdf.get_ignore_case("users") {
+    Some(users) => users.as_map().ok_or(VdfError::UnexpectedValueType { path: String::from("users"), expected: "a Map", actual: users.field_type() })?,
+    None => return Err(VdfError::MissingKey(String::from("users"))),
+  };
+
+  return Ok(users.iter()
+    .filter_map(| (id64, user) | Some(SteamUser::from_kv_map(id64.trim().parse().ok()?, user.as_map()?)))
+    .collect());
+}
+
+/// Opens a loginusers.vdf file and reads its users.
+pub fn open_loginusers_vdf(path: &PathBuf) -> Result<Vec<SteamUser>, VdfError> {
+  return parse_loginusers(&open_text_vdf(path, None)?);
+}
+
+/// Lists the users that have a dir in Steam's userdata dir, sorted by id.
+/// These only have their ids, so this is a fallback for when loginusers.vdf is missing. The "0" dir Steam makes before anyone logs in is skipped.
+pub fn list_userdata_users(userdata_dir: &Path) -> Result<Vec<SteamUser>, VdfError> {
+  let mut id32s: Vec<u32> = fs::read_dir(userdata_dir)?
+    .filter_map(| entry | entry.ok())
+    .filter(| entry | entry.file_type().map(| file_type | file_type.is_dir()).unwrap_or(false))
+    .filter_map(| entry | entry.file_name().to_str()?.parse::<u32>().ok())
+    .filter(| id32 | *id32 != 0)
+    .collect();
+
+  id32s.sort();
+
+  return Ok(id32s.into_iter().map(SteamUser::from_id32).collect());
+}
+
+/// Reads the users that have logged in on this computer from the Steam install at `steam_root`.
+/// If config/loginusers.vdf is missing, the users are listed from the userdata dir instead.
+pub fn read_steam_users(steam_root: &Path) -> Result<Vec<SteamUser>, VdfError> {
+  let loginusers_path = steam_root.join("config/loginusers.vdf");
+
+  if loginusers_path.exists() {
+    return open_loginusers_vdf(&loginusers_path);
+  }
+
+  let userdata_dir = steam_root.join("userdata");
+
+  if !userdata_dir.exists() {
+    return Ok(Vec::new());
+  }
+
+  return list_userdata_users(&userdata_dir);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::text_vdf_parser::parse_text_vdf;
+  use crate::test_utils::get_test_dir;
+
+  /// A loginusers.vdf file with two users, like Steam writes it.
+  const LOGINUSERS_VDF: &str = "\"users\"\n{\n\t\"76561198000000001\"\n\t{\n\t\t\"AccountName\"\t\t\"alice\"\n\t\t\"PersonaName\"\t\t\"Alice\"\n\t\t\"RememberPassword\"\t\t\"1\"\n\t\t\"WantsOfflineMode\"\t\t\"0\"\n\t\t\"SkipOfflineModeWarning\"\t\t\"0\"\n\t\t\"AllowAutoLogin\"\t\t\"1\"\n\t\t\"MostRecent\"\t\t\"1\"\n\t\t\"Timestamp\"\t\t\"1700000000\"\n\t}\n\t\"76561198000000002\"\n\t{\n\t\t\"AccountName\"\t\t\"bob\"\n\t\t\"PersonaName\"\t\t\"Bob\"\n\t\t\"mostrecent\"\t\t\"0\"\n\t}\n}\n";
+
+  /// Parses the contents of a loginusers.vdf file.
+  fn parse(input: &str) -> Vec<SteamUser> {
+    return parse_loginusers(&parse_text_vdf(input, None, None).unwrap()).unwrap();
+  }
+
+  #[test]
+  fn reads_users_with_typed_fields() {
+    let users = parse(LOGINUSERS_VDF);
+
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0].id64, 76561198000000001);
+    assert_eq!(users[0].id32, 39734273);
+    assert_eq!(users[0].account_name, "alice");
+    assert_eq!(users[0].persona_name, "Alice");
+    assert!(users[0].most_recent);
+    assert!(users[0].remember_password);
+    assert!(users[0].allow_auto_login);
+    assert!(!users[0].wants_offline_mode);
+    assert_eq!(users[0].timestamp, 1700000000);
+
+    assert_eq!(users[1].account_name, "bob");
+    assert!(!users[1].most_recent);
+    assert_eq!(users[1].timestamp, 0);
+  }
+
+  #[test]
+  fn converts_ids() {
+    assert_eq!(get_steam_id32(76561198000000001), 39734273);
+    assert_eq!(get_steam_id64(39734273), 76561198000000001);
+    assert_eq!(get_steam_id32(STEAM_ID64_BASE), 0);
+  }
+
+  #[test]
+  fn reads_crlf_files() {
+    let users = parse(&LOGINUSERS_VDF.replace('\n', "\r\n"));
+
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0].account_name, "alice");
+    assert!(users[0].most_recent);
+    assert_eq!(users[0].timestamp, 1700000000);
+  }
+
+  #[test]
+  fn nested_blocks_dont_end_users_early() {
+    let users = parse("\"users\"\n{\n\t\"76561198000000001\"\n\t{\n\t\t\"AccountName\"\t\t\"alice\"\n\t\t\"Settings\"\n\t\t{\n\t\t\t\"MostRecent\"\t\t\"0\"\n\t\t}\n\t\t\"MostRecent\"\t\t\"1\"\n\t}\n\t\"76561198000000002\"\n\t{\n\t\t\"AccountName\"\t\t\"bob\"\n\t}\n}\n");
+
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0].account_name, "alice");
+    assert!(users[0].most_recent);
+    assert_eq!(users[1].account_name, "bob");
+  }
+
+  #[test]
+  fn entries_that_arent_users_are_skipped() {
+    let users = parse("\"users\"\n{\n\t\"not an id\"\n\t{\n\t\t\"AccountName\"\t\t\"alice\"\n\t}\n\t\"76561198000000002\"\t\t\"bob\"\n\t\"76561198000000003\"\n\t{\n\t}\n}\n");
+
+    assert_eq!(users, vec![SteamUser { account_name: String::new(), persona_name: String::new(), ..SteamUser::from_id32(39734275) }]);
+  }
+
+  #[test]
+  fn missing_users_are_errors() {
+    let loginusers_vdf = parse_text_vdf("\"config\"\n{\n}\n", None, None).unwrap();
+
+    assert!(matches!(parse_loginusers(&loginusers_vdf), Err(VdfError::MissingKey(_))));
+  }
+
+  #[test]
+  fn reads_loginusers_vdf_from_steam_root() {
+    let steam_root = get_test_dir("loginusers");
+    fs::create_dir_all(steam_root.join("config")).unwrap();
+    fs::write(steam_root.join("config/loginusers.vdf"), LOGINUSERS_VDF).unwrap();
+    fs::create_dir_all(steam_root.join("userdata/5")).unwrap();
+
+    let users = read_steam_users(&steam_root).unwrap();
+
+    assert_eq!(users.iter().map(| user | user.account_name.as_str()).collect::<Vec<_>>(), vec!["alice", "bob"]);
+    fs::remove_dir_all(&steam_root).unwrap();
+  }
+
+  #[test]
+  fn falls_back_to_userdata_dirs() {
+    let steam_root = get_test_dir("loginusers-userdata");
+    fs::create_dir_all(steam_root.join("userdata/39734274")).unwrap();
+    fs::create_dir_all(steam_root.join("userdata/39734273")).unwrap();
+    fs::create_dir_all(steam_root.join("userdata/0")).unwrap();
+    fs::create_dir_all(steam_root.join("userdata/anonymous")).unwrap();
+    fs::write(steam_root.join("userdata/12345"), "").unwrap();
+
+    let users = read_steam_users(&steam_root).unwrap();
+
+    assert_eq!(users, vec![SteamUser::from_id32(39734273), SteamUser::from_id32(39734274)]);
+    assert_eq!(users[0].id64, 76561198000000001);
+    assert_eq!(users[0].account_name, "39734273");
+    fs::remove_dir_all(&steam_root).unwrap();
+  }
+
+  #[test]
+  fn no_loginusers_or_userdata_is_no_users() {
+    let steam_root = get_test_dir("loginusers-empty");
+
+    assert_eq!(read_steam_users(&steam_root).unwrap(), Vec::new());
+    fs::remove_dir_all(&steam_root).unwrap();
+  }
+}
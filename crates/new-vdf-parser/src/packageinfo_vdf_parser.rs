@@ -0,0 +1,327 @@
+use std::{path::PathBuf, fs};
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::VdfError;
+use crate::kv_value::KvMap;
+use crate::kv_value_ref::{KvMapRef, KvValueRef};
+use crate::reader::Reader;
+use crate::vdf_reader::{read_entry_map_ref, EntryContext};
+
+/// The magic number of the older packageinfo.vdf files, whose package headers don't have a PICS token.
+pub const PACKAGEINFO_MAGIC_V27: u32 = 0x06565527;
+/// The magic number of packageinfo.vdf files.
+pub const PACKAGEINFO_MAGIC_V28: u32 = 0x06565528;
+
+/// The packageid that marks the end of the packages.
+const PACKAGEINFO_END: u32 = 0xFFFFFFFF;
+
+/// The header Steam writes before each package's KeyValues data.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PackageInfoHeader {
+  pub packageid: u32,
+  /// The SHA-1 hash of the package's data.
+  pub sha1: [u8; 20],
+  pub change_number: u32,
+  /// Always zero in v27 files, which don't have it.
+  pub pics_token: u64,
+}
+
+/// A package in the packageinfo.vdf file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageInfoEntry {
+  pub header: PackageInfoHeader,
+  /// The package's data, including the top level map named after its packageid.
+  pub data: KvMap,
+}
+
+/// How a package is paid for, from Steam's EBillingType.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BillingType {
+  NoCost,
+  BillOnceOnly,
+  BillMonthly,
+  ProofOfPrepurchaseOnly,
+  GuestPass,
+  HardwarePromo,
+  Gift,
+  AutoGrant,
+  OemTicket,
+  RecurringOption,
+  BillOnceOrCdKey,
+  Repurchaseable,
+  FreeOnDemand,
+  Rental,
+  CommercialLicense,
+  FreeCommercialLicense,
+  /// A billing type this crate doesn't know.
+  Other(u32),
+}
+
+impl BillingType {
+  /// Gets the BillingType for Steam's numeric value.
+  pub fn from_u32(value: u32) -> BillingType {
+    match value {
+      0 => return BillingType::NoCost,
+      1 => return BillingType::BillOnceOnly,
+      2 => return BillingType::BillMonthly,
+      3 => return BillingType::ProofOfPrepurchaseOnly,
+      4 => return BillingType::GuestPass,
+      5 => return BillingType::HardwarePromo,
+      6 => return BillingType::Gift,
+      7 => return BillingType::AutoGrant,
+      8 => return BillingType::OemTicket,
+      9 => return BillingType::RecurringOption,
+      10 => return BillingType::BillOnceOrCdKey,
+      11 => return BillingType::Repurchaseable,
+      12 => return BillingType::FreeOnDemand,
+      13 => return BillingType::Rental,
+      14 => return BillingType::CommercialLicense,
+      15 => return BillingType::FreeCommercialLicense,
+      other => return BillingType::Other(other),
+    }
+  }
+}
+
+/// A package, which grants a set of apps and depots to the users that own it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Package {
+  pub packageid: u32,
+  pub change_number: u32,
+  pub billing_type: BillingType,
+  pub license_type: u32,
+  pub status: u32,
+  pub appids: Vec<u32>,
+  pub depotids: Vec<u32>,
+}
+
+impl Package {
+  /// Reads a package from its data, which has a top level map named after its packageid.
+  fn from_data(header: &PackageInfoHeader, data: &KvMapRef) -> Package {
+    let package = data.iter().next().and_then(| (_, package) | package.as_map());
+
+    let get_number = | key: &str | package.and_then(| package | package.get(key)).and_then(KvValueRef::as_u32).unwrap_or_default();
+    //? Lists are maps from "0", "1" and so on to the ids.
+    let get_ids = | key: &str | -> Vec<u32> {
+      return package.and_then(| package | package.get(key)).and_then(KvValueRef::as_map)
+        .map(| ids | ids.iter().filter_map(| (_, id) | id.as_u32()).collect())
+        .unwrap_or_default();
+    };
+
+    return Package {
+      packageid: header.packageid,
+      change_number: header.change_number,
+      billing_type: BillingType::from_u32(get_number("billingtype")),
+      license_type: get_number("licensetype"),
+      status: get_number("status"),
+      appids: get_ids("appids"),
+      depotids: get_ids("depotids"),
+    };
+  }
+}
+
+/// Opens the packageinfo.vdf file and returns its packages.
+pub fn open_packageinfo_vdf(path: &PathBuf) -> Result<Vec<Package>, VdfError> {
+  let buffer = fs::read(path)?;
+
+  return Ok(read_packages(&buffer)?.iter().map(| (header, data) | Package::from_data(header, data)).collect());
+}
+
+/// Reads packageinfo.vdf data from any reader, like a `File` or a `&[u8]`, and returns its packages.
+pub fn parse_packageinfo(mut reader: impl Read) -> Result<Vec<Package>, VdfError> {
+  let mut buffer = Vec::new();
+  reader.read_to_end(&mut buffer)?;
+
+  return Ok(read_packages(&buffer)?.iter().map(| (header, data) | Package::from_data(header, data)).collect());
+}
+
+/// Opens the packageinfo.vdf file and returns every package with its header and data.
+pub fn open_packageinfo_vdf_raw(path: &PathBuf) -> Result<Vec<PackageInfoEntry>, VdfError> {
+  let buffer = fs::read(path)?;
+
+  return Ok(read_packages(&buffer)?.into_iter().map(| (header, data) | PackageInfoEntry { header, data: data.into_kv_map() }).collect());
+}
+
+/// Reads packageinfo.vdf data from any reader, keeping each package's header and data.
+pub fn parse_packageinfo_raw(mut reader: impl Read) -> Result<Vec<PackageInfoEntry>, VdfError> {
+  let mut buffer = Vec::new();
+  reader.read_to_end(&mut buffer)?;
+
+  return Ok(read_packages(&buffer)?.into_iter().map(| (header, data) | PackageInfoEntry { header, data: data.into_kv_map() }).collect());
+}
+
+/// Reads the header and data of each package.
+/// Unlike appinfo.vdf, packages aren't prefixed with their size, so each one has to be read to find where the next one starts.
+fn read_packages(data: &[u8]) -> Result<Vec<(PackageInfoHeader, KvMapRef<'_>)>, VdfError> {
+  let mut reader = Reader::new(data);
+
+  let magic = reader.read_uint32(true)?;
+  let has_pics_token = match magic {
+    PACKAGEINFO_MAGIC_V27 => false,
+    PACKAGEINFO_MAGIC_V28 => true,
+    _ if magic >> 8 == PACKAGEINFO_MAGIC_V28 >> 8 => return Err(VdfError::UnsupportedVersion(magic)),
+    _ => return Err(VdfError::UnknownMagic(magic)),
+  };
+
+  let _universe = reader.read_uint32(true)?;
+
+  let mut packages = Vec::new();
+
+  loop {
+    let packageid = reader.read_uint32(true)?;
+
+    if packageid == PACKAGEINFO_END {
+      break;
+    }
+
+    //? The context's id is an appid, but errors still show the packageid since it is the first key in the path.
+    let mut context = EntryContext::new(None, &[]);
+    let header = read_package_header(&mut reader, packageid, has_pics_token)?;
+    let package = read_entry_map_ref(&mut reader, None, None, &mut context)?;
+
+    packages.push((header, package));
+  }
+
+  return Ok(packages);
+}
+
+/// Reads the header of a package, after its packageid.
+fn read_package_header(reader: &mut Reader, packageid: u32, has_pics_token: bool) -> Result<PackageInfoHeader, VdfError> {
+  let mut sha1 = [0; 20];
+  sha1.copy_from_slice(reader.read_bytes(20)?);
+
+  let change_number = reader.read_uint32(true)?;
+  let pics_token = if has_pics_token { reader.read_uint64(true)? } else { 0 };
+
+  return Ok(PackageInfoHeader { packageid, sha1, change_number, pics_token });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::field_type::FieldType;
+  use crate::test_utils::field;
+
+  /// Builds a list of ids, which is a map from "0", "1" and so on to the ids.
+  fn id_list(key: &str, ids: &[u32]) -> Vec<u8> {
+    let mut bytes = field(FieldType::Map, key);
+
+    for (index, id) in ids.iter().enumerate() {
+      bytes.extend(field(FieldType::Int32, &index.to_string()));
+      bytes.extend_from_slice(&id.to_le_bytes());
+    }
+
+    bytes.push(FieldType::End.to_byte());
+    return bytes;
+  }
+
+  /// Builds a package's header and data, with or without a PICS token.
+  fn package(packageid: u32, pics_token: Option<u64>, billing_type: u32, appids: &[u32], depotids: &[u32]) -> Vec<u8> {
+    let mut bytes = packageid.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&[0xab; 20]);
+    bytes.extend_from_slice(&42u32.to_le_bytes());
+
+    if let Some(pics_token) = pics_token {
+      bytes.extend_from_slice(&pics_token.to_le_bytes());
+    }
+
+    bytes.extend(field(FieldType::Map, &packageid.to_string()));
+    bytes.extend(field(FieldType::Int32, "packageid"));
+    bytes.extend_from_slice(&packageid.to_le_bytes());
+    bytes.extend(field(FieldType::Int32, "billingtype"));
+    bytes.extend_from_slice(&billing_type.to_le_bytes());
+    bytes.extend(field(FieldType::Int32, "licensetype"));
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend(field(FieldType::Int32, "status"));
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend(id_list("appids", appids));
+    bytes.extend(id_list("depotids", depotids));
+    bytes.push(FieldType::End.to_byte());
+    bytes.push(FieldType::End.to_byte());
+
+    return bytes;
+  }
+
+  /// Builds a packageinfo.vdf file from its magic number and packages.
+  fn packageinfo(magic: u32, packages: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = magic.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+
+    for package in packages {
+      bytes.extend_from_slice(package);
+    }
+
+    bytes.extend_from_slice(&PACKAGEINFO_END.to_le_bytes());
+    return bytes;
+  }
+
+  #[test]
+  fn reads_packages() {
+    let bytes = packageinfo(PACKAGEINFO_MAGIC_V28, &[
+      package(0, Some(7), 4, &[7, 3000000001], &[]),
+      package(54029, Some(0), 10, &[400, 620], &[401, 621, 622]),
+    ]);
+    let packages = parse_packageinfo(&bytes[..]).unwrap();
+
+    assert_eq!(packages.len(), 2);
+    assert_eq!(packages[0].packageid, 0);
+    assert_eq!(packages[0].billing_type, BillingType::GuestPass);
+    assert_eq!(packages[0].appids, vec![7, 3000000001]);
+    assert_eq!(packages[0].depotids, Vec::<u32>::new());
+
+    assert_eq!(packages[1].packageid, 54029);
+    assert_eq!(packages[1].change_number, 42);
+    assert_eq!(packages[1].billing_type, BillingType::BillOnceOrCdKey);
+    assert_eq!(packages[1].license_type, 1);
+    assert_eq!(packages[1].appids, vec![400, 620]);
+    assert_eq!(packages[1].depotids, vec![401, 621, 622]);
+  }
+
+  #[test]
+  fn reads_package_headers() {
+    let bytes = packageinfo(PACKAGEINFO_MAGIC_V28, &[package(54029, Some(99), 0, &[400], &[401])]);
+    let entries = parse_packageinfo_raw(&bytes[..]).unwrap();
+
+    assert_eq!(entries[0].header, PackageInfoHeader { packageid: 54029, sha1: [0xab; 20], change_number: 42, pics_token: 99 });
+    assert_eq!(entries[0].data.get_u32_at("54029/appids/0").unwrap(), 400);
+  }
+
+  #[test]
+  fn reads_v27_packages_without_pics_tokens() {
+    let bytes = packageinfo(PACKAGEINFO_MAGIC_V27, &[package(54029, None, 1, &[400], &[401])]);
+    let entries = parse_packageinfo_raw(&bytes[..]).unwrap();
+
+    assert_eq!(entries[0].header.pics_token, 0);
+    assert_eq!(entries[0].header.change_number, 42);
+
+    let packages = parse_packageinfo(&bytes[..]).unwrap();
+
+    assert_eq!(packages[0].billing_type, BillingType::BillOnceOnly);
+    assert_eq!(packages[0].appids, vec![400]);
+    assert_eq!(packages[0].depotids, vec![401]);
+  }
+
+  #[test]
+  fn unknown_billing_types_are_kept() {
+    let bytes = packageinfo(PACKAGEINFO_MAGIC_V28, &[package(1, Some(0), 99, &[], &[])]);
+
+    assert_eq!(parse_packageinfo(&bytes[..]).unwrap()[0].billing_type, BillingType::Other(99));
+  }
+
+  #[test]
+  fn bad_magic_numbers_are_errors() {
+    let appinfo = packageinfo(0x07564428, &[]);
+    assert!(matches!(parse_packageinfo(&appinfo[..]), Err(VdfError::UnknownMagic(0x07564428))));
+
+    let newer = packageinfo(0x06565529, &[]);
+    assert!(matches!(parse_packageinfo(&newer[..]), Err(VdfError::UnsupportedVersion(0x06565529))));
+  }
+
+  #[test]
+  fn truncated_files_are_errors() {
+    let bytes = packageinfo(PACKAGEINFO_MAGIC_V28, &[package(54029, Some(0), 0, &[400], &[401])]);
+
+    assert!(parse_packageinfo(&bytes[..bytes.len() - 10]).is_err());
+  }
+}
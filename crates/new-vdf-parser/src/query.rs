@@ -0,0 +1,255 @@
+use crate::error::VdfError;
+use crate::kv_value::{KvMap, KvValue};
+
+/// The segment of a key path that matches any key.
+const WILDCARD: &str = "*";
+
+/// Splits a "/" separated key path into its keys. Empty keys, like from a leading "/", are ignored.
+fn split_path(path: &str) -> Vec<&str> {
+  return path.split('/').filter(| key | !key.is_empty()).collect();
+}
+
+/// Checks if a key matches a path segment.
+fn matches_key(segment: &str, key: &str, ignore_case: bool) -> bool {
+  return segment == WILDCARD || if ignore_case { key.eq_ignore_ascii_case(segment) } else { key == segment };
+}
+
+/// Adds every value on the provided path segments to `matches`, along with its full key path.
+fn query_at<'a>(map: &'a KvMap, prefix: &str, segments: &[&str], ignore_case: bool, matches: &mut Vec<(String, &'a KvValue)>) {
+  let (segment, rest) = match segments.split_first() {
+    Some(split) => split,
+    None => return,
+  };
+
+  for (key, value) in map.iter() {
+    if !matches_key(segment, key, ignore_case) {
+      continue;
+    }
+
+    let path = if prefix.is_empty() { key.to_owned() } else { format!("{}/{}", prefix, key) };
+
+    if rest.is_empty() {
+      matches.push((path, value));
+    } else if let KvValue::Map(child) = value {
+      query_at(child, &path, rest, ignore_case, matches);
+    }
+
+    //? Without a wildcard, only the first entry with the key is followed, like `get`.
+    if *segment != WILDCARD {
+      break;
+    }
+  }
+}
+
+/// Adds a mutable reference to every value on the provided path segments to `matches`.
+fn query_mut_at<'a>(map: &'a mut KvMap, segments: &[&str], matches: &mut Vec<&'a mut KvValue>) {
+  let (segment, rest) = match segments.split_first() {
+    Some(split) => split,
+    None => return,
+  };
+
+  for (key, value) in map.iter_mut() {
+    if !matches_key(segment, key, false) {
+      continue;
+    }
+
+    if rest.is_empty() {
+      matches.push(value);
+    } else if let KvValue::Map(child) = value {
+      query_mut_at(child, rest, matches);
+    }
+
+    if *segment != WILDCARD {
+      break;
+    }
+  }
+}
+
+/// Queries a map with "/" separated key paths, like `"appinfo/common/library_assets/library_capsule/image/english"`.
+/// A `*` segment matches every key at that level, so `"shortcuts/*/AppName"` matches the name of each shortcut.
+/// Keys that contain a "/" can't be matched.
+impl KvMap {
+  /// Gets every value that matches the path, along with its full key path, in file order.
+  pub fn query(&self, path: &str) -> Vec<(String, &KvValue)> {
+    let mut matches = Vec::new();
+    query_at(self, "", &split_path(path), false, &mut matches);
+
+    return matches;
+  }
+
+  /// Gets every value that matches the path, ignoring ASCII case like Valve's text KeyValues do.
+  pub fn query_ignore_case(&self, path: &str) -> Vec<(String, &KvValue)> {
+    let mut matches = Vec::new();
+    query_at(self, "", &split_path(path), true, &mut matches);
+
+    return matches;
+  }
+
+  /// Gets a mutable reference to every value that matches the path, in file order.
+  pub fn query_mut(&mut self, path: &str) -> Vec<&mut KvValue> {
+    let mut matches = Vec::new();
+    query_mut_at(self, &split_path(path), &mut matches);
+
+    return matches;
+  }
+
+  /// Gets the first value that matches the path.
+  pub fn get_path(&self, path: &str) -> Option<&KvValue> {
+    return self.query(path).into_iter().next().map(| (_, value) | value);
+  }
+
+  /// Gets the first value that matches the path, ignoring ASCII case like Valve's text KeyValues do.
+  pub fn get_path_ignore_case(&self, path: &str) -> Option<&KvValue> {
+    return self.query_ignore_case(path).into_iter().next().map(| (_, value) | value);
+  }
+
+  /// Gets a mutable reference to the first value that matches the path.
+  pub fn get_path_mut(&mut self, path: &str) -> Option<&mut KvValue> {
+    return self.query_mut(path).into_iter().next();
+  }
+
+  /// Gets the first value that matches the path, or a `MissingKey` error.
+  fn get_required(&self, path: &str) -> Result<&KvValue, VdfError> {
+    return self.get_path(path).ok_or(VdfError::MissingKey(path.to_owned()));
+  }
+
+  /// Gets the map at the path.
+  pub fn get_map_at(&self, path: &str) -> Result<&KvMap, VdfError> {
+    let value = self.get_required(path)?;
+
+    return value.as_map().ok_or(VdfError::UnexpectedValueType { path: path.to_owned(), expected: "a Map", actual: value.field_type() });
+  }
+
+  /// Gets the String or WideString at the path.
+  pub fn get_str_at(&self, path: &str) -> Result<&str, VdfError> {
+    let value = self.get_required(path)?;
+
+    return value.as_str().ok_or(VdfError::UnexpectedValueType { path: path.to_owned(), expected: "a String", actual: value.field_type() });
+  }
+
+  /// Gets the number at the path as a u64. See `KvValue::as_u64` for how values are converted.
+  pub fn get_u64_at(&self, path: &str) -> Result<u64, VdfError> {
+    let value = self.get_required(path)?;
+
+    return value.as_u64().ok_or(VdfError::UnexpectedValueType { path: path.to_owned(), expected: "an unsigned number", actual: value.field_type() });
+  }
+
+  /// Gets the number at the path as a u32, like an appid. See `KvValue::as_u32` for how values are converted.
+  pub fn get_u32_at(&self, path: &str) -> Result<u32, VdfError> {
+    let value = self.get_required(path)?;
+
+    return value.as_u32().ok_or(VdfError::UnexpectedValueType { path: path.to_owned(), expected: "a 32 bit unsigned number", actual: value.field_type() });
+  }
+
+  /// Gets the number at the path as an i64. See `KvValue::as_i64` for how values are converted.
+  pub fn get_i64_at(&self, path: &str) -> Result<i64, VdfError> {
+    let value = self.get_required(path)?;
+
+    return value.as_i64().ok_or(VdfError::UnexpectedValueType { path: path.to_owned(), expected: "a number", actual: value.field_type() });
+  }
+
+  /// Gets the number at the path as an f64. See `KvValue::as_f64` for how values are converted.
+  pub fn get_f64_at(&self, path: &str) -> Result<f64, VdfError> {
+    let value = self.get_required(path)?;
+
+    return value.as_f64().ok_or(VdfError::UnexpectedValueType { path: path.to_owned(), expected: "a number", actual: value.field_type() });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::field_type::FieldType;
+
+  /// Builds a map from its entries, keeping duplicate keys.
+  fn map(entries: Vec<(&str, KvValue)>) -> KvMap {
+    return entries.into_iter().map(| (key, value) | (key.to_owned(), value)).collect();
+  }
+
+  /// Builds a shortcuts.vdf file with the provided shortcut names, and one entry that isn't a shortcut.
+  fn get_shortcuts_vdf(names: &[&str]) -> KvMap {
+    let mut shortcuts: KvMap = names.iter().enumerate().map(| (index, name) | (index.to_string(), KvValue::Map(map(vec![
+      ("appid", KvValue::Int32(index as i32 + 1)),
+      ("AppName", KvValue::String(name.to_string())),
+    ])))).collect();
+    shortcuts.push(String::from("count"), KvValue::Int32(names.len() as i32));
+
+    return map(vec![("shortcuts", KvValue::Map(shortcuts))]);
+  }
+
+  #[test]
+  fn query_matches_every_key_for_wildcards() {
+    let shortcuts_vdf = get_shortcuts_vdf(&["Minecraft", "Terraria"]);
+
+    let matches: Vec<(String, Option<&str>)> = shortcuts_vdf.query("shortcuts/*/AppName").into_iter().map(| (path, value) | (path, value.as_str())).collect();
+    assert_eq!(matches, vec![
+      (String::from("shortcuts/0/AppName"), Some("Minecraft")),
+      (String::from("shortcuts/1/AppName"), Some("Terraria")),
+    ]);
+
+    assert_eq!(shortcuts_vdf.query("/shortcuts/1/appid/").len(), 1);
+    assert!(shortcuts_vdf.query("shortcuts/*/Exe").is_empty());
+    assert!(shortcuts_vdf.query("shortcuts/*/appname").is_empty());
+  }
+
+  #[test]
+  fn query_only_follows_the_first_duplicate_key() {
+    let data = map(vec![("key", KvValue::Int32(1)), ("key", KvValue::Int32(2))]);
+
+    assert_eq!(data.query("key").len(), 1);
+    assert_eq!(data.get_path("key"), Some(&KvValue::Int32(1)));
+    assert_eq!(data.query("*").len(), 2);
+  }
+
+  #[test]
+  fn query_mut_changes_every_match() {
+    let mut shortcuts_vdf = get_shortcuts_vdf(&["Minecraft", "Terraria"]);
+
+    for value in shortcuts_vdf.query_mut("shortcuts/*/AppName") {
+      *value = KvValue::String(format!("{} (Modded)", value.as_str().unwrap()));
+    }
+
+    assert_eq!(shortcuts_vdf.get_str_at("shortcuts/0/AppName").unwrap(), "Minecraft (Modded)");
+    assert_eq!(shortcuts_vdf.get_str_at("shortcuts/1/AppName").unwrap(), "Terraria (Modded)");
+
+    *shortcuts_vdf.get_path_mut("shortcuts/count").unwrap() = KvValue::Int32(5);
+    assert_eq!(shortcuts_vdf.get_u64_at("shortcuts/count").unwrap(), 5);
+  }
+
+  #[test]
+  fn query_ignore_case_keeps_the_file_casing() {
+    let shortcuts_vdf = get_shortcuts_vdf(&["Minecraft"]);
+
+    let matches: Vec<String> = shortcuts_vdf.query_ignore_case("Shortcuts/*/appname").into_iter().map(| (path, _) | path).collect();
+    assert_eq!(matches, vec![String::from("shortcuts/0/AppName")]);
+    assert_eq!(shortcuts_vdf.get_path_ignore_case("SHORTCUTS/0/APPID"), Some(&KvValue::Int32(1)));
+  }
+
+  #[test]
+  fn typed_getters_report_missing_keys() {
+    let shortcuts_vdf = get_shortcuts_vdf(&["Minecraft"]);
+
+    assert!(matches!(shortcuts_vdf.get_map_at("shortcuts/1"), Err(VdfError::MissingKey(path)) if path == "shortcuts/1"));
+    assert!(matches!(shortcuts_vdf.get_str_at("shortcuts/0/Exe"), Err(VdfError::MissingKey(path)) if path == "shortcuts/0/Exe"));
+    assert!(matches!(shortcuts_vdf.get_u64_at("shortcuts/0/LastPlayTime"), Err(VdfError::MissingKey(path)) if path == "shortcuts/0/LastPlayTime"));
+  }
+
+  #[test]
+  fn typed_getters_report_unexpected_types() {
+    let shortcuts_vdf = get_shortcuts_vdf(&["Minecraft"]);
+
+    assert!(shortcuts_vdf.get_map_at("shortcuts/0").is_ok());
+    assert!(matches!(
+      shortcuts_vdf.get_map_at("shortcuts/count"),
+      Err(VdfError::UnexpectedValueType { path, expected: "a Map", actual: FieldType::Int32 }) if path == "shortcuts/count"
+    ));
+    assert!(matches!(
+      shortcuts_vdf.get_str_at("shortcuts/0/appid"),
+      Err(VdfError::UnexpectedValueType { path, expected: "a String", actual: FieldType::Int32 }) if path == "shortcuts/0/appid"
+    ));
+    assert!(matches!(
+      shortcuts_vdf.get_u64_at("shortcuts/0/AppName"),
+      Err(VdfError::UnexpectedValueType { path, expected: "an unsigned number", actual: FieldType::String }) if path == "shortcuts/0/AppName"
+    ));
+  }
+}
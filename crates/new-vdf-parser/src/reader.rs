@@ -1,3 +1,7 @@
+use std::borrow::Cow;
+
+use crate::error::VdfError;
+
 trait HasByteConvert {
   fn from_le_bytes(bytes: &[u8], offset: usize) -> Self;
   fn from_be_bytes(bytes: &[u8], offset: usize) -> Self;
@@ -97,24 +101,29 @@ pub struct Reader<'a> {
   data: &'a [u8],
   offset: usize,
   length: u64,
+  base_offset: usize,
 }
 
 #[allow(dead_code)]
-impl Reader<'_> {
+impl<'a> Reader<'a> {
   /// Gets the underlying data of the reader.
-  pub fn get_data(&self) -> &[u8] { return self.data; }
+  pub fn get_data(&self) -> &'a [u8] { return self.data; }
   /// Gets the offset of the reader.
   pub fn get_offset(&self) -> usize { return self.offset; }
   /// Gets the length of the reader.
   pub fn get_length(&self) -> u64 { return self.length; }
+  /// Gets the offset of the reader relative to the start of the original buffer, even if this Reader is a slice.
+  pub fn get_absolute_offset(&self) -> usize { return self.base_offset + self.offset; }
 
   /// Creates a new Reader from the provided buffer.
-  pub fn new(buf: &[u8]) -> Reader<'_> { return Reader { data: buf, offset: 0, length: buf.len() as u64 }; }
+  pub fn new(buf: &'a [u8]) -> Reader<'a> { return Reader { data: buf, offset: 0, length: buf.len() as u64, base_offset: 0 }; }
 
   /// Slices the Reader's buffer and returns a new Reader for the slice.
-  pub fn slice(&self, offset: usize, length: usize) -> Reader<'_> {
+  pub fn slice(&self, offset: usize, length: usize) -> Result<Reader<'a>, VdfError> {
+    self.check_bounds(offset, length)?;
+
     let sliced = &self.data[offset..(offset+length)];
-    return Reader { data: sliced, offset: 0, length: length as u64 };
+    return Ok(Reader { data: sliced, offset: 0, length: length as u64, base_offset: self.base_offset + offset });
   }
 
   /// Seek to a new offset, from 0 (start), 1 (current), or 2 (end) of the buffer.
@@ -124,120 +133,216 @@ impl Reader<'_> {
     } else if position == 1 {
       self.offset += offset;
     } else {
-      self.offset = (self.length as usize) - offset;
+      self.offset = (self.length as usize).saturating_sub(offset);
     }
   }
   
   /// Gets the remaining length of the buffer.
-  pub fn remaining(&mut self) -> u64 { return self.length - (self.offset as u64); }
+  pub fn remaining(&mut self) -> u64 { return self.length.saturating_sub(self.offset as u64); }
+
+  /// Checks that `needed` bytes can be read starting at `offset`.
+  fn check_bounds(&self, offset: usize, needed: usize) -> Result<(), VdfError> {
+    let in_bounds = offset.checked_add(needed).is_some_and(| end | end <= self.data.len());
+
+    if !in_bounds {
+      return Err(VdfError::UnexpectedEof { offset: self.base_offset + offset, needed });
+    }
+
+    return Ok(());
+  }
 
   /// Data reading interface.
-  fn read_i<T: HasByteConvert>(&mut self, endianness: bool) -> T {
-    if endianness {
-      return T::from_le_bytes(self.data, self.offset);
+  fn read_i<T: HasByteConvert>(&mut self, endianness: bool) -> Result<T, VdfError> {
+    self.check_bounds(self.offset, size_of::<T>())?;
+
+    let res = if endianness {
+      T::from_le_bytes(self.data, self.offset)
     } else {
-      return T::from_be_bytes(self.data, self.offset);
-    }
+      T::from_be_bytes(self.data, self.offset)
+    };
+    self.offset += size_of::<T>();
+
+    return Ok(res);
   }
   
   /// Reads the next char from the buffer.
-  pub fn read_char(&mut self, endianness: bool) -> char {
-    return self.read_uint8(endianness) as char;
+  pub fn read_char(&mut self, endianness: bool) -> Result<char, VdfError> {
+    return Ok(self.read_uint8(endianness)? as char);
   }
   
   /// Reads the next 8 bit unsigned int from the buffer.
-  pub fn read_uint8(&mut self, endianness: bool) -> u8 {
-    let res = self.read_i::<u8>(endianness);
-    self.offset += 1;
-    return res;
+  pub fn read_uint8(&mut self, endianness: bool) -> Result<u8, VdfError> {
+    return self.read_i::<u8>(endianness);
   }
   /// Reads the next 16 bit unsigned int from the buffer.
-  pub fn read_uint16(&mut self, endianness: bool) -> u16 {
-    let res = self.read_i::<u16>(endianness);
-    self.offset += 2;
-    return res;
+  pub fn read_uint16(&mut self, endianness: bool) -> Result<u16, VdfError> {
+    return self.read_i::<u16>(endianness);
   }
   /// Reads the next 32 bit unsigned int from the buffer.
-  pub fn read_uint32(&mut self, endianness: bool) -> u32 {
-    let res = self.read_i::<u32>(endianness);
-    self.offset += 4;
-    return res;
+  pub fn read_uint32(&mut self, endianness: bool) -> Result<u32, VdfError> {
+    return self.read_i::<u32>(endianness);
   }
   /// Reads the next 64 bit unsigned int from the buffer.
-  pub fn read_uint64(&mut self, endianness: bool) -> u64 {
-    let res = self.read_i::<u64>(endianness);
-    self.offset += 8;
-    return res;
+  pub fn read_uint64(&mut self, endianness: bool) -> Result<u64, VdfError> {
+    return self.read_i::<u64>(endianness);
   }
   
   /// Reads the next 8 bit signed int from the buffer.
-  pub fn read_int8(&mut self, endianness: bool) -> i8 {
-    let res = self.read_i::<i8>(endianness);
-    self.offset += 1;
-    return res;
+  pub fn read_int8(&mut self, endianness: bool) -> Result<i8, VdfError> {
+    return self.read_i::<i8>(endianness);
   }
   /// Reads the next 16 bit signed int from the buffer.
-  pub fn read_int16(&mut self, endianness: bool) -> i16 {
-    let res = self.read_i::<i16>(endianness);
-    self.offset += 2;
-    return res;
+  pub fn read_int16(&mut self, endianness: bool) -> Result<i16, VdfError> {
+    return self.read_i::<i16>(endianness);
   }
   /// Reads the next 32 bit signed int from the buffer.
-  pub fn read_int32(&mut self, endianness: bool) -> i32 {
-    let res = self.read_i::<i32>(endianness);
-    self.offset += 4;
-    return res;
+  pub fn read_int32(&mut self, endianness: bool) -> Result<i32, VdfError> {
+    return self.read_i::<i32>(endianness);
   }
   /// Reads the next 64 bit signed int from the buffer.
-  pub fn read_int64(&mut self, endianness: bool) -> i64 {
-    let res = self.read_i::<i64>(endianness);
-    self.offset += 8;
-    return res;
+  pub fn read_int64(&mut self, endianness: bool) -> Result<i64, VdfError> {
+    return self.read_i::<i64>(endianness);
   }
   
   /// Reads the next 32 bit float from the buffer.
-  pub fn read_float32(&mut self, endianness: bool) -> f32 {
-    let res = self.read_i::<f32>(endianness);
-    self.offset += 4;
-    return res;
+  pub fn read_float32(&mut self, endianness: bool) -> Result<f32, VdfError> {
+    return self.read_i::<f32>(endianness);
   }
   /// Reads the next 64 bit float from the buffer.
-  pub fn read_float64(&mut self, endianness: bool) -> f64 {
-    let res = self.read_i::<f64>(endianness);
-    self.offset += 8;
-    return res;
+  pub fn read_float64(&mut self, endianness: bool) -> Result<f64, VdfError> {
+    return self.read_i::<f64>(endianness);
+  }
+
+  /// Reads the next `length` bytes from the buffer.
+  pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], VdfError> {
+    self.check_bounds(self.offset, length)?;
+
+    let bytes = &self.data[self.offset..self.offset+length];
+    self.offset += length;
+
+    return Ok(bytes);
+  }
+
+  /// Reads the next UTF-16LE string from the buffer, reading till the next 00 00 code unit.
+  pub fn read_wide_string(&mut self) -> Result<String, VdfError> {
+    let start = self.get_absolute_offset();
+    let mut code_units: Vec<u16> = Vec::new();
+
+    loop {
+      let code_unit = self.read_uint16(true).map_err(| _ | VdfError::UnexpectedEof { offset: start, needed: (code_units.len() + 1) * 2 })?;
+
+      if code_unit == 0 {
+        break;
+      }
+
+      code_units.push(code_unit);
+    }
+
+    return Ok(String::from_utf16_lossy(&code_units[..]));
+  }
+
+  /// Skips past the next 00 terminated string without decoding it.
+  pub fn skip_string(&mut self) -> Result<(), VdfError> {
+    let remaining = self.data.get(self.offset..).unwrap_or(&[]);
+
+    match remaining.iter().position(| byte | *byte == 0) {
+      Some(len) => self.offset += len + 1,
+      None => return Err(VdfError::UnexpectedEof { offset: self.get_absolute_offset(), needed: remaining.len() + 1 }),
+    }
+
+    return Ok(());
+  }
+
+  /// Skips past the next UTF-16LE string without decoding it.
+  pub fn skip_wide_string(&mut self) -> Result<(), VdfError> {
+    let start = self.get_absolute_offset();
+    let mut length: usize = 0;
+
+    while self.read_uint16(true).map_err(| _ | VdfError::UnexpectedEof { offset: start, needed: (length + 1) * 2 })? != 0 {
+      length += 1;
+    }
+
+    return Ok(());
   }
 
   /// Reads the next string from the buffer, using the provided length or reading till next 00 byte.
-  pub fn read_string(&mut self, length: Option<u32>) -> String {
-    let mut len: usize = 0;
+  /// A provided length doesn't include the terminator after the string, which is skipped either way.
+  pub fn read_string(&mut self, length: Option<u32>) -> Result<String, VdfError> {
+    return self.read_str(length).map(Cow::into_owned);
+  }
 
-    if length.is_some() {
-      len = length.unwrap() as usize;
-    } else {
-      loop {
-        if self.data[self.offset + len] == 0 {
-          break;
-        } else {
-          len += 1;
+  /// Reads the next string from the buffer like `read_string`, but borrows it from the buffer instead of copying it when it is valid UTF-8.
+  pub fn read_str(&mut self, length: Option<u32>) -> Result<Cow<'a, str>, VdfError> {
+    let len: usize = match length {
+      Some(length) => {
+        //? The terminator is skipped too, so it has to be in bounds as well.
+        let length = length as usize;
+        self.check_bounds(self.offset, length.saturating_add(1))?;
+        length
+      },
+      None => {
+        let remaining = self.data.get(self.offset..).unwrap_or(&[]);
+
+        match remaining.iter().position(| byte | *byte == 0) {
+          Some(len) => len,
+          None => return Err(VdfError::UnexpectedEof { offset: self.get_absolute_offset(), needed: remaining.len() + 1 }),
         }
       }
-    }
+    };
 
-    let u8_vec = self.data[self.offset..self.offset+len].to_vec();
-    
-    let utf8_res = String::from_utf8(u8_vec.clone());
+    let bytes: &'a [u8] = &self.data[self.offset..self.offset+len];
     self.offset += len + 1;
 
-    if utf8_res.is_ok() {
-      return utf8_res.unwrap();
-    } else {
-      let u16_vec: Vec<u16> = u8_vec.iter().map(| char_code | {
-        return char_code.to_owned() as u16;
-      }).collect();
-      let char_codes = &u16_vec[..];
-  
-      return String::from_utf16(char_codes).unwrap();
+    match std::str::from_utf8(bytes) {
+      Ok(string) => return Ok(Cow::Borrowed(string)),
+      Err(_) => {
+        let u16_vec: Vec<u16> = bytes.iter().map(| char_code | {
+          return char_code.to_owned() as u16;
+        }).collect();
+    
+        return Ok(Cow::Owned(String::from_utf16_lossy(&u16_vec[..])));
+      }
     }
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reads_terminated_strings() {
+    let mut reader = Reader::new(b"Portal\0Half-Life\0");
+
+    assert_eq!(reader.read_str(None).unwrap(), "Portal");
+    assert_eq!(reader.read_string(None).unwrap(), "Half-Life");
+    assert_eq!(reader.get_offset(), 17);
+    assert!(matches!(reader.read_str(None), Err(VdfError::UnexpectedEof { offset: 17, needed: 1 })));
+  }
+
+  #[test]
+  fn reads_strings_with_a_length() {
+    let mut reader = Reader::new(b"Portal\0Half");
+
+    assert_eq!(reader.read_str(Some(6)).unwrap(), "Portal");
+    assert_eq!(reader.get_offset(), 7);
+  }
+
+  #[test]
+  fn strings_with_a_length_need_their_terminator() {
+    let mut reader = Reader::new(b"Portal");
+
+    assert!(matches!(reader.read_str(Some(6)), Err(VdfError::UnexpectedEof { offset: 0, needed: 7 })));
+    assert_eq!(reader.get_offset(), 0);
+
+    let mut reader = Reader::new(b"Portal\0");
+    assert!(reader.read_str(Some(u32::MAX)).is_err());
+  }
+
+  #[test]
+  fn strings_that_arent_utf8_are_read_as_latin1() {
+    let mut reader = Reader::new(b"caf\xe9\0");
+
+    assert_eq!(reader.read_str(None).unwrap(), "caf\u{e9}");
+  }
+}
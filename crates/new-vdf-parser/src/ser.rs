@@ -0,0 +1,413 @@
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::error::VdfError;
+use crate::kv_value::{KvMap, KvValue};
+use crate::writer::Writer;
+use crate::vdf_writer::write_entry_map;
+
+impl ser::Error for VdfError {
+  fn custom<T: std::fmt::Display>(msg: T) -> VdfError {
+    return VdfError::InvalidData(msg.to_string());
+  }
+}
+
+/// Serializes a type to binary KeyValues, like the contents of shortcuts.vdf.
+/// `T` must serialize to a map, so for shortcuts.vdf it should have a `shortcuts` field.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, VdfError> {
+  let map = to_kv_map(value)?;
+
+  let mut writer: Writer<Vec<u8>> = Writer::new(Vec::new());
+  write_entry_map(&mut writer, &map, None)?;
+
+  return Ok(writer.into_inner());
+}
+
+/// Serializes a type to a KvMap.
+pub fn to_kv_map<T: Serialize + ?Sized>(value: &T) -> Result<KvMap, VdfError> {
+  match to_kv_value(value)? {
+    KvValue::Map(map) => return Ok(map),
+    other => return Err(VdfError::InvalidData(format!("Expected a map at the top level but got {:?}", other.field_type()))),
+  }
+}
+
+/// Serializes a type to a KvValue.
+/// Integers up to 32 bits become Int32, bools become Int32 0 or 1, and lists become maps with the keys "0", "1", "2"...
+pub fn to_kv_value<T: Serialize + ?Sized>(value: &T) -> Result<KvValue, VdfError> {
+  return value.serialize(ValueSerializer)?.ok_or(VdfError::InvalidData(String::from("Can't serialize a unit or None value on its own")));
+}
+
+/// Serializes a value to a KvValue. Units and `None` serialize to nothing, so they are left out of maps.
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+  type Ok = Option<KvValue>;
+  type Error = VdfError;
+
+  type SerializeSeq = SeqSerializer;
+  type SerializeTuple = SeqSerializer;
+  type SerializeTupleStruct = SeqSerializer;
+  type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+  type SerializeMap = MapSerializer;
+  type SerializeStruct = MapSerializer;
+  type SerializeStructVariant = VariantSerializer<MapSerializer>;
+
+  fn serialize_bool(self, v: bool) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Int32(v as i32))); }
+  fn serialize_i8(self, v: i8) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Int32(v as i32))); }
+  fn serialize_i16(self, v: i16) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Int32(v as i32))); }
+  fn serialize_i32(self, v: i32) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Int32(v))); }
+  fn serialize_i64(self, v: i64) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Int64(v))); }
+  fn serialize_u8(self, v: u8) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Int32(v as i32))); }
+  fn serialize_u16(self, v: u16) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Int32(v as i32))); }
+  fn serialize_u32(self, v: u32) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Int32(v as i32))); }
+  fn serialize_u64(self, v: u64) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::UInt64(v))); }
+  fn serialize_f32(self, v: f32) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Float32(v))); }
+  fn serialize_f64(self, v: f64) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Float32(v as f32))); }
+  fn serialize_char(self, v: char) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::String(v.to_string()))); }
+  fn serialize_str(self, v: &str) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::String(v.to_owned()))); }
+
+  fn serialize_bytes(self, v: &[u8]) -> Result<Option<KvValue>, VdfError> {
+    let mut seq = SeqSerializer { map: KvMap::new() };
+
+    for byte in v {
+      ser::SerializeSeq::serialize_element(&mut seq, byte)?;
+    }
+
+    return ser::SerializeSeq::end(seq);
+  }
+
+  fn serialize_none(self) -> Result<Option<KvValue>, VdfError> { return Ok(None); }
+
+  fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Option<KvValue>, VdfError> {
+    return value.serialize(self);
+  }
+
+  fn serialize_unit(self) -> Result<Option<KvValue>, VdfError> { return Ok(None); }
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<Option<KvValue>, VdfError> { return Ok(None); }
+
+  fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Option<KvValue>, VdfError> {
+    return Ok(Some(KvValue::String(variant.to_owned())));
+  }
+
+  fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Option<KvValue>, VdfError> {
+    return value.serialize(self);
+  }
+
+  fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<Option<KvValue>, VdfError> {
+    let mut map = KvMap::new();
+
+    if let Some(value) = value.serialize(ValueSerializer)? {
+      map.push(variant.to_owned(), value);
+    }
+
+    return Ok(Some(KvValue::Map(map)));
+  }
+
+  fn serialize_seq(self, _len: Option<usize>) -> Result<SeqSerializer, VdfError> {
+    return Ok(SeqSerializer { map: KvMap::new() });
+  }
+
+  fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, VdfError> {
+    return self.serialize_seq(Some(len));
+  }
+
+  fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer, VdfError> {
+    return self.serialize_seq(Some(len));
+  }
+
+  fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<VariantSerializer<SeqSerializer>, VdfError> {
+    return Ok(VariantSerializer { variant, inner: SeqSerializer { map: KvMap::new() } });
+  }
+
+  fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, VdfError> {
+    return Ok(MapSerializer { map: KvMap::new(), key: None });
+  }
+
+  fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer, VdfError> {
+    return self.serialize_map(Some(len));
+  }
+
+  fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<VariantSerializer<MapSerializer>, VdfError> {
+    return Ok(VariantSerializer { variant, inner: MapSerializer { map: KvMap::new(), key: None } });
+  }
+}
+
+/// Serializes a list as a map with the keys "0", "1", "2"...
+struct SeqSerializer {
+  map: KvMap,
+}
+
+impl SeqSerializer {
+  /// Adds an element, using its index as the key.
+  fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), VdfError> {
+    let key = self.map.len().to_string();
+
+    if let Some(value) = value.serialize(ValueSerializer)? {
+      self.map.push(key, value);
+    }
+
+    return Ok(());
+  }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+  type Ok = Option<KvValue>;
+  type Error = VdfError;
+
+  fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), VdfError> { return self.push(value); }
+  fn end(self) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Map(self.map))); }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+  type Ok = Option<KvValue>;
+  type Error = VdfError;
+
+  fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), VdfError> { return self.push(value); }
+  fn end(self) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Map(self.map))); }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+  type Ok = Option<KvValue>;
+  type Error = VdfError;
+
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), VdfError> { return self.push(value); }
+  fn end(self) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Map(self.map))); }
+}
+
+/// Serializes a map or struct, leaving out entries with unit or `None` values.
+struct MapSerializer {
+  map: KvMap,
+  key: Option<String>,
+}
+
+impl MapSerializer {
+  /// Adds an entry, unless its value serializes to nothing.
+  fn push<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), VdfError> {
+    if let Some(value) = value.serialize(ValueSerializer)? {
+      self.map.push(key, value);
+    }
+
+    return Ok(());
+  }
+}
+
+impl ser::SerializeMap for MapSerializer {
+  type Ok = Option<KvValue>;
+  type Error = VdfError;
+
+  fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), VdfError> {
+    self.key = Some(key.serialize(KeySerializer)?);
+    return Ok(());
+  }
+
+  fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), VdfError> {
+    let key = self.key.take().ok_or(VdfError::InvalidData(String::from("Value was serialized before its key")))?;
+    return self.push(key, value);
+  }
+
+  fn end(self) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Map(self.map))); }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+  type Ok = Option<KvValue>;
+  type Error = VdfError;
+
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), VdfError> {
+    return self.push(key.to_owned(), value);
+  }
+
+  fn end(self) -> Result<Option<KvValue>, VdfError> { return Ok(Some(KvValue::Map(self.map))); }
+}
+
+/// Serializes an enum variant with data as a map with the variant name as its only key.
+struct VariantSerializer<T> {
+  variant: &'static str,
+  inner: T,
+}
+
+impl VariantSerializer<SeqSerializer> {
+  fn end(self) -> Result<Option<KvValue>, VdfError> {
+    let mut map = KvMap::new();
+    map.push(self.variant.to_owned(), KvValue::Map(self.inner.map));
+    return Ok(Some(KvValue::Map(map)));
+  }
+}
+
+impl VariantSerializer<MapSerializer> {
+  fn end(self) -> Result<Option<KvValue>, VdfError> {
+    let mut map = KvMap::new();
+    map.push(self.variant.to_owned(), KvValue::Map(self.inner.map));
+    return Ok(Some(KvValue::Map(map)));
+  }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+  type Ok = Option<KvValue>;
+  type Error = VdfError;
+
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), VdfError> { return self.inner.push(value); }
+  fn end(self) -> Result<Option<KvValue>, VdfError> { return VariantSerializer::<SeqSerializer>::end(self); }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer> {
+  type Ok = Option<KvValue>;
+  type Error = VdfError;
+
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), VdfError> {
+    return self.inner.push(key.to_owned(), value);
+  }
+
+  fn end(self) -> Result<Option<KvValue>, VdfError> { return VariantSerializer::<MapSerializer>::end(self); }
+}
+
+/// Serializes map keys, which must be strings, chars or integers.
+struct KeySerializer;
+
+macro_rules! serialize_key_to_string {
+  ($($method:ident: $type:ty),*) => {
+    $(fn $method(self, v: $type) -> Result<String, VdfError> { return Ok(v.to_string()); })*
+  };
+}
+
+impl ser::Serializer for KeySerializer {
+  type Ok = String;
+  type Error = VdfError;
+
+  type SerializeSeq = Impossible<String, VdfError>;
+  type SerializeTuple = Impossible<String, VdfError>;
+  type SerializeTupleStruct = Impossible<String, VdfError>;
+  type SerializeTupleVariant = Impossible<String, VdfError>;
+  type SerializeMap = Impossible<String, VdfError>;
+  type SerializeStruct = Impossible<String, VdfError>;
+  type SerializeStructVariant = Impossible<String, VdfError>;
+
+  serialize_key_to_string!(
+    serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+    serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64,
+    serialize_char: char, serialize_str: &str
+  );
+
+  fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<String, VdfError> {
+    return Ok(variant.to_owned());
+  }
+
+  fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String, VdfError> {
+    return value.serialize(self);
+  }
+
+  fn serialize_bool(self, _v: bool) -> Result<String, VdfError> { return Err(key_error()); }
+  fn serialize_f32(self, _v: f32) -> Result<String, VdfError> { return Err(key_error()); }
+  fn serialize_f64(self, _v: f64) -> Result<String, VdfError> { return Err(key_error()); }
+  fn serialize_bytes(self, _v: &[u8]) -> Result<String, VdfError> { return Err(key_error()); }
+  fn serialize_none(self) -> Result<String, VdfError> { return Err(key_error()); }
+  fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String, VdfError> { return Err(key_error()); }
+  fn serialize_unit(self) -> Result<String, VdfError> { return Err(key_error()); }
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<String, VdfError> { return Err(key_error()); }
+
+  fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<String, VdfError> {
+    return Err(key_error());
+  }
+
+  fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, VdfError> { return Err(key_error()); }
+  fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, VdfError> { return Err(key_error()); }
+
+  fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, VdfError> {
+    return Err(key_error());
+  }
+
+  fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, VdfError> {
+    return Err(key_error());
+  }
+
+  fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, VdfError> { return Err(key_error()); }
+  fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, VdfError> { return Err(key_error()); }
+
+  fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, VdfError> {
+    return Err(key_error());
+  }
+}
+
+/// Creates the error for a map key that can't be written as a string.
+fn key_error() -> VdfError {
+  return VdfError::InvalidData(String::from("Map keys must be strings, chars or integers"));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::BTreeMap;
+
+  use serde::Serialize;
+
+  use crate::field_type::FieldType;
+
+  #[derive(Serialize)]
+  #[serde(rename_all = "PascalCase")]
+  struct Shortcut {
+    appid: u32,
+    app_name: String,
+    is_hidden: bool,
+    last_play_time: i32,
+    icon: Option<String>,
+    tags: Vec<String>,
+    size: u64,
+    scale: f32,
+  }
+
+  /// Builds a shortcut with every kind of field.
+  fn get_shortcut() -> Shortcut {
+    return Shortcut {
+      appid: 3000000001,
+      app_name: String::from("Minecraft"),
+      is_hidden: true,
+      last_play_time: -1,
+      icon: None,
+      tags: vec![String::from("Favorite"), String::from("Sandbox")],
+      size: 1 << 40,
+      scale: 1.5,
+    };
+  }
+
+  #[test]
+  fn struct_fields_get_steam_types() {
+    let map = to_kv_map(&get_shortcut()).unwrap();
+
+    assert_eq!(map.keys().map(String::as_str).collect::<Vec<&str>>(), vec!["Appid", "AppName", "IsHidden", "LastPlayTime", "Tags", "Size", "Scale"]);
+    assert_eq!(map.get("Appid"), Some(&KvValue::Int32(3000000001u32 as i32)));
+    assert_eq!(map.get("IsHidden"), Some(&KvValue::Int32(1)));
+    assert_eq!(map.get("LastPlayTime"), Some(&KvValue::Int32(-1)));
+    assert_eq!(map.get("Size").map(KvValue::field_type), Some(FieldType::UInt64));
+    assert_eq!(map.get("Scale"), Some(&KvValue::Float32(1.5)));
+  }
+
+  #[test]
+  fn none_fields_are_left_out() {
+    let mut shortcut = get_shortcut();
+    assert!(!to_kv_map(&shortcut).unwrap().contains_key("Icon"));
+
+    shortcut.icon = Some(String::from("icon.png"));
+    assert_eq!(to_kv_map(&shortcut).unwrap().get("Icon"), Some(&KvValue::String(String::from("icon.png"))));
+  }
+
+  #[test]
+  fn lists_are_indexed_maps() {
+    let map = to_kv_map(&get_shortcut()).unwrap();
+    let tags = map.get("Tags").and_then(KvValue::as_map).unwrap();
+
+    assert_eq!(tags.iter().map(| (index, tag) | (index.as_str(), tag.as_str().unwrap())).collect::<Vec<(&str, &str)>>(), vec![("0", "Favorite"), ("1", "Sandbox")]);
+  }
+
+  #[test]
+  fn maps_need_string_keys() {
+    let numbered: BTreeMap<u32, &str> = BTreeMap::from([(7, "seven")]);
+    assert_eq!(to_kv_map(&numbered).unwrap().get("7"), Some(&KvValue::String(String::from("seven"))));
+
+    let bool_keys: BTreeMap<String, BTreeMap<bool, i32>> = BTreeMap::from([(String::from("nested"), BTreeMap::from([(true, 1)]))]);
+    assert!(to_kv_map(&bool_keys).is_err());
+  }
+
+  #[test]
+  fn top_level_values_must_be_maps() {
+    assert!(to_vec(&5).is_err());
+    assert!(to_kv_map("Minecraft").is_err());
+  }
+}
@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ShortcutsProblem, VdfError};
+use crate::field_type::FieldType;
+use crate::kv_value::{KvMap, KvValue};
+use crate::shortcuts_vdf_parser::{open_shortcuts_vdf, write_shortcuts_vdf};
+
+/// A non Steam game in the shortcuts.vdf file.
+/// Keys this struct doesn't have, like "ShortcutPath" and "FlatpakAppID", are kept from the file and written back unchanged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Shortcut {
+  pub appid: u32,
+  pub app_name: String,
+  pub exe: String,
+  pub start_dir: String,
+  pub icon: String,
+  pub launch_options: String,
+  pub is_hidden: bool,
+  pub allow_overlay: bool,
+  #[serde(rename = "openVR")]
+  pub open_vr: bool,
+  pub tags: Vec<String>,
+  /// When the shortcut was last played, as a unix timestamp. Zero if it never has been.
+  pub last_play_time: u32,
+  /// The shortcut's data as it was read, which its fields are written over.
+  #[serde(skip, default = "get_default_data")]
+  data: KvMap,
+}
+
+impl Default for Shortcut {
+  fn default() -> Shortcut {
+    return Shortcut {
+      appid: 0,
+      app_name: String::new(),
+      exe: String::new(),
+      start_dir: String::new(),
+      icon: String::new(),
+      launch_options: String::new(),
+      is_hidden: false,
+      allow_overlay: true,
+      open_vr: false,
+      tags: Vec::new(),
+      last_play_time: 0,
+      data: get_default_data(),
+    };
+  }
+}
+
+/// Gets the data of a new shortcut, with every key Steam writes in the order it writes them.
+fn get_default_data() -> KvMap {
+  let mut data = KvMap::new();
+
+  data.push(String::from("appid"), KvValue::Int32(0));
+  data.push(String::from("AppName"), KvValue::String(String::new()));
+  data.push(String::from("Exe"), KvValue::String(String::new()));
+  data.push(String::from("StartDir"), KvValue::String(String::new()));
+  data.push(String::from("icon"), KvValue::String(String::new()));
+  data.push(String::from("ShortcutPath"), KvValue::String(String::new()));
+  data.push(String::from("LaunchOptions"), KvValue::String(String::new()));
+  data.push(String::from("IsHidden"), KvValue::Int32(0));
+  data.push(String::from("AllowDesktopConfig"), KvValue::Int32(1));
+  data.push(String::from("AllowOverlay"), KvValue::Int32(1));
+  data.push(String::from("OpenVR"), KvValue::Int32(0));
+  data.push(String::from("Devkit"), KvValue::Int32(0));
+  data.push(String::from("DevkitGameID"), KvValue::String(String::new()));
+  data.push(String::from("DevkitOverrideAppID"), KvValue::Int32(0));
+  data.push(String::from("LastPlayTime"), KvValue::Int32(0));
+  data.push(String::from("FlatpakAppID"), KvValue::String(String::new()));
+  data.push(String::from("tags"), KvValue::Map(KvMap::new()));
+
+  return data;
+}
+
+/// The bits Steam sets on the crc of a shortcut's exe and name to make its appid.
+const SHORTCUT_APPID_FLAG: u32 = 0x80000000;
+/// The bits Steam sets on the low half of a shortcut's 64 bit gameid, which mark it as a shortcut.
+const SHORTCUT_GAMEID_FLAG: u64 = 0x02000000;
+
+/// Gets the appid Steam gives a shortcut, which is the CRC32 of its exe and name with the high bit set.
+/// `exe` should be exactly as it is stored in shortcuts.vdf, including any quotes around it.
+/// Existing shortcuts keep the appid stored in their "appid" key even if their exe or name changes, so this is only for new ones.
+pub fn get_shortcut_appid(exe: &str, app_name: &str) -> u32 {
+  let mut hasher = crc32fast::Hasher::new();
+  hasher.update(exe.as_bytes());
+  hasher.update(app_name.as_bytes());
+
+  return hasher.finalize() | SHORTCUT_APPID_FLAG;
+}
+
+/// Gets the appid of a shortcut from its map in shortcuts.vdf. Older files don't store one, so it is computed from "Exe" and "AppName" like Steam does.
+/// Keys are matched ignoring case. Returns `None` if the appid isn't a number, or it is missing along with "Exe" or "AppName".
+pub fn get_shortcut_map_appid(shortcut: &KvMap) -> Option<u32> {
+  if let Some(appid) = shortcut.get_ignore_case("appid") {
+    return appid.as_u32();
+  }
+
+  let exe = shortcut.get_ignore_case("Exe")?.as_str()?;
+  let app_name = shortcut.get_ignore_case("AppName")?.as_str()?;
+
+  return Some(get_shortcut_appid(exe, app_name));
+}
+
+/// Gets the legacy 64 bit gameid of a shortcut from its appid, which is what `steam://rungameid/` URLs use.
+pub fn get_shortcut_gameid(appid: u32) -> u64 {
+  return ((appid as u64) << 32) | SHORTCUT_GAMEID_FLAG;
+}
+
+/// Gets the URL that launches a shortcut through Steam.
+pub fn get_shortcut_run_url(appid: u32) -> String {
+  return format!("steam://rungameid/{}", get_shortcut_gameid(appid));
+}
+
+/// Gets the start of the file names of a shortcut's grids, like `"{stem}p.png"` for its capsule and `"{stem}_hero.png"` for its hero.
+/// Steam names them after the 32 bit appid, not the gameid.
+pub fn get_shortcut_grid_stem(appid: u32) -> String {
+  return appid.to_string();
+}
+
+/// Sets a key in a shortcut's data, keeping the casing the file already uses for it. Older files use keys like "appname" and "exe".
+fn set_key(data: &mut KvMap, key: &str, value: KvValue) {
+  let existing_key = data.keys().find(| existing_key | existing_key.eq_ignore_ascii_case(key)).cloned();
+
+  data.insert(existing_key.unwrap_or(key.to_owned()), value);
+}
+
+impl Shortcut {
+  /// Creates a new shortcut, with the appid Steam would give it.
+  pub fn new(app_name: &str, exe: &str, start_dir: &str) -> Shortcut {
+    return Shortcut {
+      appid: get_shortcut_appid(exe, app_name),
+      app_name: app_name.to_owned(),
+      exe: exe.to_owned(),
+      start_dir: start_dir.to_owned(),
+      ..Shortcut::default()
+    };
+  }
+
+  /// Gets the shortcut's legacy 64 bit gameid.
+  pub fn get_gameid(&self) -> u64 {
+    return get_shortcut_gameid(self.appid);
+  }
+
+  /// Gets the URL that launches the shortcut through Steam.
+  pub fn get_run_url(&self) -> String {
+    return get_shortcut_run_url(self.appid);
+  }
+
+  /// Gets the start of the file names of the shortcut's grids.
+  pub fn get_grid_stem(&self) -> String {
+    return get_shortcut_grid_stem(self.appid);
+  }
+
+  /// Reads a shortcut from its map in the shortcuts.vdf file. Missing keys get the same defaults Steam uses.
+  pub fn from_kv_map(data: &KvMap) -> Shortcut {
+    let get_str = | key: &str | data.get_ignore_case(key).and_then(KvValue::as_str).unwrap_or_default().to_owned();
+    let get_number = | key: &str, default: u32 | data.get_ignore_case(key).and_then(KvValue::as_u32).unwrap_or(default);
+
+    let tags = data.get_ignore_case("tags").and_then(KvValue::as_map)
+      .map(| tags | tags.values().filter_map(KvValue::as_str).map(str::to_owned).collect())
+      .unwrap_or_default();
+
+    return Shortcut {
+      appid: get_number("appid", 0),
+      app_name: get_str("AppName"),
+      exe: get_str("Exe"),
+      start_dir: get_str("StartDir"),
+      icon: get_str("icon"),
+      launch_options: get_str("LaunchOptions"),
+      is_hidden: get_number("IsHidden", 0) != 0,
+      allow_overlay: get_number("AllowOverlay", 1) != 0,
+      open_vr: get_number("OpenVR", 0) != 0,
+      tags,
+      last_play_time: get_number("LastPlayTime", 0),
+      data: data.to_owned(),
+    };
+  }
+
+  /// Converts the shortcut to its map in the shortcuts.vdf file.
+  /// Only the fields that changed since the shortcut was read are written, so unchanged shortcuts are written back exactly as they were.
+  pub fn to_kv_map(&self) -> KvMap {
+    let original = Shortcut::from_kv_map(&self.data);
+    let mut data = self.data.clone();
+
+    //? Steam writes appids and timestamps as Int32s, even though they are unsigned.
+    if self.appid != original.appid { set_key(&mut data, "appid", KvValue::Int32(self.appid as i32)); }
+    if self.app_name != original.app_name { set_key(&mut data, "AppName", KvValue::String(self.app_name.to_owned())); }
+    if self.exe != original.exe { set_key(&mut data, "Exe", KvValue::String(self.exe.to_owned())); }
+    if self.start_dir != original.start_dir { set_key(&mut data, "StartDir", KvValue::String(self.start_dir.to_owned())); }
+    if self.icon != original.icon { set_key(&mut data, "icon", KvValue::String(self.icon.to_owned())); }
+    if self.launch_options != original.launch_options { set_key(&mut data, "LaunchOptions", KvValue::String(self.launch_options.to_owned())); }
+    if self.is_hidden != original.is_hidden { set_key(&mut data, "IsHidden", KvValue::Int32(self.is_hidden as i32)); }
+    if self.allow_overlay != original.allow_overlay { set_key(&mut data, "AllowOverlay", KvValue::Int32(self.allow_overlay as i32)); }
+    if self.open_vr != original.open_vr { set_key(&mut data, "OpenVR", KvValue::Int32(self.open_vr as i32)); }
+    if self.last_play_time != original.last_play_time { set_key(&mut data, "LastPlayTime", KvValue::Int32(self.last_play_time as i32)); }
+
+    if self.tags != original.tags {
+      let tags: KvMap = self.tags.iter().enumerate().map(| (i, tag) | (i.to_string(), KvValue::String(tag.to_owned()))).collect();
+      set_key(&mut data, "tags", KvValue::Map(tags));
+    }
+
+    return data;
+  }
+}
+
+/// The shortcuts in a shortcuts.vdf file, along with the index each one is stored under.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ShortcutsFile {
+  entries: Vec<(String, Shortcut)>,
+}
+
+impl ShortcutsFile {
+  /// Creates a new ShortcutsFile with no shortcuts.
+  pub fn new() -> ShortcutsFile {
+    return ShortcutsFile { entries: Vec::new() };
+  }
+
+  /// Opens a shortcuts.vdf file.
+  pub fn open(path: &PathBuf) -> Result<ShortcutsFile, VdfError> {
+    return ShortcutsFile::from_kv_map(&open_shortcuts_vdf(path)?);
+  }
+
+  /// Reads the shortcuts from the contents of a shortcuts.vdf file, including the top level "shortcuts" map.
+  pub fn from_kv_map(shortcuts_vdf: &KvMap) -> Result<ShortcutsFile, VdfError> {
+    let shortcuts = shortcuts_vdf.get_map_at("shortcuts")?;
+    let mut entries = Vec::with_capacity(shortcuts.len());
+
+    for (index, shortcut) in shortcuts.iter() {
+      let shortcut_map = shortcut.as_map().ok_or(VdfError::InvalidData(format!("Shortcut {} was not a map", index)))?;
+      entries.push((index.to_owned(), Shortcut::from_kv_map(shortcut_map)));
+    }
+
+    return Ok(ShortcutsFile { entries });
+  }
+
+  /// Converts the shortcuts to the contents of a shortcuts.vdf file, including the top level "shortcuts" map.
+  pub fn to_kv_map(&self) -> KvMap {
+    let shortcuts: KvMap = self.entries.iter().map(| (index, shortcut) | (index.to_owned(), KvValue::Map(shortcut.to_kv_map()))).collect();
+
+    let mut shortcuts_vdf = KvMap::new();
+    shortcuts_vdf.insert(String::from("shortcuts"), KvValue::Map(shortcuts));
+
+    return shortcuts_vdf;
+  }
+
+  /// Writes the shortcuts to a shortcuts.vdf file.
+  pub fn write(&self, path: &PathBuf) -> Result<(), VdfError> {
+    return write_shortcuts_vdf(path, &self.to_kv_map());
+  }
+
+  /// Gets the number of shortcuts.
+  pub fn len(&self) -> usize { return self.entries.len(); }
+  /// Checks if there are no shortcuts.
+  pub fn is_empty(&self) -> bool { return self.entries.is_empty(); }
+
+  /// Iterates over the shortcuts in file order.
+  pub fn shortcuts(&self) -> impl Iterator<Item = &Shortcut> {
+    return self.entries.iter().map(| (_, shortcut) | shortcut);
+  }
+
+  /// Gets the shortcut with the provided appid.
+  pub fn get(&self, appid: u32) -> Option<&Shortcut> {
+    return self.shortcuts().find(| shortcut | shortcut.appid == appid);
+  }
+
+  /// Adds a shortcut after the last one, under the index after the highest one in use.
+  /// Shortcuts with an appid of 0 are given the one Steam would give them, so their grids are picked up.
+  pub fn add(&mut self, mut shortcut: Shortcut) -> Result<(), VdfError> {
+    if shortcut.appid == 0 {
+      shortcut.appid = get_shortcut_appid(&shortcut.exe, &shortcut.app_name);
+    }
+
+    if self.get(shortcut.appid).is_some() {
+      return Err(VdfError::InvalidData(format!("A shortcut with appid {} already exists", shortcut.appid)));
+    }
+
+    let next_index = self.entries.iter().filter_map(| (index, _) | index.parse::<usize>().ok()).max().map_or(0, | index | index + 1);
+    self.entries.push((next_index.to_string(), shortcut));
+
+    return Ok(());
+  }
+
+  /// Removes the shortcut with the provided appid and returns it. The other shortcuts keep their indexes, so use `renumber` to close the gap.
+  pub fn remove(&mut self, appid: u32) -> Option<Shortcut> {
+    let position = self.entries.iter().position(| (_, shortcut) | shortcut.appid == appid)?;
+
+    return Some(self.entries.remove(position).1);
+  }
+
+  /// Replaces the fields of the shortcut with the same appid. Keys that `Shortcut` doesn't have are kept from the existing shortcut.
+  pub fn update(&mut self, shortcut: Shortcut) -> Result<(), VdfError> {
+    let existing = self.entries.iter_mut().map(| (_, existing) | existing).find(| existing | existing.appid == shortcut.appid)
+      .ok_or(VdfError::InvalidData(format!("There is no shortcut with appid {}", shortcut.appid)))?;
+
+    let data = std::mem::take(&mut existing.data);
+    *existing = Shortcut { data, ..shortcut };
+
+    return Ok(());
+  }
+
+  /// Stores the shortcuts under the indexes "0", "1", "2"... in their current order, like Steam does.
+  pub fn renumber(&mut self) {
+    for (i, (index, _)) in self.entries.iter_mut().enumerate() {
+      *index = i.to_string();
+    }
+  }
+}
+
+/// The keys every shortcut needs for Steam to show it.
+/// Older files don't store an "appid", since Steam computes it from "Exe" and "AppName", so it is only checked when it is there.
+const REQUIRED_SHORTCUT_KEYS: [&str; 3] = ["AppName", "Exe", "StartDir"];
+
+/// The types of the keys Steam writes for each shortcut. Other keys are allowed with any type.
+const SHORTCUT_KEY_TYPES: [(&str, FieldType); 17] = [
+  ("appid", FieldType::Int32),
+  ("AppName", FieldType::String),
+  ("Exe", FieldType::String),
+  ("StartDir", FieldType::String),
+  ("icon", FieldType::String),
+  ("ShortcutPath", FieldType::String),
+  ("LaunchOptions", FieldType::String),
+  ("IsHidden", FieldType::Int32),
+  ("AllowDesktopConfig", FieldType::Int32),
+  ("AllowOverlay", FieldType::Int32),
+  ("OpenVR", FieldType::Int32),
+  ("Devkit", FieldType::Int32),
+  ("DevkitGameID", FieldType::String),
+  ("DevkitOverrideAppID", FieldType::Int32),
+  ("LastPlayTime", FieldType::Int32),
+  ("FlatpakAppID", FieldType::String),
+  ("tags", FieldType::Map),
+];
+
+/// Checks shortcuts.vdf data against the shape Steam expects, including the top level "shortcuts" map. Returns every problem found.
+/// Key names are matched ignoring case, since older files use keys like "appname" and "exe".
+pub fn validate_shortcuts_vdf(shortcuts_vdf: &KvMap) -> Vec<ShortcutsProblem> {
+  let mut problems = Vec::new();
+
+  for key in shortcuts_vdf.keys().filter(| key | !key.eq_ignore_ascii_case("shortcuts")) {
+    problems.push(ShortcutsProblem::UnexpectedKey { key: key.to_owned() });
+  }
+
+  let shortcuts = match shortcuts_vdf.get_ignore_case("shortcuts").and_then(KvValue::as_map) {
+    Some(shortcuts) => shortcuts,
+    None => {
+      problems.push(ShortcutsProblem::MissingShortcuts);
+      return problems;
+    }
+  };
+
+  let mut appid_indexes: HashMap<u32, &String> = HashMap::new();
+
+  for (i, (index, shortcut)) in shortcuts.iter().enumerate() {
+    if *index != i.to_string() {
+      problems.push(ShortcutsProblem::UnexpectedIndex { index: index.to_owned(), expected: i.to_string() });
+    }
+
+    let shortcut = match shortcut.as_map() {
+      Some(shortcut) => shortcut,
+      None => {
+        problems.push(ShortcutsProblem::NotAMap { index: index.to_owned() });
+        continue;
+      }
+    };
+
+    for key in REQUIRED_SHORTCUT_KEYS {
+      if shortcut.get_ignore_case(key).is_none() {
+        problems.push(ShortcutsProblem::MissingKey { index: index.to_owned(), key: key.to_owned() });
+      }
+    }
+
+    for (key, expected) in SHORTCUT_KEY_TYPES {
+      if let Some(value) = shortcut.get_ignore_case(key) {
+        if value.field_type() != expected {
+          problems.push(ShortcutsProblem::WrongType { index: index.to_owned(), key: key.to_owned(), expected: format!("{:?}", expected), actual: format!("{:?}", value.field_type()) });
+        }
+      }
+    }
+
+    if let Some(tags) = shortcut.get_ignore_case("tags").and_then(KvValue::as_map) {
+      for (tag_index, tag) in tags.iter().filter(| (_, tag) | tag.field_type() != FieldType::String) {
+        problems.push(ShortcutsProblem::WrongType { index: index.to_owned(), key: format!("tags/{}", tag_index), expected: format!("{:?}", FieldType::String), actual: format!("{:?}", tag.field_type()) });
+      }
+    }
+
+    if let Some(appid) = get_shortcut_map_appid(shortcut) {
+      match appid_indexes.get(&appid) {
+        Some(original) => problems.push(ShortcutsProblem::DuplicateAppid { index: index.to_owned(), appid, original: original.to_string() }),
+        None => {
+          appid_indexes.insert(appid, index);
+        },
+      }
+    }
+  }
+
+  return problems;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Builds a shortcut's data with the keys Steam needs.
+  fn get_shortcut_data(appid: Option<u32>, app_name: &str) -> KvMap {
+    let mut data = KvMap::new();
+
+    if let Some(appid) = appid {
+      data.push(String::from("appid"), KvValue::Int32(appid as i32));
+    }
+
+    data.push(String::from("AppName"), KvValue::String(app_name.to_owned()));
+    data.push(String::from("Exe"), KvValue::String(format!("\"/usr/bin/{}\"", app_name.to_lowercase())));
+    data.push(String::from("StartDir"), KvValue::String(String::from("\"/usr/bin/\"")));
+
+    return data;
+  }
+
+  /// Builds shortcuts.vdf data from shortcuts under the provided indexes.
+  fn get_shortcuts_vdf(shortcuts: Vec<(&str, KvValue)>) -> KvMap {
+    let mut shortcuts_vdf = KvMap::new();
+    shortcuts_vdf.push(String::from("shortcuts"), KvValue::Map(shortcuts.into_iter().map(| (index, shortcut) | (index.to_owned(), shortcut)).collect()));
+
+    return shortcuts_vdf;
+  }
+
+  #[test]
+  fn ids_match_steams() {
+    //? The CRC32 of the exe and name is 0x2028c2f8, so this also checks the high bit gets set.
+    let appid = get_shortcut_appid("\"C:\\Program Files\\Mozilla Firefox\\firefox.exe\"", "Firefox");
+
+    assert_eq!(appid, 2687025912);
+    assert_eq!(appid, 0xa028c2f8);
+    assert_eq!(get_shortcut_gameid(appid), 11540688415578128384);
+    assert_eq!(get_shortcut_gameid(appid), (0xa028c2f8 << 32) | 0x02000000);
+    assert_eq!(get_shortcut_run_url(appid), "steam://rungameid/11540688415578128384");
+    assert_eq!(get_shortcut_grid_stem(appid), "2687025912");
+
+    assert_eq!(get_shortcut_appid("\"/usr/bin/firefox\"", "Firefox"), 2910723666);
+  }
+
+  #[test]
+  fn map_appids_are_stored_or_computed() {
+    let minecraft_appid = get_shortcut_appid("\"/usr/bin/minecraft\"", "Minecraft");
+
+    assert_eq!(get_shortcut_map_appid(&get_shortcut_data(Some(3000000001), "Minecraft")), Some(3000000001));
+    assert_eq!(get_shortcut_map_appid(&get_shortcut_data(None, "Minecraft")), Some(minecraft_appid));
+
+    let mut older = KvMap::new();
+    older.push(String::from("appname"), KvValue::String(String::from("Minecraft")));
+    older.push(String::from("exe"), KvValue::String(String::from("\"/usr/bin/minecraft\"")));
+    assert_eq!(get_shortcut_map_appid(&older), Some(minecraft_appid));
+
+    older.remove("exe");
+    assert_eq!(get_shortcut_map_appid(&older), None);
+  }
+
+  #[test]
+  fn valid_shortcuts_have_no_problems() {
+    let shortcuts_vdf = get_shortcuts_vdf(vec![
+      ("0", KvValue::Map(get_shortcut_data(Some(3000000001), "Minecraft"))),
+      ("1", KvValue::Map(get_shortcut_data(Some(3000000002), "Terraria"))),
+    ]);
+
+    assert!(validate_shortcuts_vdf(&shortcuts_vdf).is_empty());
+  }
+
+  #[test]
+  fn shortcuts_without_an_appid_are_valid() {
+    let mut older = KvMap::new();
+    older.push(String::from("appname"), KvValue::String(String::from("Minecraft")));
+    older.push(String::from("exe"), KvValue::String(String::from("\"/usr/bin/minecraft\"")));
+    older.push(String::from("StartDir"), KvValue::String(String::from("\"/usr/bin/\"")));
+
+    let shortcuts_vdf = get_shortcuts_vdf(vec![("0", KvValue::Map(older)), ("1", KvValue::Map(get_shortcut_data(None, "Terraria")))]);
+
+    assert!(validate_shortcuts_vdf(&shortcuts_vdf).is_empty());
+  }
+
+  #[test]
+  fn missing_shortcuts_and_unexpected_keys_are_problems() {
+    let mut shortcuts_vdf = KvMap::new();
+    shortcuts_vdf.push(String::from("Shortcut"), KvValue::Map(KvMap::new()));
+
+    assert_eq!(validate_shortcuts_vdf(&shortcuts_vdf), vec![
+      ShortcutsProblem::UnexpectedKey { key: String::from("Shortcut") },
+      ShortcutsProblem::MissingShortcuts,
+    ]);
+  }
+
+  #[test]
+  fn shortcuts_that_arent_maps_are_problems() {
+    let shortcuts_vdf = get_shortcuts_vdf(vec![("0", KvValue::String(String::from("Minecraft")))]);
+
+    assert_eq!(validate_shortcuts_vdf(&shortcuts_vdf), vec![ShortcutsProblem::NotAMap { index: String::from("0") }]);
+  }
+
+  #[test]
+  fn gaps_in_indexes_are_problems() {
+    let shortcuts_vdf = get_shortcuts_vdf(vec![
+      ("0", KvValue::Map(get_shortcut_data(Some(3000000001), "Minecraft"))),
+      ("2", KvValue::Map(get_shortcut_data(Some(3000000002), "Terraria"))),
+    ]);
+
+    assert_eq!(validate_shortcuts_vdf(&shortcuts_vdf), vec![ShortcutsProblem::UnexpectedIndex { index: String::from("2"), expected: String::from("1") }]);
+  }
+
+  #[test]
+  fn missing_keys_are_problems() {
+    let mut shortcut = get_shortcut_data(Some(3000000001), "Minecraft");
+    shortcut.remove("Exe");
+
+    let problems = validate_shortcuts_vdf(&get_shortcuts_vdf(vec![("0", KvValue::Map(shortcut))]));
+    assert_eq!(problems, vec![ShortcutsProblem::MissingKey { index: String::from("0"), key: String::from("Exe") }]);
+    assert_eq!(problems[0].to_string(), "Shortcut 0 is missing \"Exe\"");
+  }
+
+  #[test]
+  fn wrong_types_are_problems() {
+    let mut shortcut = get_shortcut_data(None, "Minecraft");
+    shortcut.push(String::from("appid"), KvValue::String(String::from("3000000001")));
+    shortcut.push(String::from("tags"), KvValue::Map([(String::from("0"), KvValue::Int32(1))].into_iter().collect()));
+
+    assert_eq!(validate_shortcuts_vdf(&get_shortcuts_vdf(vec![("0", KvValue::Map(shortcut))])), vec![
+      ShortcutsProblem::WrongType { index: String::from("0"), key: String::from("appid"), expected: String::from("Int32"), actual: String::from("String") },
+      ShortcutsProblem::WrongType { index: String::from("0"), key: String::from("tags/0"), expected: String::from("String"), actual: String::from("Int32") },
+    ]);
+  }
+
+  #[test]
+  fn duplicate_appids_are_problems() {
+    let appid = get_shortcut_appid("\"/usr/bin/minecraft\"", "Minecraft");
+    //? A shortcut without an appid gets the one Steam would compute for it, so it can still clash with a stored one.
+    let shortcuts_vdf = get_shortcuts_vdf(vec![
+      ("0", KvValue::Map(get_shortcut_data(Some(appid), "Minecraft"))),
+      ("1", KvValue::Map(get_shortcut_data(None, "Minecraft"))),
+    ]);
+
+    assert_eq!(validate_shortcuts_vdf(&shortcuts_vdf), vec![ShortcutsProblem::DuplicateAppid { index: String::from("1"), appid, original: String::from("0") }]);
+  }
+}
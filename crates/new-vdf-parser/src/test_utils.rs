@@ -0,0 +1,22 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::field_type::FieldType;
+
+/// Builds a binary field's type byte and key.
+pub fn field(field_type: FieldType, key: &str) -> Vec<u8> {
+  let mut bytes = vec![field_type.to_byte()];
+  bytes.extend_from_slice(key.as_bytes());
+  bytes.push(0);
+
+  return bytes;
+}
+
+/// Creates an empty temporary directory for a test's files. Names should be unique across tests, since they run in parallel.
+pub fn get_test_dir(name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!("new-vdf-parser-{}-{}", name, std::process::id()));
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+
+  return dir;
+}
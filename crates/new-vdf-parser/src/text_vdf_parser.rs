@@ -0,0 +1,494 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io::Write;
+
+use crate::error::VdfError;
+use crate::kv_value::{KvMap, KvValue};
+use crate::writer::write_file_atomic;
+
+/// How many nested #include and #base files are followed before giving up.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Gets the conditions that are true on the current platform, like "$WIN32" or "$LINUX".
+pub fn get_platform_conditions() -> Vec<&'static str> {
+  if cfg!(target_os = "windows") {
+    return vec!["$WIN32", "$WINDOWS"];
+  } else if cfg!(target_os = "macos") {
+    return vec!["$OSX", "$POSIX"];
+  } else {
+    return vec!["$LINUX", "$POSIX"];
+  }
+}
+
+/// Opens a text vdf file, like loginusers.vdf or libraryfolders.vdf.
+/// #include and #base paths are resolved relative to the file, and conditionals like [$WIN32] are checked against `conditions`, or the current platform's if `None`.
+pub fn open_text_vdf(path: &PathBuf, conditions: Option<&[&str]>) -> Result<KvMap, VdfError> {
+  let contents = fs::read_to_string(path)?;
+
+  return parse_text_vdf(&contents, conditions, path.parent());
+}
+
+/// Parses the contents of a text vdf file.
+/// #include and #base paths are resolved relative to `include_dir`, and are an error if it is `None`.
+/// Conditionals like [$WIN32] are checked against `conditions`, or the current platform's if `None`.
+pub fn parse_text_vdf(input: &str, conditions: Option<&[&str]>, include_dir: Option<&Path>) -> Result<KvMap, VdfError> {
+  let platform_conditions = get_platform_conditions();
+  let conditions = conditions.unwrap_or(&platform_conditions);
+
+  return parse_document(input, conditions, include_dir, 0);
+}
+
+/// Parses a text vdf document, following any #include and #base directives.
+fn parse_document(input: &str, conditions: &[&str], include_dir: Option<&Path>, depth: usize) -> Result<KvMap, VdfError> {
+  let mut parser = TextParser { chars: input.chars().collect(), position: 0, line: 1, column: 1, token_start: (1, 1), conditions };
+  let mut directives = Vec::new();
+
+  let mut document = parser.parse_entries(false, &mut directives)?;
+
+  for (directive, file_name, line, column) in directives {
+    if depth >= MAX_INCLUDE_DEPTH {
+      return Err(VdfError::Syntax { line, column, message: format!("{} of \"{}\" is nested too deeply", directive, file_name) });
+    }
+
+    let dir = include_dir.ok_or(VdfError::Syntax { line, column, message: format!("Can't resolve {} \"{}\" without a directory", directive, file_name) })?;
+    let include_path = dir.join(&file_name);
+    let contents = fs::read_to_string(&include_path)?;
+    let included = parse_document(&contents, conditions, include_path.parent(), depth + 1)?;
+
+    if directive == "#include" {
+      for (key, value) in included {
+        document.push(key, value);
+      }
+    } else {
+      merge_base(&mut document, included);
+    }
+  }
+
+  return Ok(document);
+}
+
+/// Adds the entries of a #base file that the document doesn't already have. Maps in both are merged recursively.
+fn merge_base(document: &mut KvMap, base: KvMap) {
+  for (key, base_value) in base {
+    match (document.get_mut(&key), base_value) {
+      (Some(KvValue::Map(existing)), KvValue::Map(base_map)) => merge_base(existing, base_map),
+      (Some(_), _) => {},
+      (None, base_value) => document.push(key, base_value),
+    }
+  }
+}
+
+/// A token in a text vdf file.
+#[derive(Debug, PartialEq)]
+enum Token {
+  /// A quoted or unquoted string. Unquoted strings starting with "#" are directives.
+  String { value: String, quoted: bool },
+  Condition(String),
+  OpenBrace,
+  CloseBrace,
+  Eof,
+}
+
+struct TextParser<'a> {
+  chars: Vec<char>,
+  position: usize,
+  line: usize,
+  column: usize,
+  /// The line and column where the last token started.
+  token_start: (usize, usize),
+  conditions: &'a [&'a str],
+}
+
+impl TextParser<'_> {
+  /// Creates a syntax error at the start of the last token.
+  fn error(&self, message: String) -> VdfError {
+    return VdfError::Syntax { line: self.token_start.0, column: self.token_start.1, message };
+  }
+
+  /// Gets the next char without consuming it.
+  fn peek(&self) -> Option<char> {
+    return self.chars.get(self.position).copied();
+  }
+
+  /// Consumes the next char, keeping track of the line and column.
+  fn advance(&mut self) -> Option<char> {
+    let next = self.peek()?;
+    self.position += 1;
+
+    if next == '\n' {
+      self.line += 1;
+      self.column = 1;
+    } else {
+      self.column += 1;
+    }
+
+    return Some(next);
+  }
+
+  /// Skips whitespace, including CR from CRLF line endings, and // comments.
+  fn skip_whitespace(&mut self) {
+    while let Some(next) = self.peek() {
+      if next.is_whitespace() || next == '\u{feff}' {
+        self.advance();
+      } else if next == '/' && self.chars.get(self.position + 1) == Some(&'/') {
+        while self.peek().is_some_and(| next | next != '\n') {
+          self.advance();
+        }
+      } else {
+        break;
+      }
+    }
+  }
+
+  /// Reads the next token.
+  fn next_token(&mut self) -> Result<Token, VdfError> {
+    self.skip_whitespace();
+    self.token_start = (self.line, self.column);
+
+    match self.peek() {
+      None => return Ok(Token::Eof),
+      Some('{') => { self.advance(); return Ok(Token::OpenBrace); },
+      Some('}') => { self.advance(); return Ok(Token::CloseBrace); },
+      Some('"') => {
+        self.advance();
+        return Ok(Token::String { value: self.read_quoted()?, quoted: true });
+      },
+      Some('[') => {
+        self.advance();
+        let mut condition = String::new();
+
+        loop {
+          match self.advance() {
+            Some(']') => return Ok(Token::Condition(condition)),
+            Some('\n') | None => return Err(self.error(String::from("Unterminated conditional"))),
+            Some(next) => condition.push(next),
+          }
+        }
+      },
+      Some(_) => {
+        let mut value = String::new();
+
+        while let Some(next) = self.peek() {
+          if next.is_whitespace() || next == '"' || next == '{' || next == '}' || next == '[' {
+            break;
+          }
+
+          value.push(next);
+          self.advance();
+        }
+
+        return Ok(Token::String { value, quoted: false });
+      },
+    }
+  }
+
+  /// Reads the rest of a quoted string, handling escape sequences.
+  fn read_quoted(&mut self) -> Result<String, VdfError> {
+    let mut value = String::new();
+
+    loop {
+      match self.advance() {
+        Some('"') => return Ok(value),
+        Some('\\') => {
+          match self.advance() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('\\') => value.push('\\'),
+            Some('"') => value.push('"'),
+            //? Unknown escapes are kept as is, since Steam doesn't escape backslashes in some paths.
+            Some(next) => { value.push('\\'); value.push(next); },
+            None => return Err(self.error(String::from("Unterminated string"))),
+          }
+        },
+        Some(next) => value.push(next),
+        None => return Err(self.error(String::from("Unterminated string"))),
+      }
+    }
+  }
+
+  /// Checks if a conditional like "$WIN32 || !$X360" is true.
+  fn check_condition(&self, condition: &str) -> bool {
+    return condition.split("||").any(| any_of | {
+      return any_of.split("&&").all(| term | {
+        let term = term.trim();
+
+        match term.strip_prefix('!') {
+          Some(negated) => return !self.has_condition(negated.trim()),
+          None => return self.has_condition(term),
+        }
+      });
+    });
+  }
+
+  /// Checks if a single condition is set.
+  fn has_condition(&self, name: &str) -> bool {
+    return self.conditions.iter().any(| condition | condition.eq_ignore_ascii_case(name));
+  }
+
+  /// Parses entries until the closing brace of the map, or the end of the file if `is_nested` is false.
+  /// Directives are collected into `directives` as (directive, file name, line, column).
+  fn parse_entries(&mut self, is_nested: bool, directives: &mut Vec<(String, String, usize, usize)>) -> Result<KvMap, VdfError> {
+    let mut map = KvMap::new();
+
+    loop {
+      let token = self.next_token()?;
+      let (line, column) = self.token_start;
+
+      let key = match token {
+        Token::String { value, quoted } => {
+          if !quoted && !is_nested && (value == "#include" || value == "#base") {
+            match self.next_token()? {
+              Token::String { value: file_name, .. } => directives.push((value, file_name, line, column)),
+              _ => return Err(self.error(format!("Expected a file name after {}", value))),
+            }
+
+            continue;
+          }
+
+          value
+        },
+        Token::CloseBrace if is_nested => return Ok(map),
+        Token::Eof if !is_nested => return Ok(map),
+        Token::Eof => return Err(self.error(String::from("Unexpected end of file, expected \"}\""))),
+        Token::CloseBrace => return Err(self.error(String::from("Unexpected \"}\""))),
+        Token::OpenBrace => return Err(self.error(String::from("Expected a key but got \"{\""))),
+        Token::Condition(_) => return Err(self.error(String::from("Expected a key but got a conditional"))),
+      };
+
+      let mut token = self.next_token()?;
+      let mut is_included = true;
+
+      if let Token::Condition(condition) = &token {
+        is_included = self.check_condition(condition);
+        token = self.next_token()?;
+      }
+
+      let value = match token {
+        Token::OpenBrace => KvValue::Map(self.parse_entries(true, directives)?),
+        Token::String { value, .. } => {
+          self.skip_whitespace();
+
+          if self.peek() == Some('[') {
+            if let Token::Condition(condition) = self.next_token()? {
+              is_included = is_included && self.check_condition(&condition);
+            }
+          }
+
+          KvValue::String(value)
+        },
+        _ => return Err(self.error(format!("Expected a value or \"{{\" after \"{}\"", key))),
+      };
+
+      if is_included {
+        map.push(key, value);
+      }
+    }
+  }
+}
+
+/// Writes the text vdf file, using tabs like Steam does.
+pub fn write_text_vdf(path: &PathBuf, data: &KvMap) -> Result<(), VdfError> {
+  return write_file_atomic(path, | sink | {
+    sink.write_all(to_text_vdf(data).as_bytes())?;
+    return Ok(());
+  });
+}
+
+/// Converts a KvMap to text vdf. Numbers are written as strings, since text vdf has no other value types.
+pub fn to_text_vdf(data: &KvMap) -> String {
+  let mut output = String::new();
+  write_text_map(&mut output, data, 0);
+
+  return output;
+}
+
+/// Writes the entries of a map at the provided indentation.
+fn write_text_map(output: &mut String, map: &KvMap, depth: usize) {
+  let indent = "\t".repeat(depth);
+
+  for (key, value) in map.iter() {
+    output.push_str(&indent);
+    output.push_str(&quote(key));
+
+    match value {
+      KvValue::Map(sub_map) => {
+        output.push('\n');
+        output.push_str(&indent);
+        output.push_str("{\n");
+        write_text_map(output, sub_map, depth + 1);
+        output.push_str(&indent);
+        output.push_str("}\n");
+      },
+      _ => {
+        output.push_str("\t\t");
+        output.push_str(&quote(&text_value(value)));
+        output.push('\n');
+      },
+    }
+  }
+}
+
+/// Gets the text form of a non map value.
+fn text_value(value: &KvValue) -> String {
+  match value {
+    KvValue::String(string) | KvValue::WideString(string) => return string.to_owned(),
+    KvValue::Int32(number) => return number.to_string(),
+    KvValue::Float32(number) => return number.to_string(),
+    KvValue::Int64(number) => return number.to_string(),
+    _ => return value.as_u64().unwrap_or_default().to_string(),
+  }
+}
+
+/// Quotes a string, escaping backslashes, quotes, newlines and tabs.
+fn quote(string: &str) -> String {
+  let mut quoted = String::with_capacity(string.len() + 2);
+  quoted.push('"');
+
+  for next in string.chars() {
+    match next {
+      '\\' => quoted.push_str("\\\\"),
+      '"' => quoted.push_str("\\\""),
+      '\n' => quoted.push_str("\\n"),
+      '\t' => quoted.push_str("\\t"),
+      _ => quoted.push(next),
+    }
+  }
+
+  quoted.push('"');
+  return quoted;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::get_test_dir;
+
+  /// Parses a document with the provided conditions and no include directory.
+  fn parse(input: &str, conditions: &[&str]) -> KvMap {
+    return parse_text_vdf(input, Some(conditions), None).unwrap();
+  }
+
+  /// Gets a string at a key path.
+  fn get_str<'a>(map: &'a KvMap, path: &str) -> &'a str {
+    return map.get_str_at(path).unwrap();
+  }
+
+  #[test]
+  fn parses_nested_maps_and_unquoted_strings() {
+    let map = parse("\"users\"\n{\n\t\"1\"\n\t{\n\t\tAccountName alice // a comment\n\t}\n}\n", &[]);
+
+    assert_eq!(get_str(&map, "users/1/AccountName"), "alice");
+  }
+
+  #[test]
+  fn parses_escapes() {
+    let map = parse(r#""key" "a\\b \"quoted\"\n\tend \q""#, &[]);
+
+    assert_eq!(get_str(&map, "key"), "a\\b \"quoted\"\n\tend \\q");
+  }
+
+  #[test]
+  fn escapes_round_trip() {
+    let mut map = KvMap::new();
+    map.push(String::from("path"), KvValue::String(String::from("C:\\Games\\\"Quoted\"\n\tTabbed")));
+
+    assert_eq!(parse(&to_text_vdf(&map), &[]), map);
+  }
+
+  #[test]
+  fn parses_crlf_and_bom() {
+    let map = parse("\u{feff}\"users\"\r\n{\r\n\t\"1\"\t\t\"a\"\r\n\t\"2\"\r\n\t{\r\n\t}\r\n}\r\n", &[]);
+
+    assert_eq!(get_str(&map, "users/1"), "a");
+    assert!(map.get_map_at("users/2").unwrap().is_empty());
+  }
+
+  #[test]
+  fn reports_where_syntax_errors_are() {
+    match parse_text_vdf("\"a\"\n{\n\t\"b\" }\n", Some(&[]), None) {
+      Err(VdfError::Syntax { line, column, .. }) => assert_eq!((line, column), (3, 6)),
+      other => panic!("Expected a syntax error, got {:?}", other),
+    }
+
+    assert!(matches!(parse_text_vdf("\"a\" \"unterminated", Some(&[]), None), Err(VdfError::Syntax { .. })));
+    assert!(matches!(parse_text_vdf("\"a\" {", Some(&[]), None), Err(VdfError::Syntax { .. })));
+  }
+
+  #[test]
+  fn checks_conditionals() {
+    let input = r#"
+      "windows"   "1"   [$WIN32]
+      "linux"     "1"   [$LINUX]
+      "not_linux" "1"   [!$LINUX]
+      "either"    "1"   [$WIN32 || $LINUX]
+      "both"      "1"   [$POSIX && $LINUX]
+      "neither"   "1"   [$X360 || $PS3]
+      "block"     [$LINUX]
+      {
+        "inner"   "1"
+      }
+    "#;
+
+    let linux = parse(input, &["$LINUX", "$POSIX"]);
+    let linux_keys: Vec<&String> = linux.keys().collect();
+    assert_eq!(linux_keys, vec!["linux", "either", "both", "block"]);
+
+    let windows = parse(input, &["$WIN32", "$WINDOWS"]);
+    let windows_keys: Vec<&String> = windows.keys().collect();
+    assert_eq!(windows_keys, vec!["windows", "not_linux", "either"]);
+  }
+
+  #[test]
+  fn conditions_ignore_case() {
+    let map = parse("\"key\" \"1\" [$win32]", &["$WIN32"]);
+
+    assert!(map.contains_key("key"));
+  }
+
+  #[test]
+  fn writes_negative_int32_values() {
+    let mut map = KvMap::new();
+    map.push(String::from("negative"), KvValue::Int32(-1));
+    map.push(String::from("big"), KvValue::UInt64(u64::MAX));
+
+    assert_eq!(to_text_vdf(&map), format!("\"negative\"\t\t\"-1\"\n\"big\"\t\t\"{}\"\n", u64::MAX));
+  }
+
+  #[test]
+  fn includes_append_and_bases_merge() {
+    let dir = get_test_dir("includes");
+    fs::write(dir.join("included.vdf"), "\"extra\" { \"a\" \"included\" }").unwrap();
+    fs::write(dir.join("base.vdf"), "\"root\" { \"kept\" \"document\" \"added\" \"base\" \"nested\" { \"b\" \"base\" } }").unwrap();
+    fs::write(dir.join("main.vdf"), "#include \"included.vdf\"\n#base \"base.vdf\"\n\"root\" { \"kept\" \"main\" \"nested\" { \"a\" \"main\" } }").unwrap();
+
+    let map = open_text_vdf(&dir.join("main.vdf"), Some(&[])).unwrap();
+    let keys: Vec<&String> = map.keys().collect();
+
+    assert_eq!(keys, vec!["root", "extra"]);
+    assert_eq!(get_str(&map, "extra/a"), "included");
+    assert_eq!(get_str(&map, "root/kept"), "main");
+    assert_eq!(get_str(&map, "root/added"), "base");
+    assert_eq!(get_str(&map, "root/nested/a"), "main");
+    assert_eq!(get_str(&map, "root/nested/b"), "base");
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn includes_stop_when_nested_too_deeply() {
+    let dir = get_test_dir("recursive");
+    fs::write(dir.join("self.vdf"), "#include \"self.vdf\"\n\"key\" \"value\"").unwrap();
+
+    match open_text_vdf(&dir.join("self.vdf"), Some(&[])) {
+      Err(VdfError::Syntax { message, .. }) => assert!(message.contains("nested too deeply")),
+      other => panic!("Expected a syntax error, got {:?}", other),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn includes_need_a_directory() {
+    assert!(matches!(parse_text_vdf("#include \"other.vdf\"", Some(&[]), None), Err(VdfError::Syntax { .. })));
+  }
+}
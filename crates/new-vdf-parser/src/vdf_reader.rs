@@ -1,53 +1,252 @@
-use serde_json::{Value, Map};
+use std::borrow::Cow;
 
+use crate::error::VdfError;
+use crate::field_type::FieldType;
+use crate::kv_value::KvMap;
+use crate::kv_value_ref::{KvMapRef, KvValueRef};
 use crate::reader::Reader;
+use crate::visitor::{KvVisitor, Visit};
 
-/// Reads a vdf entry string to JSON.
-pub fn read_vdf_string(reader: &mut Reader, magic: Option<u32>, strings: &Option<&mut Vec<String>>) -> String {
+/// Tracks which app and key path is being parsed, so errors can say where they happened.
+/// Keys are borrowed like the entries they belong to, so tracking the path doesn't copy them.
+pub struct EntryContext<'a> {
+  pub appid: Option<u32>,
+  path: Vec<Cow<'a, str>>,
+}
+
+impl<'a> EntryContext<'a> {
+  /// Creates a new EntryContext for the provided appid, starting at the provided root path.
+  pub fn new(appid: Option<u32>, root: &[&'a str]) -> EntryContext<'a> {
+    return EntryContext { appid, path: root.iter().map(| key | Cow::Borrowed(*key)).collect() };
+  }
+
+  /// Gets the current key path, joined with "/".
+  pub fn get_path(&self) -> String {
+    return self.path.join("/");
+  }
+
+  /// Wraps an error with the current offset, appid and key path, unless it has already been wrapped.
+  pub fn wrap(&self, err: VdfError, offset: usize) -> VdfError {
+    if let VdfError::Entry { .. } = err {
+      return err;
+    }
+
+    return VdfError::Entry { offset, appid: self.appid, path: self.get_path(), source: Box::new(err) };
+  }
+}
+
+/// Reads a vdf entry string, borrowing it from the buffer or string table when possible.
+pub fn read_vdf_string<'a>(reader: &mut Reader<'a>, magic: Option<u32>, strings: Option<&'a [String]>) -> Result<Cow<'a, str>, VdfError> {
   if magic.is_some() && magic.unwrap() == 0x07564429 {
-    let index: usize = reader.read_uint32(true).try_into().unwrap();
-    let string_pool = strings.as_ref().unwrap();
-    let string = &string_pool[index];
-    
-    return string.to_owned();
+    let index: usize = reader.read_uint32(true)? as usize;
+    let string_pool = strings.ok_or(VdfError::InvalidStringIndex(index))?;
+    let string = string_pool.get(index).ok_or(VdfError::InvalidStringIndex(index))?;
+
+    return Ok(Cow::Borrowed(string.as_str()));
   } else {
-    return reader.read_string(None);
+    return reader.read_str(None);
   }
 }
 
-/// Reads a vdf entry map to JSON.
-pub fn read_entry_map(reader: &mut Reader, magic: Option<u32>, strings: &Option<&mut Vec<String>>) -> Map<String, Value> {
-  let mut props = Map::new();
-  let mut field_type = reader.read_uint8(true);
+/// Reads a vdf entry map, keeping the order of its entries.
+pub fn read_entry_map<'a>(reader: &mut Reader<'a>, magic: Option<u32>, strings: Option<&'a [String]>, context: &mut EntryContext<'a>) -> Result<KvMap, VdfError> {
+  return Ok(read_entry_map_ref(reader, magic, strings, context)?.into_kv_map());
+}
+
+/// Reads a vdf entry map without copying its strings, keeping the order of its entries.
+pub fn read_entry_map_ref<'a>(reader: &mut Reader<'a>, magic: Option<u32>, strings: Option<&'a [String]>, context: &mut EntryContext<'a>) -> Result<KvMapRef<'a>, VdfError> {
+  let mut props = KvMapRef::new();
 
-  while field_type != 0x08 {
-    let key = read_vdf_string(reader, magic, strings);
-    let value = read_entry_field(reader, field_type, magic, strings);
+  loop {
+    let field_offset = reader.get_absolute_offset();
+    let type_byte = reader.read_uint8(true).map_err(| err | context.wrap(err, field_offset))?;
+    let field_type = FieldType::from_byte(type_byte).map_err(| err | context.wrap(err, field_offset))?;
+
+    if field_type.is_end() {
+      props.set_end_type(field_type);
+      break;
+    }
 
-    props.insert(key, value);
+    let key = read_vdf_string(reader, magic, strings).map_err(| err | context.wrap(err, field_offset))?;
 
-    field_type = reader.read_uint8(true);
+    context.path.push(key.clone());
+    let value = read_entry_field(reader, field_type, magic, strings, context).map_err(| err | context.wrap(err, field_offset))?;
+    let key = context.path.pop().unwrap_or_default();
+
+    props.push(key, value);
   }
 
-  return props;
+  return Ok(props);
 }
 
-/// Reads a vdf entry field to JSON.
-pub fn read_entry_field(reader: &mut Reader, field_type: u8, magic: Option<u32>, strings: &Option<&mut Vec<String>>) -> Value {
+/// Reads a vdf entry field.
+pub fn read_entry_field<'a>(reader: &mut Reader<'a>, field_type: FieldType, magic: Option<u32>, strings: Option<&'a [String]>, context: &mut EntryContext<'a>) -> Result<KvValueRef<'a>, VdfError> {
   match field_type {
-    0x00 => { //? map
-      return Value::Object(read_entry_map(reader, magic, strings));
-    },
-    0x01 => { //? string
-      let value = reader.read_string(None);
-      return Value::String(value);
-    },
-    0x02 => { //? number
-      let value = reader.read_uint32(true);
-      return Value::Number(value.into());
+    FieldType::Map => return Ok(KvValueRef::Map(read_entry_map_ref(reader, magic, strings, context)?)),
+    FieldType::String => return Ok(KvValueRef::String(reader.read_str(None)?)),
+    FieldType::WideString => return Ok(KvValueRef::WideString(Cow::Owned(reader.read_wide_string()?))),
+    FieldType::Int32 => return Ok(KvValueRef::Int32(reader.read_int32(true)?)),
+    FieldType::Float32 => return Ok(KvValueRef::Float32(reader.read_float32(true)?)),
+    FieldType::Pointer => return Ok(KvValueRef::Pointer(reader.read_uint32(true)?)),
+    FieldType::Color => return Ok(KvValueRef::Color(reader.read_uint32(true)?)),
+    FieldType::UInt64 => return Ok(KvValueRef::UInt64(reader.read_uint64(true)?)),
+    FieldType::Int64 => return Ok(KvValueRef::Int64(reader.read_int64(true)?)),
+    FieldType::End | FieldType::AlternateEnd => {
+      return Err(VdfError::UnexpectedFieldType(field_type.to_byte()));
+    }
+  }
+}
+
+/// Visits a vdf entry map, calling `begin_map` before its fields and `end_map` after them. Returns `false` if the visitor stopped.
+pub fn visit_entry_map<'a, V: KvVisitor<'a>>(reader: &mut Reader<'a>, magic: Option<u32>, strings: Option<&'a [String]>, context: &mut EntryContext<'a>, visitor: &mut V) -> Result<bool, VdfError> {
+  visitor.begin_map();
+
+  loop {
+    let field_offset = reader.get_absolute_offset();
+    let type_byte = reader.read_uint8(true).map_err(| err | context.wrap(err, field_offset))?;
+    let field_type = FieldType::from_byte(type_byte).map_err(| err | context.wrap(err, field_offset))?;
+
+    if field_type.is_end() {
+      break;
+    }
+
+    let key = read_vdf_string(reader, magic, strings).map_err(| err | context.wrap(err, field_offset))?;
+    let action = visitor.key(&key, field_type);
+
+    context.path.push(key);
+    let keep_going = match action {
+      Visit::Stop => false,
+      Visit::Skip => {
+        skip_entry_field(reader, field_type, magic).map_err(| err | context.wrap(err, field_offset))?;
+        true
+      },
+      Visit::Continue => visit_entry_field(reader, field_type, magic, strings, context, visitor).map_err(| err | context.wrap(err, field_offset))?,
+    };
+    context.path.pop();
+
+    if !keep_going {
+      return Ok(false);
+    }
+  }
+
+  visitor.end_map();
+
+  return Ok(true);
+}
+
+/// Visits a vdf entry field. Returns `false` if the visitor stopped.
+fn visit_entry_field<'a, V: KvVisitor<'a>>(reader: &mut Reader<'a>, field_type: FieldType, magic: Option<u32>, strings: Option<&'a [String]>, context: &mut EntryContext<'a>, visitor: &mut V) -> Result<bool, VdfError> {
+  if field_type == FieldType::Map {
+    return visit_entry_map(reader, magic, strings, context, visitor);
+  }
+
+  let value = read_entry_field(reader, field_type, magic, strings, context)?;
+
+  return Ok(visitor.value(value) != Visit::Stop);
+}
+
+/// Skips past a vdf entry field without decoding it. Maps are skipped along with all of their fields.
+fn skip_entry_field(reader: &mut Reader, field_type: FieldType, magic: Option<u32>) -> Result<(), VdfError> {
+  match field_type {
+    FieldType::Map => {
+      loop {
+        let field_type = FieldType::from_byte(reader.read_uint8(true)?)?;
+
+        if field_type.is_end() {
+          return Ok(());
+        }
+
+        //? Keys are string table indices in v29 appinfo.vdf files, and inline strings everywhere else.
+        if magic == Some(0x07564429) {
+          reader.read_bytes(4)?;
+        } else {
+          reader.skip_string()?;
+        }
+
+        skip_entry_field(reader, field_type, magic)?;
+      }
     },
-    _ => {
-      panic!("Unexpected field type {}!", field_type);
+    FieldType::String => return reader.skip_string(),
+    FieldType::WideString => return reader.skip_wide_string(),
+    FieldType::Int32 | FieldType::Float32 | FieldType::Pointer | FieldType::Color => return reader.read_bytes(4).map(| _ | ()),
+    FieldType::UInt64 | FieldType::Int64 => return reader.read_bytes(8).map(| _ | ()),
+    FieldType::End | FieldType::AlternateEnd => {
+      return Err(VdfError::UnexpectedFieldType(field_type.to_byte()));
     }
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::kv_value::KvValue;
+  use crate::test_utils::field;
+
+  /// Reads a map from the start of the provided bytes.
+  fn read_map(bytes: &[u8]) -> Result<KvMap, VdfError> {
+    let mut reader = Reader::new(bytes);
+    let mut context = EntryContext::new(None, &[]);
+
+    return read_entry_map(&mut reader, None, None, &mut context);
+  }
+
+  #[test]
+  fn reads_every_field_type() {
+    let mut bytes = field(FieldType::String, "name");
+    bytes.extend_from_slice(b"Portal\0");
+    bytes.extend(field(FieldType::Int32, "appid"));
+    bytes.extend_from_slice(&(-1i32).to_le_bytes());
+    bytes.extend(field(FieldType::Float32, "scale"));
+    bytes.extend_from_slice(&1.5f32.to_le_bytes());
+    bytes.extend(field(FieldType::Pointer, "pointer"));
+    bytes.extend_from_slice(&7u32.to_le_bytes());
+    bytes.extend(field(FieldType::WideString, "wide"));
+    bytes.extend_from_slice(&[b'h', 0, b'i', 0, 0, 0]);
+    bytes.extend(field(FieldType::Color, "color"));
+    bytes.extend_from_slice(&0xff00ffu32.to_le_bytes());
+    bytes.extend(field(FieldType::UInt64, "big"));
+    bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+    bytes.extend(field(FieldType::Int64, "signed"));
+    bytes.extend_from_slice(&(-2i64).to_le_bytes());
+    bytes.push(FieldType::End.to_byte());
+
+    let map = read_map(&bytes).unwrap();
+
+    assert_eq!(map.get("name"), Some(&KvValue::String(String::from("Portal"))));
+    assert_eq!(map.get("appid"), Some(&KvValue::Int32(-1)));
+    assert_eq!(map.get("scale"), Some(&KvValue::Float32(1.5)));
+    assert_eq!(map.get("pointer"), Some(&KvValue::Pointer(7)));
+    assert_eq!(map.get("wide"), Some(&KvValue::WideString(String::from("hi"))));
+    assert_eq!(map.get("color"), Some(&KvValue::Color(0xff00ff)));
+    assert_eq!(map.get("big"), Some(&KvValue::UInt64(u64::MAX)));
+    assert_eq!(map.get("signed"), Some(&KvValue::Int64(-2)));
+  }
+
+  #[test]
+  fn keeps_the_end_marker_of_each_map() {
+    let mut bytes = field(FieldType::Map, "outer");
+    bytes.extend(field(FieldType::Map, "inner"));
+    bytes.push(FieldType::AlternateEnd.to_byte());
+    bytes.push(FieldType::End.to_byte());
+    bytes.push(FieldType::AlternateEnd.to_byte());
+
+    let map = read_map(&bytes).unwrap();
+    let outer = map.get_map_at("outer").unwrap();
+
+    assert_eq!(map.get_end_type(), FieldType::AlternateEnd);
+    assert_eq!(outer.get_end_type(), FieldType::End);
+    assert_eq!(outer.get_map_at("inner").unwrap().get_end_type(), FieldType::AlternateEnd);
+  }
+
+  #[test]
+  fn reports_where_a_truncated_map_ends() {
+    let mut bytes = field(FieldType::Map, "outer");
+    bytes.extend(field(FieldType::Int32, "appid"));
+    bytes.extend_from_slice(&[1, 0]);
+
+    match read_map(&bytes) {
+      Err(VdfError::Entry { path, .. }) => assert_eq!(path, "outer/appid"),
+      other => panic!("Expected an entry error, got {:?}", other),
+    }
+  }
+}
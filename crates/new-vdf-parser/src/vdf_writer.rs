@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::error::VdfError;
+use crate::kv_value::{KvMap, KvValue};
+use crate::writer::Writer;
+
+/// The string table used by appinfo.vdf v29 keys. Strings are added in the order they are first written.
+#[derive(Default)]
+pub struct StringTable {
+  strings: Vec<String>,
+  indices: HashMap<String, u32>,
+}
+
+impl StringTable {
+  /// Creates a new, empty StringTable.
+  pub fn new() -> StringTable {
+    return StringTable::default();
+  }
+
+  /// Gets the index of a string, adding it to the table if it's new.
+  pub fn get_index(&mut self, string: &str) -> u32 {
+    if let Some(index) = self.indices.get(string) {
+      return *index;
+    }
+
+    let index = self.strings.len() as u32;
+    self.strings.push(string.to_owned());
+    self.indices.insert(string.to_owned(), index);
+
+    return index;
+  }
+
+  /// Writes the string table, prefixed with its length.
+  pub fn write<W: Write>(&self, writer: &mut Writer<W>) -> Result<(), VdfError> {
+    writer.write_uint32(self.strings.len() as u32, true)?;
+
+    for string in self.strings.iter() {
+      writer.write_string(string, false, true)?;
+    }
+
+    return Ok(());
+  }
+}
+
+/// Writes a vdf entry string, as an index into the string table if one is provided.
+pub fn write_vdf_string<W: Write>(writer: &mut Writer<W>, string: &str, strings: Option<&mut StringTable>) -> Result<(), VdfError> {
+  match strings {
+    Some(string_table) => { writer.write_uint32(string_table.get_index(string), true)?; },
+    None => { writer.write_string(string, false, true)?; },
+  }
+
+  return Ok(());
+}
+
+/// Writes a vdf entry map, closed with the end marker it was read with.
+pub fn write_entry_map<W: Write>(writer: &mut Writer<W>, map: &KvMap, mut strings: Option<&mut StringTable>) -> Result<(), VdfError> {
+  for (key, value) in map.iter() {
+    write_entry_field(writer, key, value, strings.as_deref_mut())?;
+  }
+
+  writer.write_uint8(map.get_end_type().to_byte(), true)?;
+
+  return Ok(());
+}
+
+/// Writes a vdf entry field, using the field type the value was read with.
+pub fn write_entry_field<W: Write>(writer: &mut Writer<W>, key: &str, field: &KvValue, mut strings: Option<&mut StringTable>) -> Result<(), VdfError> {
+  writer.write_uint8(field.field_type().to_byte(), true)?;
+  write_vdf_string(writer, key, strings.as_deref_mut())?;
+
+  match field {
+    KvValue::Map(map) => { write_entry_map(writer, map, strings)?; },
+    KvValue::String(string) => { writer.write_string(string, false, true)?; },
+    KvValue::WideString(string) => { writer.write_wide_string(string, true)?; },
+    KvValue::Int32(number) => { writer.write_int32(*number, true)?; },
+    KvValue::Float32(number) => { writer.write_float32(*number, true)?; },
+    KvValue::Pointer(number) | KvValue::Color(number) => { writer.write_uint32(*number, true)?; },
+    KvValue::UInt64(number) => { writer.write_uint64(*number, true)?; },
+    KvValue::Int64(number) => { writer.write_int64(*number, true)?; },
+  }
+
+  return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::field_type::FieldType;
+  use crate::shortcuts_vdf_parser::{parse_shortcuts, write_shortcuts};
+  use crate::test_utils::field;
+
+  /// Builds a shortcuts.vdf file with a shortcut that has a field of every type, and maps closed with both end markers.
+  fn get_shortcuts_bytes() -> Vec<u8> {
+    let mut bytes = field(FieldType::Map, "shortcuts");
+    bytes.extend(field(FieldType::Map, "0"));
+    bytes.extend(field(FieldType::Int32, "appid"));
+    bytes.extend_from_slice(&3071491651u32.to_le_bytes());
+    bytes.extend(field(FieldType::String, "AppName"));
+    bytes.extend_from_slice(b"Firefox\0");
+    bytes.extend(field(FieldType::Int32, "IsHidden"));
+    bytes.extend_from_slice(&0i32.to_le_bytes());
+    bytes.extend(field(FieldType::Float32, "scale"));
+    bytes.extend_from_slice(&0.5f32.to_le_bytes());
+    bytes.extend(field(FieldType::Pointer, "pointer"));
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend(field(FieldType::WideString, "wide"));
+    bytes.extend_from_slice(&[b'h', 0, b'i', 0, 0, 0]);
+    bytes.extend(field(FieldType::Color, "color"));
+    bytes.extend_from_slice(&2u32.to_le_bytes());
+    bytes.extend(field(FieldType::UInt64, "small"));
+    bytes.extend_from_slice(&3u64.to_le_bytes());
+    bytes.extend(field(FieldType::Int64, "negative"));
+    bytes.extend_from_slice(&(-4i64).to_le_bytes());
+    bytes.extend(field(FieldType::Map, "tags"));
+    bytes.extend(field(FieldType::String, "0"));
+    bytes.extend_from_slice(b"Browsers\0");
+    bytes.push(FieldType::AlternateEnd.to_byte());
+    bytes.push(FieldType::End.to_byte());
+    bytes.push(FieldType::AlternateEnd.to_byte());
+    bytes.push(FieldType::End.to_byte());
+
+    return bytes;
+  }
+
+  /// Writes shortcuts.vdf data to bytes.
+  fn to_bytes(data: &KvMap) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_shortcuts(&mut bytes, data).unwrap();
+
+    return bytes;
+  }
+
+  #[test]
+  fn parse_then_write_is_byte_identical() {
+    let bytes = get_shortcuts_bytes();
+
+    assert_eq!(to_bytes(&parse_shortcuts(&bytes).unwrap()), bytes);
+  }
+
+  #[test]
+  fn json_with_types_is_byte_identical() {
+    let bytes = get_shortcuts_bytes();
+    let data = parse_shortcuts(&bytes).unwrap();
+
+    let json = data.to_json();
+    let from_json = KvMap::from_json_with_types(&json, &data).unwrap();
+
+    assert_eq!(from_json, data);
+    assert_eq!(to_bytes(&from_json), bytes);
+  }
+
+  #[test]
+  fn json_without_types_picks_types_by_size() {
+    let data = parse_shortcuts(&get_shortcuts_bytes()).unwrap();
+    let from_json = KvMap::from_json(&data.to_json()).unwrap();
+    let shortcut = from_json.get_map_at("shortcuts/0").unwrap();
+
+    assert_eq!(shortcut.get("small"), Some(&KvValue::Int32(3)));
+    assert_eq!(shortcut.get("negative"), Some(&KvValue::Int32(-4)));
+    assert_eq!(shortcut.get_end_type(), FieldType::End);
+  }
+
+  #[test]
+  fn json_with_types_keeps_changed_values_in_their_types() {
+    let data = parse_shortcuts(&get_shortcuts_bytes()).unwrap();
+
+    let mut json = data.to_json();
+    let shortcut = json["shortcuts"]["0"].as_object_mut().unwrap();
+    shortcut.insert(String::from("small"), serde_json::json!(10));
+    shortcut.insert(String::from("IsHidden"), serde_json::json!(1));
+    shortcut.insert(String::from("added"), serde_json::json!(5));
+
+    let from_json = KvMap::from_json_with_types(&json, &data).unwrap();
+    let shortcut = from_json.get_map_at("shortcuts/0").unwrap();
+
+    assert_eq!(shortcut.get("small"), Some(&KvValue::UInt64(10)));
+    assert_eq!(shortcut.get("IsHidden"), Some(&KvValue::Int32(1)));
+    assert_eq!(shortcut.get("added"), Some(&KvValue::Int32(5)));
+    assert_eq!(shortcut.get_map_at("tags").unwrap().get_end_type(), FieldType::AlternateEnd);
+  }
+
+  #[test]
+  fn string_table_indexes_are_reused() {
+    let mut strings = StringTable::new();
+
+    assert_eq!(strings.get_index("appid"), 0);
+    assert_eq!(strings.get_index("common"), 1);
+    assert_eq!(strings.get_index("appid"), 0);
+
+    let mut writer = Writer::new(Vec::new());
+    strings.write(&mut writer).unwrap();
+
+    assert_eq!(writer.into_inner(), b"\x02\0\0\0appid\0common\0".to_vec());
+  }
+}
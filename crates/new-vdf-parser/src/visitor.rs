@@ -0,0 +1,274 @@
+use std::borrow::Cow;
+
+use crate::error::VdfError;
+use crate::field_type::FieldType;
+use crate::kv_value_ref::{KvMapRef, KvValueRef};
+use crate::reader::Reader;
+use crate::vdf_reader::{visit_entry_map, EntryContext};
+
+/// What the reader should do after a visitor callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+  /// Keep reading.
+  Continue,
+  /// Skip the value of the current key without decoding it. For maps, none of their fields are visited.
+  Skip,
+  /// Stop reading. No more callbacks are made, including the `end_map`s of the maps being read.
+  Stop,
+}
+
+/// Receives binary KeyValues as they are read, instead of having them built into a map.
+/// Each map, including the root, is visited as `begin_map`, then `key` and `value` (or a nested map) for each field, then `end_map`.
+/// Every callback has a default that keeps reading, so visitors only need to implement the ones they use.
+pub trait KvVisitor<'a> {
+  /// Called when a map starts. For fields, this comes after their `key`.
+  fn begin_map(&mut self) {}
+
+  /// Called with the key and type of each field, before its value is read. Return `Visit::Skip` to skip the value, which is much cheaper than decoding it.
+  //? The key is a Cow rather than a str so visitors can keep it without copying it when it is borrowed from the buffer.
+  #[allow(clippy::ptr_arg)]
+  fn key(&mut self, _key: &Cow<'a, str>, _field_type: FieldType) -> Visit {
+    return Visit::Continue;
+  }
+
+  /// Called with the value of each field that isn't a map.
+  fn value(&mut self, _value: KvValueRef<'a>) -> Visit {
+    return Visit::Continue;
+  }
+
+  /// Called when a map ends.
+  fn end_map(&mut self) {}
+}
+
+/// Visits binary KeyValues data, like the contents of shortcuts.vdf. Returns `false` if the visitor stopped early.
+pub fn visit_slice<'a, V: KvVisitor<'a>>(data: &'a [u8], visitor: &mut V) -> Result<bool, VdfError> {
+  let mut reader = Reader::new(data);
+  let mut context = EntryContext::new(None, &[]);
+
+  return visit_entry_map(&mut reader, None, None, &mut context, visitor);
+}
+
+/// A map being built by a `ProjectionVisitor`, along with the paths that are kept inside it.
+struct ProjectionFrame<'a, 'p> {
+  key: Option<Cow<'a, str>>,
+  map: KvMapRef<'a>,
+  /// The paths left to match in this map. `None` if every field is kept.
+  paths: Option<Vec<&'p str>>,
+}
+
+/// Builds a map of only the fields on the provided "/" separated key paths, skipping everything else without decoding it.
+/// Each path keeps its whole subtree, so `"common/name"` keeps "name" in "common", and `"common"` keeps all of "common".
+pub struct ProjectionVisitor<'a, 'p> {
+  paths: Vec<&'p str>,
+  stack: Vec<ProjectionFrame<'a, 'p>>,
+  pending: Option<(Cow<'a, str>, Option<Vec<&'p str>>)>,
+  result: Option<KvMapRef<'a>>,
+}
+
+impl<'a, 'p> ProjectionVisitor<'a, 'p> {
+  /// Creates a new ProjectionVisitor that keeps the provided key paths.
+  pub fn new(paths: &[&'p str]) -> ProjectionVisitor<'a, 'p> {
+    return ProjectionVisitor { paths: paths.to_vec(), stack: Vec::new(), pending: None, result: None };
+  }
+
+  /// Gets the projected map. Empty if the root map wasn't finished.
+  pub fn into_map(self) -> KvMapRef<'a> {
+    return self.result.unwrap_or_default();
+  }
+}
+
+impl<'a, 'p> KvVisitor<'a> for ProjectionVisitor<'a, 'p> {
+  fn begin_map(&mut self) {
+    let frame = match self.pending.take() {
+      Some((key, paths)) => ProjectionFrame { key: Some(key), map: KvMapRef::new(), paths },
+      None => ProjectionFrame { key: None, map: KvMapRef::new(), paths: Some(self.paths.clone()) },
+    };
+
+    self.stack.push(frame);
+  }
+
+  fn key(&mut self, key: &Cow<'a, str>, field_type: FieldType) -> Visit {
+    let sub_paths = match self.stack.last() {
+      Some(ProjectionFrame { paths: Some(paths), .. }) => get_sub_paths(paths, key),
+      Some(ProjectionFrame { paths: None, .. }) => None,
+      None => return Visit::Skip,
+    };
+
+    //? Paths that go deeper than a value that isn't a map can't match anything.
+    if let Some(sub_paths) = &sub_paths {
+      if sub_paths.is_empty() || field_type != FieldType::Map {
+        return Visit::Skip;
+      }
+    }
+
+    self.pending = Some((key.clone(), sub_paths));
+    return Visit::Continue;
+  }
+
+  fn value(&mut self, value: KvValueRef<'a>) -> Visit {
+    if let (Some((key, _)), Some(frame)) = (self.pending.take(), self.stack.last_mut()) {
+      frame.map.push(key, value);
+    }
+
+    return Visit::Continue;
+  }
+
+  fn end_map(&mut self) {
+    let frame = match self.stack.pop() {
+      Some(frame) => frame,
+      None => return,
+    };
+
+    match (frame.key, self.stack.last_mut()) {
+      (Some(key), Some(parent)) => parent.map.push(key, KvValueRef::Map(frame.map)),
+      _ => self.result = Some(frame.map),
+    }
+  }
+}
+
+/// Gets the paths inside a key, from the paths of the map it is in. Returns `None` if a path ends at the key, since that keeps its whole value.
+fn get_sub_paths<'p>(paths: &[&'p str], key: &str) -> Option<Vec<&'p str>> {
+  let mut sub_paths: Vec<&'p str> = Vec::new();
+
+  for path in paths {
+    match path.split_once('/') {
+      Some((first, rest)) if first == key => sub_paths.push(rest),
+      None if *path == key => return None,
+      _ => {},
+    }
+  }
+
+  return Some(sub_paths);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::field;
+
+  /// Builds a map with a "skipped" subtree holding a field of each type and a nested map, followed by an "after" field.
+  fn get_data() -> Vec<u8> {
+    let mut bytes = field(FieldType::Map, "skipped");
+    bytes.extend(field(FieldType::String, "name"));
+    bytes.extend_from_slice(b"Portal\0");
+    bytes.extend(field(FieldType::Int32, "appid"));
+    bytes.extend_from_slice(&400u32.to_le_bytes());
+    bytes.extend(field(FieldType::WideString, "wide"));
+    bytes.extend_from_slice(&[b'h', 0, b'i', 0, 0, 0]);
+    bytes.extend(field(FieldType::UInt64, "big"));
+    bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+    bytes.extend(field(FieldType::Map, "nested"));
+    bytes.extend(field(FieldType::Float32, "scale"));
+    bytes.extend_from_slice(&1.5f32.to_le_bytes());
+    bytes.push(FieldType::End.to_byte());
+    bytes.push(FieldType::End.to_byte());
+    bytes.extend(field(FieldType::String, "after"));
+    bytes.extend_from_slice(b"kept\0");
+    bytes.push(FieldType::End.to_byte());
+
+    return bytes;
+  }
+
+  /// Records every callback, skipping and stopping at the provided keys.
+  struct RecordingVisitor {
+    skip: &'static str,
+    stop: &'static str,
+    events: Vec<String>,
+  }
+
+  impl<'a> KvVisitor<'a> for RecordingVisitor {
+    fn begin_map(&mut self) {
+      self.events.push(String::from("begin"));
+    }
+
+    fn key(&mut self, key: &Cow<'a, str>, _field_type: FieldType) -> Visit {
+      self.events.push(format!("key {}", key));
+
+      if key == self.skip {
+        return Visit::Skip;
+      }
+
+      if key == self.stop {
+        return Visit::Stop;
+      }
+
+      return Visit::Continue;
+    }
+
+    fn value(&mut self, value: KvValueRef<'a>) -> Visit {
+      self.events.push(format!("value {}", value.as_str().unwrap_or("?")));
+      return Visit::Continue;
+    }
+
+    fn end_map(&mut self) {
+      self.events.push(String::from("end"));
+    }
+  }
+
+  /// Visits the data with a visitor that skips and stops at the provided keys, returning its events and where the reader ended.
+  fn visit(data: &[u8], skip: &'static str, stop: &'static str) -> (bool, Vec<String>, usize) {
+    let mut visitor = RecordingVisitor { skip, stop, events: Vec::new() };
+    let mut reader = Reader::new(data);
+    let mut context = EntryContext::new(None, &[]);
+
+    let finished = visit_entry_map(&mut reader, None, None, &mut context, &mut visitor).unwrap();
+
+    return (finished, visitor.events, reader.get_offset());
+  }
+
+  #[test]
+  fn skipped_subtrees_are_not_visited() {
+    let data = get_data();
+    let (finished, events, offset) = visit(&data, "skipped", "");
+
+    assert!(finished);
+    assert_eq!(events, vec!["begin", "key skipped", "key after", "value kept", "end"]);
+    assert_eq!(offset, data.len());
+  }
+
+  #[test]
+  fn skips_end_right_after_the_subtree() {
+    let data = get_data();
+    let after_offset = data.len() - field(FieldType::String, "after").len() - b"kept\0".len() - 1;
+
+    //? Stopping at the next key leaves the reader right after that key, so the skip must have ended where the "after" field starts.
+    let (finished, events, offset) = visit(&data, "skipped", "after");
+
+    assert!(!finished);
+    assert_eq!(events, vec!["begin", "key skipped", "key after"]);
+    assert_eq!(offset, after_offset + field(FieldType::String, "after").len());
+  }
+
+  #[test]
+  fn unskipped_subtrees_are_visited() {
+    let data = get_data();
+    let (finished, events, _) = visit(&data, "", "");
+
+    assert!(finished);
+    assert_eq!(events, vec![
+      "begin", "key skipped", "begin", "key name", "value Portal", "key appid", "value ?", "key wide", "value hi", "key big", "value ?",
+      "key nested", "begin", "key scale", "value ?", "end", "end", "key after", "value kept", "end",
+    ]);
+  }
+
+  #[test]
+  fn stop_ends_the_walk_early() {
+    let data = get_data();
+    let (finished, events, _) = visit(&data, "", "appid");
+
+    assert!(!finished);
+    assert_eq!(events, vec!["begin", "key skipped", "begin", "key name", "value Portal", "key appid"]);
+  }
+
+  #[test]
+  fn visit_slice_reports_stops() {
+    let data = get_data();
+    let mut visitor = RecordingVisitor { skip: "skipped", stop: "after", events: Vec::new() };
+
+    assert!(!visit_slice(&data, &mut visitor).unwrap());
+
+    let mut visitor = RecordingVisitor { skip: "skipped", stop: "", events: Vec::new() };
+
+    assert!(visit_slice(&data, &mut visitor).unwrap());
+  }
+}
@@ -1,340 +1,263 @@
-trait HasByteConvert {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: Self, offset: usize) -> u8;
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: Self, offset: usize) -> u8;
-}
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-impl HasByteConvert for u8 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: u8, offset: usize) -> u8 {
-    bytes[offset] = data;
-    return 1;
-  }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: u8, offset: usize) -> u8 {
-    bytes[offset] = data;
-    return 1;
-  }
-}
+use crate::error::VdfError;
 
-impl HasByteConvert for u16 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: u16, offset: usize) -> u8 {
-    let u8_arr = data.to_le_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    return 2;
-  }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: u16, offset: usize) -> u8 {
-    let u8_arr = data.to_be_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    return 2;
-  }
+trait HasByteConvert: Sized {
+  fn write_le_bytes<W: Write>(sink: &mut W, data: Self) -> io::Result<u8>;
+  fn write_be_bytes<W: Write>(sink: &mut W, data: Self) -> io::Result<u8>;
 }
 
-impl HasByteConvert for u32 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: u32, offset: usize) -> u8 {
-    let u8_arr = data.to_le_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    return 4;
-  }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: u32, offset: usize) -> u8 {
-    let u8_arr = data.to_be_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    return 4;
-  }
+macro_rules! impl_has_byte_convert {
+  ($($type:ty),*) => {
+    $(
+      impl HasByteConvert for $type {
+        fn write_le_bytes<W: Write>(sink: &mut W, data: $type) -> io::Result<u8> {
+          sink.write_all(&data.to_le_bytes())?;
+          return Ok(std::mem::size_of::<$type>() as u8);
+        }
+        fn write_be_bytes<W: Write>(sink: &mut W, data: $type) -> io::Result<u8> {
+          sink.write_all(&data.to_be_bytes())?;
+          return Ok(std::mem::size_of::<$type>() as u8);
+        }
+      }
+    )*
+  };
 }
 
-impl HasByteConvert for u64 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: u64, offset: usize) -> u8 {
-    let u8_arr = data.to_le_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    bytes[offset+4] = u8_arr[4];
-    bytes[offset+5] = u8_arr[5];
-    bytes[offset+6] = u8_arr[6];
-    bytes[offset+7] = u8_arr[7];
-    return 8;
-  }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: u64, offset: usize) -> u8 {
-    let u8_arr = data.to_be_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    bytes[offset+4] = u8_arr[4];
-    bytes[offset+5] = u8_arr[5];
-    bytes[offset+6] = u8_arr[6];
-    bytes[offset+7] = u8_arr[7];
-    return 8;
-  }
-}
+impl_has_byte_convert!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
 
-impl HasByteConvert for i8 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: i8, offset: usize) -> u8 {
-    bytes[offset] = data.to_le_bytes()[0];
-    return 1;
-  }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: i8, offset: usize) -> u8 {
-    bytes[offset] = data.to_be_bytes()[0];
-    return 1;
-  }
-}
+/// Counts the temporary files made by `write_file_atomic`, so writes from different threads never share one.
+static TEMP_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
 
-impl HasByteConvert for i16 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: i16, offset: usize) -> u8 {
-    let u8_arr = data.to_le_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    return 2;
-  }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: i16, offset: usize) -> u8 {
-    let u8_arr = data.to_be_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    return 2;
-  }
-}
+/// Writes a file without ever leaving it half written. The data is written to a temporary file next to it, synced to disk, and then renamed over it.
+/// If writing fails, the original file is left untouched and the temporary file is removed.
+/// Each write gets its own temporary file, named after the process and a counter, so concurrent writes to the same file can't interleave. The last rename wins.
+pub fn write_file_atomic<F: FnOnce(&mut BufWriter<File>) -> Result<(), VdfError>>(path: &PathBuf, write: F) -> Result<(), VdfError> {
+  let mut temp_name: OsString = path.file_name().map(| name | name.to_owned()).unwrap_or_default();
+  temp_name.push(format!(".{}.{}.tmp", process::id(), TEMP_FILE_COUNT.fetch_add(1, Ordering::Relaxed)));
+  let temp_path = path.with_file_name(temp_name);
 
-impl HasByteConvert for i32 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: i32, offset: usize) -> u8 {
-    let u8_arr = data.to_le_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    return 4;
-  }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: i32, offset: usize) -> u8 {
-    let u8_arr = data.to_be_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    return 4;
-  }
-}
+  let write_res = File::create(&temp_path).map_err(VdfError::from).and_then(| file | {
+    let mut sink = BufWriter::new(file);
+    write(&mut sink)?;
 
-impl HasByteConvert for i64 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: i64, offset: usize) -> u8 {
-    let u8_arr = data.to_le_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    bytes[offset+4] = u8_arr[4];
-    bytes[offset+5] = u8_arr[5];
-    bytes[offset+6] = u8_arr[6];
-    bytes[offset+7] = u8_arr[7];
-    return 8;
-  }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: i64, offset: usize) -> u8 {
-    let u8_arr = data.to_be_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    bytes[offset+4] = u8_arr[4];
-    bytes[offset+5] = u8_arr[5];
-    bytes[offset+6] = u8_arr[6];
-    bytes[offset+7] = u8_arr[7];
-    return 8;
-  }
-}
+    let file = sink.into_inner().map_err(| err | err.into_error())?;
+    file.sync_all()?;
 
-impl HasByteConvert for f32 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: f32, offset: usize) -> u8 {
-    let u8_arr = data.to_le_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    return 4;
-  }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: f32, offset: usize) -> u8 {
-    let u8_arr = data.to_be_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    return 4;
+    return Ok(());
+  });
+
+  if let Err(err) = write_res {
+    let _ = fs::remove_file(&temp_path);
+    return Err(err);
   }
-}
 
-impl HasByteConvert for f64 {
-  fn to_le_bytes(bytes: &mut Vec<u8>, data: f64, offset: usize) -> u8 {
-    let u8_arr = data.to_le_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    bytes[offset+4] = u8_arr[4];
-    bytes[offset+5] = u8_arr[5];
-    bytes[offset+6] = u8_arr[6];
-    bytes[offset+7] = u8_arr[7];
-    return 8;
+  //? Renaming within a directory replaces the original in one step, so it is either the old file or the new one, never a mix.
+  if let Err(err) = fs::rename(&temp_path, path) {
+    let _ = fs::remove_file(&temp_path);
+    return Err(err.into());
   }
-  fn to_be_bytes(bytes: &mut Vec<u8>, data: f64, offset: usize) -> u8 {
-    let u8_arr = data.to_be_bytes();
-    bytes[offset] = u8_arr[0];
-    bytes[offset+1] = u8_arr[1];
-    bytes[offset+2] = u8_arr[2];
-    bytes[offset+3] = u8_arr[3];
-    bytes[offset+4] = u8_arr[4];
-    bytes[offset+5] = u8_arr[5];
-    bytes[offset+6] = u8_arr[6];
-    bytes[offset+7] = u8_arr[7];
-    return 8;
+
+  //? The rename is only durable once the directory entry is, so a crash right after it can't bring back the old file.
+  #[cfg(unix)]
+  {
+    let parent = path.parent().filter(| parent | !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    File::open(parent)?.sync_all()?;
   }
+
+  return Ok(());
 }
 
-pub struct Writer<'a> {
-  data: &'a mut Vec<u8>,
+/// Writes binary data to any sink, like a `Vec<u8>`, a `File` or a `BufWriter`, keeping track of how many bytes have been written.
+pub struct Writer<W: Write> {
+  sink: W,
   offset: usize,
 }
 
 #[allow(dead_code)]
-impl Writer<'_> {
-  /// Gets the underlying data of the writer.
-  pub fn get_data(&self) -> &[u8] { return &self.data[..]; }
-  /// Gets the offset of the writer.
+impl<W: Write> Writer<W> {
+  /// Gets the number of bytes written so far.
   pub fn get_offset(&self) -> usize { return self.offset; }
+  /// Gets a reference to the underlying sink.
+  pub fn get_ref(&self) -> &W { return &self.sink; }
+  /// Gets a mutable reference to the underlying sink. Bytes written directly to it aren't counted by `get_offset`.
+  pub fn get_mut(&mut self) -> &mut W { return &mut self.sink; }
+  /// Consumes the writer, returning the underlying sink.
+  pub fn into_inner(self) -> W { return self.sink; }
 
-  /// Creates a new Writer from the provided buffer.
-  pub fn new(buf: &mut Vec<u8>) -> Writer<'_> { return Writer { data: buf, offset: 0 }; }
-
-  /// Seek to a new offset, from 0 (start), 1 (current), or 2 (end) of the buffer.
-  pub fn seek(&mut self, offset: usize, position: u8) {
-    if position == 0 {
-      self.offset = offset;
-    } else if position == 1 {
-      self.offset += offset;
-    } else {
-      self.offset = (self.data.len() as usize) - offset;
-    }
-  }
-  
-  /// Expands the capacity of the underlying buffer.
-  fn expand_capacity(&mut self) {
-    self.data.resize(self.data.len() * 2, 0);
-  }
+  /// Creates a new Writer that writes to the provided sink.
+  pub fn new(sink: W) -> Writer<W> { return Writer { sink, offset: 0 }; }
 
   /// Data writing interface.
-  fn write_i<T: HasByteConvert>(&mut self, data: T, length: u8, endianness: bool) -> u8 {
-    if self.remaining() <= length.into() {
-      self.expand_capacity()
-    }
-
-    if endianness {
-      return T::to_le_bytes(self.data, data, self.offset);
+  fn write_i<T: HasByteConvert>(&mut self, data: T, endianness: bool) -> io::Result<u8> {
+    let length = if endianness {
+      T::write_le_bytes(&mut self.sink, data)?
     } else {
-      return T::to_be_bytes(self.data, data, self.offset);
-    }
-  }
+      T::write_be_bytes(&mut self.sink, data)?
+    };
 
-  /// Trims any excess bytes from the underlying buffer.
-  pub fn trim(&mut self) {
-    self.data.truncate(self.offset);
+    self.offset += length as usize;
+    return Ok(length);
   }
 
-  /// Gets the remaining length of the buffer.
-  pub fn remaining(&mut self) -> usize {
-    return self.data.len() - (self.offset + 1);
-  }
-  
-  /// Writes an 8 bit unsigned int to the buffer.
-  pub fn write_uint8(&mut self, data: u8, endianness: bool) -> u8 {
-    let res = self.write_i::<u8>(data, 1, endianness);
-    self.offset += 1;
-    return res;
+  /// Writes an 8 bit unsigned int.
+  pub fn write_uint8(&mut self, data: u8, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<u8>(data, endianness);
   }
-  /// Writes a 16 bit unsigned int to the buffer.
-  pub fn write_uint16(&mut self, data: u16, endianness: bool) -> u8 {
-    let res = self.write_i::<u16>(data, 2, endianness);
-    self.offset += 2;
-    return res;
+  /// Writes a 16 bit unsigned int.
+  pub fn write_uint16(&mut self, data: u16, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<u16>(data, endianness);
   }
-  /// Writes a 32 bit unsigned int to the buffer.
-  pub fn write_uint32(&mut self, data: u32, endianness: bool) -> u8 {
-    let res = self.write_i::<u32>(data, 4, endianness);
-    self.offset += 4;
-    return res;
+  /// Writes a 32 bit unsigned int.
+  pub fn write_uint32(&mut self, data: u32, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<u32>(data, endianness);
   }
-  /// Writes a 64 bit unsigned int to the buffer.
-  pub fn write_uint64(&mut self, data: u64, endianness: bool) -> u8 {
-    let res = self.write_i::<u64>(data, 8, endianness);
-    self.offset += 8;
-    return res;
+  /// Writes a 64 bit unsigned int.
+  pub fn write_uint64(&mut self, data: u64, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<u64>(data, endianness);
   }
-  
-  /// Writes an 8 bit signed int to the buffer.
-  pub fn write_int8(&mut self, data: i8, endianness: bool) -> u8 {
-    let res = self.write_i::<i8>(data, 1, endianness);
-    self.offset += 1;
-    return res;
+
+  /// Writes an 8 bit signed int.
+  pub fn write_int8(&mut self, data: i8, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<i8>(data, endianness);
   }
-  /// Writes a 16 bit signed int to the buffer.
-  pub fn write_int16(&mut self, data: i16, endianness: bool) -> u8 {
-    let res = self.write_i::<i16>(data, 2, endianness);
-    self.offset += 2;
-    return res;
+  /// Writes a 16 bit signed int.
+  pub fn write_int16(&mut self, data: i16, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<i16>(data, endianness);
   }
-  /// Writes a 32 bit signed int to the buffer.
-  pub fn write_int32(&mut self, data: i32, endianness: bool) -> u8 {
-    let res = self.write_i::<i32>(data, 4, endianness);
-    self.offset += 4;
-    return res;
+  /// Writes a 32 bit signed int.
+  pub fn write_int32(&mut self, data: i32, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<i32>(data, endianness);
   }
-  /// Writes a 64 bit signed int to the buffer.
-  pub fn write_int64(&mut self, data: i64, endianness: bool) -> u8 {
-    let res = self.write_i::<i64>(data, 8, endianness);
-    self.offset += 8;
-    return res;
+  /// Writes a 64 bit signed int.
+  pub fn write_int64(&mut self, data: i64, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<i64>(data, endianness);
   }
-  
-  /// Writes a 32 bit float to the buffer.
-  pub fn write_float32(&mut self, data: f32, endianness: bool) -> u8 {
-    let res = self.write_i::<f32>(data, 4, endianness);
-    self.offset += 4;
-    return res;
+
+  /// Writes a 32 bit float.
+  pub fn write_float32(&mut self, data: f32, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<f32>(data, endianness);
   }
-  /// Writes a 64 bit float to the buffer.
-  pub fn write_float64(&mut self, data: f64, endianness: bool) -> u8 {
-    let res = self.write_i::<f64>(data, 8, endianness);
-    self.offset += 8;
-    return res;
+  /// Writes a 64 bit float.
+  pub fn write_float64(&mut self, data: f64, endianness: bool) -> io::Result<u8> {
+    return self.write_i::<f64>(data, endianness);
   }
 
-  /// Writes a string to the buffer, optionally prefixing it with its length.
-  pub fn write_string(&mut self, data: String, prefix_with_length: bool, endianness: bool) -> u32 {
-    let str_bytes = data.into_bytes();
-    let str_bytes_len = str_bytes.len();
-    let mut length_to_return = str_bytes_len;
+  /// Writes a null terminated string, optionally prefixing it with its length.
+  pub fn write_string(&mut self, data: &str, prefix_with_length: bool, endianness: bool) -> io::Result<u32> {
+    let str_bytes = data.as_bytes();
+    let mut length_to_return = str_bytes.len();
 
     if prefix_with_length {
-      self.write_uint32(str_bytes_len as u32, endianness);
+      self.write_uint32(str_bytes.len() as u32, endianness)?;
       length_to_return += 4;
     }
-    
-    if self.remaining() <= str_bytes_len {
-      self.expand_capacity()
-    }
-    
-    let mut i: usize = 0;
-    while i < str_bytes_len {
-      self.data[self.offset + i] = str_bytes[i];
-      i += 1;
+
+    self.write_bytes(str_bytes)?;
+    self.write_uint8(0x00, endianness)?;
+
+    return Ok(length_to_return as u32);
+  }
+
+  /// Writes raw bytes.
+  pub fn write_bytes(&mut self, data: &[u8]) -> io::Result<u32> {
+    self.sink.write_all(data)?;
+    self.offset += data.len();
+
+    return Ok(data.len() as u32);
+  }
+
+  /// Writes a string as null terminated UTF-16LE.
+  pub fn write_wide_string(&mut self, data: &str, endianness: bool) -> io::Result<u32> {
+    let mut length_to_return: u32 = 0;
+
+    for code_unit in data.encode_utf16() {
+      length_to_return += self.write_uint16(code_unit, endianness)? as u32;
     }
 
-    self.data[self.offset + str_bytes_len] = 0x00;
+    length_to_return += self.write_uint16(0, endianness)? as u32;
 
-    self.offset += str_bytes_len + 1;
+    return Ok(length_to_return);
+  }
+}
 
-    return length_to_return as u32;
+impl<W: Write> Write for Writer<W> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let written = self.sink.write(buf)?;
+    self.offset += written;
+
+    return Ok(written);
   }
-}
\ No newline at end of file
+
+  fn flush(&mut self) -> io::Result<()> {
+    return self.sink.flush();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::get_test_dir;
+
+  /// Lists the names of the files in a dir.
+  fn list_files(dir: &PathBuf) -> Vec<String> {
+    return fs::read_dir(dir).unwrap().map(| entry | entry.unwrap().file_name().into_string().unwrap()).collect();
+  }
+
+  #[test]
+  fn write_file_atomic_replaces_the_file() {
+    let dir = get_test_dir("write-atomic");
+    let path = dir.join("shortcuts.vdf");
+    fs::write(&path, b"before").unwrap();
+
+    write_file_atomic(&path, | sink | Ok(sink.write_all(b"after")?)).unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), b"after");
+    assert_eq!(list_files(&dir), vec![String::from("shortcuts.vdf")]);
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn write_file_atomic_keeps_the_file_if_writing_fails() {
+    let dir = get_test_dir("write-atomic-fails");
+    let path = dir.join("shortcuts.vdf");
+    fs::write(&path, b"before").unwrap();
+
+    let write_res = write_file_atomic(&path, | sink | {
+      sink.write_all(b"half")?;
+      return Err(VdfError::InvalidData(String::from("failed")));
+    });
+
+    assert!(matches!(write_res, Err(VdfError::InvalidData(_))));
+    assert_eq!(fs::read(&path).unwrap(), b"before");
+    assert_eq!(list_files(&dir), vec![String::from("shortcuts.vdf")]);
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn write_file_atomic_uses_a_new_temp_file_each_time() {
+    let dir = get_test_dir("write-atomic-temp");
+    let path = dir.join("shortcuts.vdf");
+    let mut temp_names = Vec::new();
+
+    for _ in 0..2 {
+      write_file_atomic(&path, | _ | {
+        temp_names.extend(list_files(&dir).into_iter().filter(| name | name.ends_with(".tmp")));
+        return Ok(());
+      }).unwrap();
+    }
+
+    assert_eq!(temp_names.len(), 2);
+    assert_ne!(temp_names[0], temp_names[1]);
+    assert!(temp_names.iter().all(| name | name.starts_with(&format!("shortcuts.vdf.{}.", process::id()))));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
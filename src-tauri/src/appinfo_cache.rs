@@ -0,0 +1,234 @@
+use crate::logger;
+
+use std::collections::HashMap;
+use std::fs::{self, create_dir_all};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use new_vdf_parser::appinfo_vdf_parser::{AppInfoFilter, AppInfoIndex};
+use new_vdf_parser::writer::write_file_atomic;
+use new_vdf_parser::{KvMapRef, KvValueRef, LibraryAssets, VdfError};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+/// Bump this when the shape of the cached summaries changes, so old caches are rebuilt instead of being sent to the frontend.
+const APPINFO_CACHE_VERSION: u32 = 4;
+
+/// The keys of each app that summaries and library assets are built from.
+const SUMMARY_KEYS: [&str; 8] = [
+  "common/name",
+  "common/type",
+  "common/icon",
+  "common/clienticon",
+  "common/clienttga",
+  "common/header_image",
+  "common/library_assets",
+  "common/library_assets_full",
+];
+
+/// The parts of an app's appinfo that the frontend uses.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AppSummary {
+  pub appid: u32,
+  pub name: String,
+  #[serde(rename = "type")]
+  pub app_type: String,
+}
+
+/// An app's summary and library assets, along with the header fields that tell if it has changed.
+/// Apps without a "common" section have neither, and apps without a "common"/"type" have no summary, but both are still cached so they aren't decoded again.
+#[derive(Serialize, Deserialize)]
+struct CachedApp {
+  change_number: u32,
+  last_updated: u32,
+  summary: Option<AppSummary>,
+  library_assets: Option<LibraryAssets>,
+}
+
+/// The apps loaded from appinfo.vdf, along with which version of the file they were loaded from.
+struct LoadedApps {
+  appinfo_path: PathBuf,
+  modified: SystemTime,
+  len: u64,
+  apps: Arc<Vec<(u32, CachedApp)>>,
+}
+
+/// The apps from the last time appinfo.vdf was loaded. Summaries and library assets are both built from them, so the file is only read once while it hasn't changed.
+static LOADED_APPS: Mutex<Option<LoadedApps>> = Mutex::new(None);
+
+/// The cache of app summaries, keyed by appid.
+#[derive(Serialize, Deserialize, Default)]
+struct AppInfoCache {
+  version: u32,
+  apps: HashMap<u32, CachedApp>,
+}
+
+/// Gets the path of the appinfo cache in the app's data directory.
+fn get_appinfo_cache_path(app_handle: &AppHandle) -> PathBuf {
+  let app_data_dir: PathBuf = app_handle.to_owned().path().app_data_dir().expect("Tried to resolve app data dir and failed.");
+
+  if !app_data_dir.exists() {
+    create_dir_all(&app_data_dir).expect("Failed to make directory");
+  }
+
+  return app_data_dir.join("appinfo_cache.json");
+}
+
+/// Loads the appinfo cache. A missing, unreadable or outdated cache is treated as empty.
+fn load_appinfo_cache(app_handle: &AppHandle, cache_path: &PathBuf) -> AppInfoCache {
+  if !cache_path.exists() {
+    return AppInfoCache { version: APPINFO_CACHE_VERSION, apps: HashMap::new() };
+  }
+
+  let cache_res = fs::read_to_string(cache_path).map_err(| err | err.to_string())
+    .and_then(| contents | serde_json::from_str::<AppInfoCache>(&contents).map_err(| err | err.to_string()));
+
+  match cache_res {
+    Ok(cache) if cache.version == APPINFO_CACHE_VERSION => return cache,
+    Ok(_) => {
+      logger::log_to_core_file(app_handle.to_owned(), "Appinfo cache is from an older version, rebuilding it.", 0);
+    },
+    Err(err) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to load appinfo cache, rebuilding it: {}", err).as_str(), 1);
+    },
+  }
+
+  return AppInfoCache { version: APPINFO_CACHE_VERSION, apps: HashMap::new() };
+}
+
+/// Builds the summary of an app from its "appinfo" map. Returns `None` if it has no "common" section or no "common"/"type".
+/// Apps without a type are dropped, like `AppInfoFilter` drops them, since they can't be matched against the app types setting.
+fn get_app_summary(appid: u32, appinfo: &KvMapRef) -> Option<AppSummary> {
+  let common = appinfo.get("common")?.as_map()?;
+
+  //? Some apps have numeric names, so anything that isn't a string is written as it would be in text KeyValues.
+  let name = match common.get("name") {
+    Some(KvValueRef::String(name)) | Some(KvValueRef::WideString(name)) => name.to_string(),
+    Some(other) => other.as_u64().map(| number | number.to_string()).unwrap_or_default(),
+    None => String::new(),
+  };
+
+  let app_type = common.get("type")?.as_str()?.to_owned();
+
+  return Some(AppSummary { appid, name, app_type });
+}
+
+/// Decodes the library assets of an app from its "appinfo" map. Returns `None` if it has no "common" section.
+fn get_app_library_assets(appinfo: &KvMapRef) -> Option<LibraryAssets> {
+  let common = appinfo.get("common")?.as_map()?;
+
+  return Some(LibraryAssets::from_common(&common.to_kv_map()));
+}
+
+/// Gets the cached data of every app in appinfo.vdf, in file order.
+/// The apps are kept in memory until appinfo.vdf changes, so getting summaries and library assets on the same launch only reads the file once.
+fn get_cached_apps(app_handle: &AppHandle, appinfo_path: &PathBuf) -> Result<Arc<Vec<(u32, CachedApp)>>, VdfError> {
+  let metadata = fs::metadata(appinfo_path)?;
+  let modified = metadata.modified()?;
+
+  //? Holding the lock while loading makes a second caller wait for the first load instead of starting its own.
+  let mut loaded_apps = LOADED_APPS.lock().unwrap_or_else(| poisoned | poisoned.into_inner());
+
+  let is_unchanged = | loaded: &&LoadedApps | &loaded.appinfo_path == appinfo_path && loaded.modified == modified && loaded.len == metadata.len();
+
+  if let Some(loaded) = loaded_apps.as_ref().filter(is_unchanged) {
+    return Ok(loaded.apps.clone());
+  }
+
+  let apps = Arc::new(load_cached_apps(app_handle, appinfo_path)?);
+  *loaded_apps = Some(LoadedApps { appinfo_path: appinfo_path.to_owned(), modified, len: metadata.len(), apps: apps.clone() });
+
+  return Ok(apps);
+}
+
+/// Writes the appinfo cache. It is replaced atomically, so a crash while writing leaves the old cache instead of a half written one.
+fn write_appinfo_cache(cache_path: &PathBuf, cache: &AppInfoCache) -> Result<(), VdfError> {
+  return write_file_atomic(cache_path, | sink | {
+    serde_json::to_writer(sink, cache).map_err(| err | VdfError::InvalidData(err.to_string()))?;
+    return Ok(());
+  });
+}
+
+/// Loads the data of every app in appinfo.vdf, in file order.
+/// Apps whose change number and last updated time match the cache are taken from it, and only the rest are decoded.
+/// Apps that can't be decoded are logged and left out, instead of failing the whole file.
+fn load_cached_apps(app_handle: &AppHandle, appinfo_path: &PathBuf) -> Result<Vec<(u32, CachedApp)>, VdfError> {
+  let cache_path = get_appinfo_cache_path(app_handle);
+  let mut cache = load_appinfo_cache(app_handle, &cache_path);
+
+  let projection = AppInfoFilter::new(None, Some(&SUMMARY_KEYS));
+
+  let index = AppInfoIndex::open(appinfo_path)?;
+  let mut apps: Vec<(u32, CachedApp)> = Vec::with_capacity(index.len());
+  let mut decoded_count: usize = 0;
+  let mut skipped_count: usize = 0;
+
+  for header in index.headers() {
+    let cached = cache.apps.remove(&header.appid)
+      .filter(| cached | cached.change_number == header.change_number && cached.last_updated == header.last_updated);
+
+    let cached_app = match cached {
+      Some(cached) => cached,
+      None => {
+        //? Only the summary keys are decoded, everything else in the app is skipped.
+        let appinfo = match index.get_filtered_ref(header.appid, &projection) {
+          Ok(appinfo) => appinfo.unwrap_or_default(),
+          Err(err) => {
+            logger::log_to_core_file(app_handle.to_owned(), format!("Skipping app {} in appinfo.vdf: {}", header.appid, err).as_str(), 1);
+            skipped_count += 1;
+            continue;
+          },
+        };
+
+        decoded_count += 1;
+
+        CachedApp {
+          change_number: header.change_number,
+          last_updated: header.last_updated,
+          summary: get_app_summary(header.appid, &appinfo),
+          library_assets: get_app_library_assets(&appinfo),
+        }
+      },
+    };
+
+    apps.push((header.appid, cached_app));
+  }
+
+  logger::log_to_core_file(app_handle.to_owned(), format!("Loaded {} apps from the appinfo cache, decoded {} and skipped {}.", apps.len() - decoded_count, decoded_count, skipped_count).as_str(), 0);
+
+  //? Anything left in the old cache was removed from appinfo.vdf, so only rewrite the cache if something changed.
+  //? Skipped apps aren't cached, so they are tried again once Steam rewrites them.
+  if decoded_count > 0 || !cache.apps.is_empty() {
+    let mut new_cache = AppInfoCache { version: APPINFO_CACHE_VERSION, apps: apps.drain(..).collect() };
+
+    if let Err(err) = write_appinfo_cache(&cache_path, &new_cache) {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to write appinfo cache: {}", err).as_str(), 1);
+    }
+
+    apps = index.appids().filter_map(| appid | new_cache.apps.remove(&appid).map(| app | (appid, app))).collect();
+  }
+
+  return Ok(apps);
+}
+
+/// Gets the summary of every app in appinfo.vdf whose type is in `app_types`, in file order.
+pub fn get_app_summaries(app_handle: &AppHandle, appinfo_path: &PathBuf, app_types: &[&str]) -> Result<Vec<AppSummary>, VdfError> {
+  //? Every app is cached regardless of its type, so changing the app types setting doesn't need the file to be decoded again.
+  let type_filter = AppInfoFilter::new(Some(app_types), None);
+  let apps = get_cached_apps(app_handle, appinfo_path)?;
+
+  let summaries = apps.iter()
+    .filter_map(| (_, cached_app) | cached_app.summary.clone())
+    .filter(| summary | type_filter.matches_type(Some(&summary.app_type)))
+    .collect();
+
+  return Ok(summaries);
+}
+
+/// Gets the library assets of every app in appinfo.vdf that has them, keyed by appid.
+pub fn get_library_assets(app_handle: &AppHandle, appinfo_path: &PathBuf) -> Result<HashMap<u32, LibraryAssets>, VdfError> {
+  let apps = get_cached_apps(app_handle, appinfo_path)?;
+
+  return Ok(apps.iter().filter_map(| (appid, cached_app) | cached_app.library_assets.clone().map(| assets | (*appid, assets))).collect());
+}
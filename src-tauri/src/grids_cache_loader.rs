@@ -1,5 +1,6 @@
 use crate::steam;
 use crate::logger;
+use crate::appinfo_cache;
 use crate::types::GridInfo;
 use crate::types::GRID_CACHE_TYPES;
 use crate::types::LIBRARY_CACHE_TYPES;
@@ -9,6 +10,8 @@ use std::fs;
 use std::fs::DirEntry;
 use std::path::PathBuf;
 
+use new_vdf_parser::library_assets::DEFAULT_LANGUAGE;
+use new_vdf_parser::{LibraryAssets, LocalizedImage};
 use serde_json::{Value, Map};
 use tauri::AppHandle;
 
@@ -190,6 +193,43 @@ fn add_library_folder_to_cache(parent_path: PathBuf, entry: DirEntry, dirname: &
   }
 }
 
+/// Gets the librarycache file names of an app's assets in the provided language.
+fn get_grid_info(library_assets: &LibraryAssets, language: &str) -> GridInfo {
+  let get_image = | image: &Option<LocalizedImage> | image.as_ref().and_then(| image | image.get(language)).unwrap_or_default().to_owned();
+
+  return GridInfo {
+    icon: library_assets.get_icon_file_name().unwrap_or_default(),
+    capsule: get_image(&library_assets.capsule),
+    wideCapsule: library_assets.get_header(language).unwrap_or_default().to_owned(),
+    hero: get_image(&library_assets.hero),
+    logo: get_image(&library_assets.logo),
+  };
+}
+
+/// Gets the grid info of the provided steam apps from their library assets in appinfo.vdf, using the user's Steam language.
+fn get_steam_apps_grid_info(app_handle: &AppHandle, steam_path: String, steam_app_ids: &Vec<String>) -> HashMap<String, Option<GridInfo>> {
+  let language = steam::get_steam_language().unwrap_or(DEFAULT_LANGUAGE.to_owned());
+  let appinfo_path: PathBuf = PathBuf::from(steam::get_appinfo_path(app_handle.to_owned(), steam_path));
+
+  let mut library_assets = match appinfo_cache::get_library_assets(app_handle, &appinfo_path) {
+    Ok(library_assets) => library_assets,
+    Err(err) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to read library assets from appinfo.vdf: {}", err).as_str(), 2);
+      HashMap::new()
+    },
+  };
+
+  logger::log_to_core_file(app_handle.to_owned(), format!("Using library assets for language \"{}\".", language).as_str(), 0);
+
+  return steam_app_ids.iter().map(| app_id | {
+    let grid_info = app_id.parse::<u32>().ok()
+      .and_then(| appid | library_assets.remove(&appid))
+      .map(| assets | get_grid_info(&assets, &language));
+
+    return (app_id.to_owned(), grid_info);
+  }).collect();
+}
+
 /// Filters the user's library cache dir.
 async fn filter_library_dir(app_handle: &AppHandle, steam_path: String, steam_apps: &HashMap<String, Option<GridInfo>>, grid_cache_data: &mut Map<String, Value>) -> Map<String, Value> {
   let mut unfiltered_cache: Map<String, Value> = Map::new();
@@ -237,9 +277,11 @@ async fn filter_library_dir(app_handle: &AppHandle, steam_path: String, steam_ap
 
 #[tauri::command]
 /// Gets the cache data for the user's grids.
-pub async fn get_cache_data(app_handle: AppHandle, steam_path: String, steam_active_user_id: String, shortcut_ids: Vec<String>, steam_apps: HashMap<String, Option<GridInfo>>) -> (Map<String, Value>, Map<String, Value>, Vec<String>) {
+pub async fn get_cache_data(app_handle: AppHandle, steam_path: String, steam_active_user_id: String, shortcut_ids: Vec<String>, steam_app_ids: Vec<String>) -> (Map<String, Value>, Map<String, Value>, Vec<String>) {
   logger::log_to_core_file(app_handle.to_owned(), "Loading Grids Cache...", 0);
 
+  let steam_apps = get_steam_apps_grid_info(&app_handle, steam_path.clone(), &steam_app_ids);
+
   let (mut grid_cache_data, logo_configs) = filter_grids_dir(&app_handle, steam_path.clone(), steam_active_user_id, &shortcut_ids).await;
 
   let unfiltered_cache = filter_library_dir(&app_handle, steam_path, &steam_apps, &mut grid_cache_data).await;
@@ -1,9 +1,10 @@
 use std::{path::PathBuf, collections::HashMap, fs};
 
-use new_vdf_parser::shortcuts_vdf_parser::write_shortcuts_vdf;
+use new_vdf_parser::{get_shortcut_map_appid, KvMap, KvValue, VdfError};
 use serde_json::{Map, Value};
 
 use serde;
+use crate::shortcuts;
 use crate::steam;
 use tauri::{self, AppHandle};
 
@@ -116,6 +117,27 @@ fn check_for_shortcut_changes(shortcut_icons: &Map<String, Value>, original_shor
   return false;
 }
 
+/// Points the icon of each shortcut with a changed icon to its new path.
+fn set_shortcut_icons(shortcuts_data: &mut KvMap, paths_id_map: &HashMap<String, ChangedPath>) -> Result<(), VdfError> {
+  shortcuts_data.get_map_at("shortcuts")?;
+
+  for shortcut in shortcuts_data.query_mut("shortcuts/*") {
+    if let KvValue::Map(shortcut_map) = shortcut {
+      //? Older shortcuts.vdf files don't store appids, so those are computed the way Steam does.
+      let appid = match get_shortcut_map_appid(shortcut_map) {
+        Some(appid) => appid,
+        None => continue,
+      };
+      let path_key: String = format!("{}_icon", appid);
+
+      if let Some(changed_path) = paths_id_map.get(&path_key) {
+        shortcut_map.insert(String::from("icon"), KvValue::String(changed_path.targetPath.to_owned()));
+      }
+    }
+  }
+
+  return Ok(());
+}
 
 
 #[tauri::command]
@@ -195,31 +217,20 @@ pub async fn save_changes(app_handle: AppHandle, steam_path: String, steam_activ
   
   if should_change_shortcuts {
     logger::log_to_core_file(app_handle.to_owned(), "Changes to shortcuts detected. Writing shortcuts.vdf...", 0);
-    let mut shortcuts_data: Value = serde_json::from_str(shortcuts_str.as_str()).expect("Should have been able to parse json string.");
-
-    let shortcuts_obj_map: &mut Value = shortcuts_data.get_mut("shortcuts").expect("key: shortcuts should have existed.");
-    let shortcuts_map: &mut Map<String, Value> = shortcuts_obj_map.as_object_mut().expect("Should have been able to convert shortcuts to map");
-
-    for (_, shortcut) in shortcuts_map.into_iter() {
-      let shortcut_map: &mut Map<String, Value> = shortcut.as_object_mut().expect("should have been able to convert shortcut to map.");
-      let shortcut_appid_val: &Value = shortcut_map.get("appid").expect("shortcut should have had an appid");
-      let shortcut_appid_num: i64 = shortcut_appid_val.as_i64().expect("should have been able to convert shortcut appid to str.");
-      let shortcut_appid: String = shortcut_appid_num.to_string();
-
-      let path_key: String = format!("{}_icon", shortcut_appid.to_owned()).to_string();
-
-      if paths_id_map.contains_key(&path_key) {
-        let changed_path: &ChangedPath = paths_id_map.get(&path_key).expect("entry should have existed.");
-        shortcut_map.insert(String::from("icon"), Value::String(changed_path.targetPath.to_owned()));
-      }
+    let shortcuts_vdf_path: PathBuf = PathBuf::from(steam::get_shortcuts_path(app_handle.to_owned(), steam_path.to_owned(), steam_active_user_id));
+    let write_res = serde_json::from_str::<Map<String, Value>>(shortcuts_str.as_str())
+      .map_err(| err | VdfError::InvalidData(err.to_string()))
+      .and_then(| shortcuts_json | shortcuts::shortcuts_from_json(&shortcuts_vdf_path, &shortcuts_json))
+      .and_then(| mut shortcuts_data | {
+        set_shortcut_icons(&mut shortcuts_data, &paths_id_map)?;
+        return shortcuts::write_shortcuts_file(&app_handle, &shortcuts_vdf_path, &shortcuts_data);
+      });
+
+    if let Err(err) = write_res {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to write shortcuts.vdf: {}", err).as_str(), 2);
+      return shortcuts::error_to_json(&err);
     }
 
-    let mut modified_shortcuts_data: Map<String, Value> = Map::new();
-    modified_shortcuts_data.insert(String::from("shortcuts"), shortcuts_obj_map.to_owned());
-    shortcuts_data = Value::Object(modified_shortcuts_data);
-
-    let shortcuts_vdf_path: PathBuf = PathBuf::from(steam::get_shortcuts_path(app_handle.to_owned(), steam_path.to_owned(), steam_active_user_id));
-    write_shortcuts_vdf(&shortcuts_vdf_path, shortcuts_data);
     logger::log_to_core_file(app_handle.to_owned(), "Changes to shortcuts saved.", 0);
   } else {
     logger::log_to_core_file(app_handle.to_owned(), "No changes to shortcuts detected. Skipping...", 0);
@@ -237,19 +248,21 @@ pub async fn save_changes(app_handle: AppHandle, steam_path: String, steam_activ
 }
 
 #[tauri::command]
-/// Writes the user's shortcuts.vdf file.
-pub async fn write_shortcuts(app_handle: AppHandle, steam_path: String, steam_active_user_id: String, shortcuts_str: String) -> bool {
+/// Writes the user's shortcuts.vdf file. Returns true, or the error along with any validation problems.
+pub async fn write_shortcuts(app_handle: AppHandle, steam_path: String, steam_active_user_id: String, shortcuts_str: String) -> String {
   logger::log_to_core_file(app_handle.to_owned(), "Writing shortcuts.vdf...", 0);
   let shortcuts_vdf_path: PathBuf = PathBuf::from(steam::get_shortcuts_path(app_handle.to_owned(), steam_path, steam_active_user_id));
-  let shortcuts_data: Value = serde_json::from_str(shortcuts_str.as_str()).expect("Should have been able to parse json string.");
 
-  let success: bool = write_shortcuts_vdf(&shortcuts_vdf_path, shortcuts_data);
+  let write_res = serde_json::from_str::<Map<String, Value>>(shortcuts_str.as_str())
+    .map_err(| err | VdfError::InvalidData(err.to_string()))
+    .and_then(| shortcuts_json | shortcuts::shortcuts_from_json(&shortcuts_vdf_path, &shortcuts_json))
+    .and_then(| shortcuts_data | shortcuts::write_shortcuts_file(&app_handle, &shortcuts_vdf_path, &shortcuts_data));
 
-  if success {
-    logger::log_to_core_file(app_handle.to_owned(), "Changes to shortcuts saved.", 0);
-    return true;
-  } else {
-    logger::log_to_core_file(app_handle.to_owned(), "Changes to shortcuts failed.", 0);
-    return false;
+  if let Err(err) = write_res {
+    logger::log_to_core_file(app_handle.to_owned(), format!("Changes to shortcuts failed: {}", err).as_str(), 2);
+    return shortcuts::error_to_json(&err);
   }
+
+  logger::log_to_core_file(app_handle.to_owned(), "Changes to shortcuts saved.", 0);
+  return String::from("true");
 }
@@ -1,8 +1,10 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod utils;
+mod appinfo_cache;
 mod handle_changes;
 mod steam;
+mod shortcuts;
 mod zip_controller;
 mod start_menu_tiles;
 mod grids_cache_loader;
@@ -181,6 +183,7 @@ fn main() {
       steam::get_grids_directory,
       steam::get_library_cache_directory,
       steam::get_appinfo_path,
+      steam::get_packageinfo_path,
       steam::get_shortcuts_path,
       steam::get_localconfig_path,
       steam::get_sourcemod_path,
@@ -200,6 +203,13 @@ fn main() {
       steam::get_installed_appids,
       handle_changes::save_changes,
       handle_changes::write_shortcuts,
+      shortcuts::get_shortcuts,
+      shortcuts::add_shortcut,
+      shortcuts::update_shortcut,
+      shortcuts::remove_shortcut,
+      shortcuts::renumber_shortcuts,
+      shortcuts::get_shortcuts_backups,
+      shortcuts::restore_shortcuts_backup,
       download_grid,
       copy_grid_to_selected,
       clean_grids::clean_grids,
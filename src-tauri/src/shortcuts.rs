@@ -0,0 +1,245 @@
+use std::fs::{self, create_dir_all};
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use serde_json::{Map, Value};
+use new_vdf_parser::shortcuts_vdf_parser::{open_shortcuts_vdf, write_shortcuts_vdf};
+use new_vdf_parser::writer::write_file_atomic;
+use new_vdf_parser::{validate_shortcuts_vdf, KvMap, Shortcut, ShortcutsFile, VdfError};
+use tauri::AppHandle;
+
+use crate::logger;
+use crate::steam;
+
+/// How many backups of shortcuts.vdf to keep. The oldest ones are removed when a new one is made.
+const SHORTCUTS_BACKUP_COUNT: usize = 5;
+/// The start of the file names of shortcuts.vdf backups, which are followed by when they were made.
+const SHORTCUTS_BACKUP_PREFIX: &str = "shortcuts.vdf.";
+/// The end of the file names of shortcuts.vdf backups.
+const SHORTCUTS_BACKUP_EXTENSION: &str = ".bak";
+
+/// Gets the file names of the backups of shortcuts.vdf in the user's config dir, newest first.
+fn get_backup_names(config_dir: &PathBuf) -> Vec<String> {
+  let mut backup_names: Vec<String> = match fs::read_dir(config_dir) {
+    Ok(entries) => entries.filter_map(| entry | entry.ok())
+      .filter_map(| entry | entry.file_name().into_string().ok())
+      .filter(| name | name.starts_with(SHORTCUTS_BACKUP_PREFIX) && name.ends_with(SHORTCUTS_BACKUP_EXTENSION))
+      .collect(),
+    Err(_) => Vec::new(),
+  };
+
+  //? The timestamps are zero padded down to the millisecond, so they sort the same way as the times they represent.
+  backup_names.sort();
+  backup_names.reverse();
+
+  return backup_names;
+}
+
+/// Copies the user's shortcuts.vdf file to a timestamped backup next to it, and removes the oldest backups past `SHORTCUTS_BACKUP_COUNT`.
+/// A backup that already has the same timestamp is never overwritten, since it may be the only copy of an older file.
+/// The backup named `keep_backup` is never removed, even if it is one of the oldest.
+fn backup_shortcuts(app_handle: &AppHandle, shortcuts_path: &PathBuf, keep_backup: Option<&str>) {
+  let config_dir = match shortcuts_path.parent() {
+    Some(config_dir) => config_dir.to_path_buf(),
+    None => return,
+  };
+
+  if !shortcuts_path.exists() {
+    return;
+  }
+
+  let timestamp = Local::now().format("%Y-%m-%d_%H-%M-%S%.3f");
+  let backup_path = config_dir.join(format!("{}{}{}", SHORTCUTS_BACKUP_PREFIX, timestamp, SHORTCUTS_BACKUP_EXTENSION));
+
+  if backup_path.exists() {
+    logger::log_to_core_file(app_handle.to_owned(), format!("Shortcuts.vdf backup {} already exists, skipping backup.", backup_path.display()).as_str(), 1);
+    return;
+  }
+
+  if let Err(err) = fs::copy(shortcuts_path, &backup_path) {
+    logger::log_to_core_file(app_handle.to_owned(), format!("Failed to back up shortcuts.vdf: {}", err).as_str(), 1);
+    return;
+  }
+
+  //? Keeping a backup past the limit leaves one extra, which the next backup removes.
+  for old_backup in get_backup_names(&config_dir).iter().skip(SHORTCUTS_BACKUP_COUNT).filter(| old_backup | Some(old_backup.as_str()) != keep_backup) {
+    if let Err(err) = fs::remove_file(config_dir.join(old_backup)) {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to remove old shortcuts.vdf backup {}: {}", old_backup, err).as_str(), 1);
+    }
+  }
+}
+
+/// Writes the user's shortcuts.vdf file, after validating the data and backing up the current one.
+/// Data Steam would discard is rejected before anything touches disk. Backups are best effort, so failing to make one is logged instead of stopping the write.
+pub fn write_shortcuts_file(app_handle: &AppHandle, shortcuts_path: &PathBuf, shortcuts_data: &KvMap) -> Result<(), VdfError> {
+  let problems = validate_shortcuts_vdf(shortcuts_data);
+
+  if !problems.is_empty() {
+    return Err(VdfError::InvalidShortcuts(problems));
+  }
+
+  backup_shortcuts(app_handle, shortcuts_path, None);
+
+  if let Some(config_dir) = shortcuts_path.parent() {
+    create_dir_all(config_dir)?;
+  }
+
+  return write_shortcuts_vdf(shortcuts_path, shortcuts_data);
+}
+
+/// Converts shortcuts the frontend sent as JSON to KeyValues, keeping the field types and end markers of the user's current shortcuts.vdf file.
+/// Without them, numbers would get whichever type fits their size, and unchanged shortcuts wouldn't be written back byte for byte.
+pub fn shortcuts_from_json(shortcuts_path: &PathBuf, shortcuts_json: &Map<String, Value>) -> Result<KvMap, VdfError> {
+  if !shortcuts_path.exists() {
+    return KvMap::from_json(shortcuts_json);
+  }
+
+  return KvMap::from_json_with_types(shortcuts_json, &open_shortcuts_vdf(shortcuts_path)?);
+}
+
+/// Converts an error to JSON for the frontend. Validation errors include each problem, so the frontend can show what was wrong.
+pub fn error_to_json(err: &VdfError) -> String {
+  match err {
+    VdfError::InvalidShortcuts(problems) => return serde_json::json!({ "error": err.to_string(), "problems": problems }).to_string(),
+    _ => return serde_json::json!({ "error": err.to_string() }).to_string(),
+  }
+}
+
+/// Opens the user's shortcuts.vdf file. Users who haven't added any non Steam games don't have one, so a missing file has no shortcuts.
+fn open_shortcuts_file(shortcuts_path: &PathBuf) -> Result<ShortcutsFile, VdfError> {
+  if !shortcuts_path.exists() {
+    return Ok(ShortcutsFile::new());
+  }
+
+  return ShortcutsFile::open(shortcuts_path);
+}
+
+/// Serializes the shortcuts for the frontend.
+fn shortcuts_to_json(shortcuts_file: &ShortcutsFile) -> String {
+  let shortcuts: Vec<&Shortcut> = shortcuts_file.shortcuts().collect();
+
+  return serde_json::to_string(&shortcuts).expect("Should have been able to serialize shortcuts to string.");
+}
+
+/// Applies an edit to the user's shortcuts.vdf file and writes it. Returns the edited shortcuts, or an error.
+fn edit_shortcuts<F: FnOnce(&mut ShortcutsFile) -> Result<(), VdfError>>(app_handle: &AppHandle, steam_path: String, steam_active_user_id: String, action: &str, edit: F) -> String {
+  let shortcuts_path = PathBuf::from(steam::get_shortcuts_path(app_handle.to_owned(), steam_path, steam_active_user_id));
+
+  let edit_res = open_shortcuts_file(&shortcuts_path).and_then(| mut shortcuts_file | {
+    edit(&mut shortcuts_file)?;
+
+    write_shortcuts_file(app_handle, &shortcuts_path, &shortcuts_file.to_kv_map())?;
+    return Ok(shortcuts_file);
+  });
+
+  match edit_res {
+    Ok(shortcuts_file) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("{} Wrote {} shortcuts.", action, shortcuts_file.len()).as_str(), 0);
+      return shortcuts_to_json(&shortcuts_file);
+    },
+    Err(err) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to edit shortcuts.vdf: {}", err).as_str(), 2);
+      return error_to_json(&err);
+    }
+  }
+}
+
+#[tauri::command]
+/// Gets the user's shortcuts.
+pub async fn get_shortcuts(app_handle: AppHandle, steam_path: String, steam_active_user_id: String) -> String {
+  let shortcuts_path = PathBuf::from(steam::get_shortcuts_path(app_handle.to_owned(), steam_path, steam_active_user_id));
+
+  match open_shortcuts_file(&shortcuts_path) {
+    Ok(shortcuts_file) => return shortcuts_to_json(&shortcuts_file),
+    Err(err) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to read shortcuts.vdf: {}", err).as_str(), 2);
+      return serde_json::json!({ "error": err.to_string() }).to_string();
+    }
+  }
+}
+
+#[tauri::command]
+/// Adds a shortcut to the user's shortcuts.vdf file, and renumbers the shortcuts so the file has no gaps Steam would reject.
+pub async fn add_shortcut(app_handle: AppHandle, steam_path: String, steam_active_user_id: String, shortcut: Shortcut) -> String {
+  let action = format!("Added shortcut {}.", shortcut.app_name);
+
+  return edit_shortcuts(&app_handle, steam_path, steam_active_user_id, &action, | shortcuts_file | {
+    shortcuts_file.add(shortcut)?;
+    shortcuts_file.renumber();
+
+    return Ok(());
+  });
+}
+
+#[tauri::command]
+/// Updates a shortcut in the user's shortcuts.vdf file, matching it by appid.
+pub async fn update_shortcut(app_handle: AppHandle, steam_path: String, steam_active_user_id: String, shortcut: Shortcut) -> String {
+  let action = format!("Updated shortcut {}.", shortcut.appid);
+
+  return edit_shortcuts(&app_handle, steam_path, steam_active_user_id, &action, | shortcuts_file | shortcuts_file.update(shortcut));
+}
+
+#[tauri::command]
+/// Removes a shortcut from the user's shortcuts.vdf file, and renumbers the rest.
+pub async fn remove_shortcut(app_handle: AppHandle, steam_path: String, steam_active_user_id: String, appid: u32) -> String {
+  let action = format!("Removed shortcut {}.", appid);
+
+  return edit_shortcuts(&app_handle, steam_path, steam_active_user_id, &action, | shortcuts_file | {
+    shortcuts_file.remove(appid).ok_or(VdfError::InvalidData(format!("There is no shortcut with appid {}", appid)))?;
+    shortcuts_file.renumber();
+
+    return Ok(());
+  });
+}
+
+#[tauri::command]
+/// Renumbers the shortcuts in the user's shortcuts.vdf file, so their indexes have no gaps.
+pub async fn renumber_shortcuts(app_handle: AppHandle, steam_path: String, steam_active_user_id: String) -> String {
+  return edit_shortcuts(&app_handle, steam_path, steam_active_user_id, "Renumbered shortcuts.", | shortcuts_file | {
+    shortcuts_file.renumber();
+    return Ok(());
+  });
+}
+
+#[tauri::command]
+/// Gets the file names of the backups of the user's shortcuts.vdf file, newest first.
+pub async fn get_shortcuts_backups(app_handle: AppHandle, steam_path: String, steam_active_user_id: String) -> String {
+  let shortcuts_path = PathBuf::from(steam::get_shortcuts_path(app_handle.to_owned(), steam_path, steam_active_user_id));
+  let backup_names = shortcuts_path.parent().map(| config_dir | get_backup_names(&config_dir.to_path_buf())).unwrap_or_default();
+
+  return serde_json::to_string(&backup_names).expect("Should have been able to serialize shortcuts.vdf backups to string.");
+}
+
+#[tauri::command]
+/// Restores the user's shortcuts.vdf file from one of its backups. The current file is backed up first, so restoring can be undone.
+/// The backup's bytes are copied as they are, rather than parsed and rewritten, so the restored file is exactly the one that was backed up.
+pub async fn restore_shortcuts_backup(app_handle: AppHandle, steam_path: String, steam_active_user_id: String, backup_name: String) -> String {
+  let shortcuts_path = PathBuf::from(steam::get_shortcuts_path(app_handle.to_owned(), steam_path, steam_active_user_id));
+  let config_dir = shortcuts_path.parent().map(| config_dir | config_dir.to_path_buf()).unwrap_or_default();
+
+  //? Only names from the backup list are accepted, so the frontend can't restore from an arbitrary path.
+  if !get_backup_names(&config_dir).contains(&backup_name) {
+    logger::log_to_core_file(app_handle.to_owned(), format!("Shortcuts.vdf backup {} does not exist.", backup_name).as_str(), 2);
+    return serde_json::json!({ "error": format!("Backup {} does not exist", backup_name) }).to_string();
+  }
+
+  let restore_res = fs::read(config_dir.join(&backup_name)).map_err(VdfError::from).and_then(| backup_bytes | {
+    //? The backup being restored is kept, so restoring one of the oldest backups doesn't remove it.
+    backup_shortcuts(&app_handle, &shortcuts_path, Some(&backup_name));
+    write_file_atomic(&shortcuts_path, | sink | Ok(sink.write_all(&backup_bytes)?))?;
+
+    return ShortcutsFile::open(&shortcuts_path);
+  });
+
+  match restore_res {
+    Ok(shortcuts_file) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Restored shortcuts.vdf from {}.", backup_name).as_str(), 0);
+      return shortcuts_to_json(&shortcuts_file);
+    },
+    Err(err) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to restore shortcuts.vdf from {}: {}", backup_name, err).as_str(), 2);
+      return error_to_json(&err);
+    }
+  }
+}
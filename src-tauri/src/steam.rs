@@ -1,13 +1,17 @@
 
+use crate::appinfo_cache;
 use crate::logger;
-use new_vdf_parser::appinfo_vdf_parser::open_appinfo_vdf;
+use new_vdf_parser::appinfo_vdf_parser::verify_appinfo_vdf;
+use new_vdf_parser::packageinfo_vdf_parser::open_packageinfo_vdf;
 use new_vdf_parser::shortcuts_vdf_parser::open_shortcuts_vdf;
+use new_vdf_parser::text_vdf_parser::open_text_vdf;
+use new_vdf_parser::{read_steam_users, KvMap, KvValue, SteamUser};
 
+use std::collections::HashSet;
 use std::fs::{self, create_dir_all};
 use std::path::{ PathBuf, Path };
 
 use serde_json::{Value, Map};
-use keyvalues_parser::{Vdf, parse};
 
 #[cfg(target_os = "windows")]
 use winreg::{ enums::*, RegKey };
@@ -58,6 +62,30 @@ pub fn get_steam_root_dir() -> Result<PathBuf, String> {
   }
 }
 
+#[cfg(target_os = "windows")]
+/// Gets the language the Steam client is set to, like "english" or "schinese". (Windows)
+pub fn get_steam_language() -> Option<String> {
+  let hkcu: RegKey = RegKey::predef(HKEY_CURRENT_USER);
+  let steam_install_data: RegKey = hkcu.open_subkey("SOFTWARE\\Valve\\Steam").ok()?;
+
+  return steam_install_data.get_value("Language").ok();
+}
+
+#[cfg(target_os = "linux")]
+/// Gets the language the Steam client is set to, like "english" or "schinese". (Linux)
+pub fn get_steam_language() -> Option<String> {
+  let pc_home_dir: PathBuf = home_dir()?;
+  let registry_paths = [
+    pc_home_dir.join(".var/app/com.valvesoftware.Steam/.steam/registry.vdf"),
+    pc_home_dir.join(".steam/registry.vdf"),
+  ];
+
+  let registry_path = registry_paths.into_iter().find(| path | path.exists())?;
+  let registry: KvMap = open_text_vdf(&registry_path, None).ok()?;
+
+  return registry.get_path_ignore_case("Registry/HKCU/Software/Valve/Steam/language").and_then(KvValue::as_str).map(str::to_owned);
+}
+
 #[tauri::command]
 /// Gets the steam grids directory.
 pub fn get_grids_directory(app_handle: AppHandle, steam_path: String, steam_active_user_id: String) -> String {
@@ -110,6 +138,17 @@ pub fn get_appinfo_path(app_handle: AppHandle, steam_path: String) -> String {
   return joined_path.to_str().expect("Should have been able to convert to a string.").to_owned().replace("\\", "/");
 }
 
+#[tauri::command]
+/// Gets the steam packageinfo.vdf path.
+pub fn get_packageinfo_path(app_handle: AppHandle, steam_path: String) -> String {
+  logger::log_to_core_file(app_handle.to_owned(), "Getting steam packageinfo.vdf...", 0);
+  
+  let steam_root: PathBuf = PathBuf::from(steam_path);
+  let joined_path: PathBuf = steam_root.join("appcache/packageinfo.vdf");
+
+  return joined_path.to_str().expect("Should have been able to convert to a string.").to_owned().replace("\\", "/");
+}
+
 #[tauri::command]
 /// Gets the steam shortcuts.vdf path.
 pub fn get_shortcuts_path(app_handle: AppHandle, steam_path: String, steam_active_user_id: String) -> String {
@@ -190,84 +229,56 @@ pub fn get_goldsrc_path(app_handle: AppHandle, steam_path: String) -> String {
   }
 }
 
-/// Reads a steam user's id.
-fn read_steam_user_id(user_block: &str) -> String {
-  let quote_index = user_block.find("\"").expect("Should have been able to find a quote.");
-  let id_str = &user_block[..quote_index];
-  return id_str.to_owned();
-}
-
-/// Reads a steam user.
-fn read_steam_user(user_id: &str, user_block: &str) -> Map<String, Value> {
-  let id_32 = user_id.parse::<u64>().unwrap() - 76561197960265728;
-
-  let mut steam_user: Map<String, Value> = Map::new();
-  steam_user.insert("id64".to_owned(), Value::String(user_id.to_owned()));
-  steam_user.insert("id32".to_owned(), Value::String(id_32.to_string()));
-
-  let prop_start_matches: Vec<(usize, &str)> = user_block.match_indices("\n\t").collect();
-  let len = prop_start_matches.len();
-
-  for (vec_index, (index, _)) in prop_start_matches.clone().into_iter().enumerate() {
-    if vec_index < len - 1 {
-      let next_prop_start = prop_start_matches[vec_index + 1].0;
-      let prop_line = &user_block[(index + 4)..(next_prop_start - 1)];
-
-      let segments: Vec<&str> = prop_line.split("\"\t\t\"").collect();
-      let key = segments[0].to_owned();
-      let value = segments[1].to_owned();
-
-      steam_user.insert(key, Value::String(value));
-    }
-  }
-
-  return steam_user;
-}
+#[tauri::command]
+/// Gets all steam users that have logged in on this computer, keyed by their 64 bit ids.
+/// If loginusers.vdf is missing, the users are listed from the userdata dir instead, and only have their ids.
+pub fn get_steam_users(app_handle: AppHandle, steam_path: String) -> String {
+  logger::log_to_core_file(app_handle.to_owned(), "Checking config/loginusers.vdf for current user info.", 0);
 
-/// Reads the steam users.
-fn read_steam_users(steam_path: String) -> Map<String, Value> {
-  let mut steam_users: Map<String, Value> = Map::new();
-    
   let steam_root: PathBuf = PathBuf::from(steam_path);
 
-  let loginusers_vdf: PathBuf = steam_root.join("config/loginusers.vdf");
-  let contents: String = fs::read_to_string(loginusers_vdf).unwrap();
-
-  let id_start_matches: Vec<(usize, &str)> = contents.match_indices("\n\t\"").collect();
-  let block_end_matches: Vec<(usize, &str)> = contents.match_indices("}").collect();
-
-  for (vec_index, (index, _)) in id_start_matches.iter().enumerate() {
-    let close_brace_index: usize = block_end_matches[vec_index].0;
-    let user_block: String = contents[(*index + 3)..close_brace_index].to_owned();
-
-    let id: String = read_steam_user_id(&user_block);
-    
-    let user_map: Map<String, Value> = read_steam_user(&id, &user_block[(id.len() + 4)..]);
-
-    steam_users.insert(id.to_string(), Value::Object(user_map));
+  if !steam_root.join("config/loginusers.vdf").exists() {
+    logger::log_to_core_file(app_handle.to_owned(), "config/loginusers.vdf does not exist. Listing users from userdata instead.", 1);
   }
 
-  return steam_users;
-}
+  let steam_users: Vec<SteamUser> = match read_steam_users(&steam_root) {
+    Ok(steam_users) => steam_users,
+    Err(err) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to read steam users: {}", err).as_str(), 2);
+      Vec::new()
+    }
+  };
 
-#[tauri::command]
-/// Gets all steam users that have logged in on this computer.
-pub fn get_steam_users(app_handle: AppHandle, steam_path: String) -> String {
-  logger::log_to_core_file(app_handle.to_owned(), "Checking config/loginusers.vdf for current user info.", 0);
-    
-  let steam_users = read_steam_users(steam_path);
-  
   logger::log_to_core_file(app_handle.to_owned(), format!("Loaded {} steam users.", steam_users.len()).as_str(), 0);
 
-  return serde_json::to_string(&steam_users).unwrap();
+  let users_by_id: Map<String, Value> = steam_users.iter()
+    .map(| steam_user | (steam_user.id64.to_string(), serde_json::to_value(steam_user).expect("Should have been able to serialize steam user.")))
+    .collect();
+
+  return serde_json::to_string(&users_by_id).expect("Should have been able to serialize steam users to string.");
 }
 
 #[tauri::command]
-/// Reads the user's appinfo.vdf file.
-pub async fn read_appinfo_vdf(app_handle: AppHandle, steam_path: String) -> String {
+/// Reads the summaries of the user's apps with the provided types from appinfo.vdf, using the appinfo cache for apps that haven't changed.
+pub async fn read_appinfo_vdf(app_handle: AppHandle, steam_path: String, app_types: Vec<String>) -> String {
   let appinfo_path: PathBuf = PathBuf::from(get_appinfo_path(app_handle.to_owned(), steam_path));
-  let appinfo_vdf: Map<String, Value> = open_appinfo_vdf(&appinfo_path, Some(false));
-  return serde_json::to_string(&appinfo_vdf).expect("Should have been able to serialize AppInfo vdf to string.");
+  let app_types: Vec<&str> = app_types.iter().map(String::as_str).collect();
+
+  match appinfo_cache::get_app_summaries(&app_handle, &appinfo_path, &app_types) {
+    Ok(entries) => return serde_json::json!({ "entries": entries }).to_string(),
+    Err(err) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to read appinfo.vdf: {}", err).as_str(), 2);
+
+      //? Log which apps are damaged, so a corrupt Steam cache can be told apart from a parser bug.
+      if let Ok(problems) = verify_appinfo_vdf(&appinfo_path) {
+        for problem in problems {
+          logger::log_to_core_file(app_handle.to_owned(), problem.to_string().as_str(), 1);
+        }
+      }
+
+      return serde_json::json!({ "error": err.to_string() }).to_string();
+    }
+  }
 }
 
 #[tauri::command]
@@ -277,48 +288,105 @@ pub async fn read_shortcuts_vdf(app_handle: AppHandle, steam_path: String, steam
     
   if shortcuts_path.as_path().exists() {
     logger::log_to_core_file(app_handle.to_owned(), "shortcuts.vdf exists, reading...", 0);
-    let shortcuts_array = open_shortcuts_vdf(&shortcuts_path);
-    return serde_json::to_string(&shortcuts_array).expect("Should have been able to serialize Shortcuts vdf to string.");
+
+    match open_shortcuts_vdf(&shortcuts_path) {
+      Ok(mut shortcuts_vdf) => {
+        let shortcuts_array: &mut KvValue = match shortcuts_vdf.get_mut("shortcuts") {
+          Some(shortcuts) => shortcuts,
+          None => return "{}".to_owned(),
+        };
+
+        //? Int32s are serialized signed, but the frontend names grids after the unsigned appid. The file's types are read again when writing, so this doesn't change them.
+        for shortcut in shortcuts_array.as_map_mut().into_iter().flat_map(| shortcuts | shortcuts.iter_mut()).filter_map(| (_, shortcut) | shortcut.as_map_mut()) {
+          for (_, appid) in shortcut.iter_mut().filter(| (key, _) | key.eq_ignore_ascii_case("appid")) {
+            if let Some(unsigned) = appid.as_u32() {
+              *appid = KvValue::UInt64(unsigned as u64);
+            }
+          }
+        }
+
+        return serde_json::to_string(shortcuts_array).expect("Should have been able to serialize Shortcuts vdf to string.");
+      },
+      Err(err) => {
+        logger::log_to_core_file(app_handle.to_owned(), format!("Failed to read shortcuts.vdf: {}", err).as_str(), 2);
+        return serde_json::json!({ "error": err.to_string() }).to_string();
+      }
+    }
   } else {
     logger::log_to_core_file(app_handle.to_owned(), "shortcuts.vdf does not exist.", 0);
     return "{}".to_owned();
   }
 }
 
+/// Gets the appids granted by the user's licenses, by looking up their packages in packageinfo.vdf.
+/// Valve doesn't document either file. This follows what the Steam client writes: localconfig.vdf has a `UserLocalConfigStore/Licenses` block keyed by packageid,
+/// and each package in packageinfo.vdf has an `appids` block listing the apps it grants.
+/// If the user's licenses can't be found, no apps are added, so the library falls back to the apps localconfig.vdf lists.
+fn get_licensed_appids(app_handle: &AppHandle, steam_path: String, localconfig: &KvMap) -> Vec<String> {
+  let licenses: &KvMap = match localconfig.get_path_ignore_case("UserLocalConfigStore/Licenses").and_then(KvValue::as_map) {
+    Some(licenses) => licenses,
+    None => {
+      logger::log_to_core_file(app_handle.to_owned(), "localconfig.vdf has no Licenses block, so only the apps it lists will be shown.", 1);
+      return Vec::new();
+    }
+  };
+
+  let packageids: HashSet<u32> = licenses.keys().filter_map(| packageid | packageid.parse().ok()).collect();
+
+  if packageids.is_empty() {
+    logger::log_to_core_file(app_handle.to_owned(), "localconfig.vdf has no licenses, so only the apps it lists will be shown.", 0);
+    return Vec::new();
+  }
+
+  let packageinfo_path = PathBuf::from(get_packageinfo_path(app_handle.to_owned(), steam_path));
+
+  match open_packageinfo_vdf(&packageinfo_path) {
+    Ok(packages) => {
+      let appids: Vec<String> = packages.iter()
+        .filter(| package | packageids.contains(&package.packageid))
+        .flat_map(| package | package.appids.iter().map(| appid | appid.to_string()))
+        .collect();
+
+      logger::log_to_core_file(app_handle.to_owned(), format!("Found {} apps in {} licenses.", appids.len(), packageids.len()).as_str(), 0);
+      return appids;
+    },
+    Err(err) => {
+      logger::log_to_core_file(app_handle.to_owned(), format!("Failed to read packageinfo.vdf: {}", err).as_str(), 1);
+      return Vec::new();
+    }
+  }
+}
+
 #[tauri::command]
-/// Reads the user's localconfig.vdf file.
+/// Reads the user's localconfig.vdf file, and gets the appids of the apps it lists and the apps granted by their licenses.
 pub async fn read_localconfig_vdf(app_handle: AppHandle, steam_path: String, steam_active_user_id: String) -> String {
-  let localconfig_path = PathBuf::from(get_localconfig_path(app_handle.to_owned(), steam_path, steam_active_user_id));
+  let localconfig_path = PathBuf::from(get_localconfig_path(app_handle.to_owned(), steam_path.clone(), steam_active_user_id));
     
   if localconfig_path.as_path().exists() {
     logger::log_to_core_file(app_handle.to_owned(), "localconfig.vdf exists, reading...", 0);
-    let localconfig_contents: String = fs::read_to_string(localconfig_path).expect("localconfig.vdf should have existed.").parse().expect("File should have been a text file.");
-    
-    let mut appids: Vec<String> = Vec::new();
-    
-    let mut is_reading_appids: bool = false;
 
-    for line in localconfig_contents.split("\n") {
-      if line == "\t\t\t\t\"apps\"" {
-        is_reading_appids = true;
-        continue;
-      }
+    match open_text_vdf(&localconfig_path, None) {
+      Ok(localconfig) => {
+        let apps: Option<&KvMap> = localconfig.get_path_ignore_case("UserLocalConfigStore/Software/Valve/Steam/apps").and_then(KvValue::as_map);
 
-      if is_reading_appids {
-        if line.starts_with("\t\t\t\t\t\"") {
-          let length = line.chars().count();
-          let appid_res = line.get(6..(length - 1));
-          let appid = appid_res.expect("appid_res should have been ok");
-          appids.push(appid.to_string());
-        }
+        let mut appids: Vec<String> = apps.map(| apps | apps.keys().cloned().collect()).unwrap_or_default();
+
+        //? The apps list only has apps that have been launched, so owned apps that were never installed come from the user's licenses.
+        let mut seen: HashSet<String> = appids.iter().cloned().collect();
 
-        if line == "\t\t\t\t}" {
-          break;
+        for appid in get_licensed_appids(&app_handle, steam_path, &localconfig) {
+          if seen.insert(appid.clone()) {
+            appids.push(appid);
+          }
         }
+
+        return serde_json::to_string(&appids).expect("Should have been able to serialize localconfig vdf to string.");
+      },
+      Err(err) => {
+        logger::log_to_core_file(app_handle.to_owned(), format!("Failed to read localconfig.vdf: {}", err).as_str(), 2);
+        return "[]".to_owned();
       }
     }
-
-    return serde_json::to_string(&appids).expect("Should have been able to serialize localconfig vdf to string.");
   } else {
     logger::log_to_core_file(app_handle.to_owned(), "localconfig.vdf does not exist.", 0);
     return "{}".to_owned();
@@ -332,34 +400,21 @@ pub async fn get_installed_appids(app_handle: AppHandle, steam_path: String) ->
     
   if libraryfolders_path.as_path().exists() {
     logger::log_to_core_file(app_handle.to_owned(), "libraryfolders.vdf exists, reading...", 0);
-    let libraryfolders_contents: String = fs::read_to_string(libraryfolders_path).expect("libraryfolders.vdf should have existed.").parse().expect("File should have been a text file.");
-    
-    let mut appids: Vec<String> = Vec::new();
-    
-    let vdf = parse(&libraryfolders_contents).map(Vdf::from).expect("libraryfolders.vdf should have been parsable.");
-
-    let root = vdf.value
-      .get_obj()
-      .expect("should have been able to get library folders obj.");
 
-    for (_, library_value) in root.clone().0 {
-      let library = match library_value[0].get_obj() {
-        Some(v) => v,
-        None => continue,
-      };
-
-      if let Some(apps) = library
-        .get("apps")
-      {
-        let apps_list = apps[0].get_obj().expect("Should have been able to convert library apps to obj.");
-
-        for (app_id, _) in apps_list.clone().0 {
-          appids.push(app_id.into_owned());
-        }
+    match open_text_vdf(&libraryfolders_path, None) {
+      Ok(libraryfolders) => {
+        let appids: Vec<&String> = libraryfolders.query_ignore_case("libraryfolders/*/apps").into_iter()
+          .filter_map(| (_, apps) | apps.as_map())
+          .flat_map(| apps | apps.keys())
+          .collect();
+
+        return serde_json::to_string(&appids).expect("Should have been able to serialize libraryfolders vdf to string.");
+      },
+      Err(err) => {
+        logger::log_to_core_file(app_handle.to_owned(), format!("Failed to read libraryfolders.vdf: {}", err).as_str(), 2);
+        return "[]".to_owned();
       }
     }
-
-    return serde_json::to_string(&appids).expect("Should have been able to serialize libraryfolders vdf to string.");
   } else {
     logger::log_to_core_file(app_handle.to_owned(), "libraryfolders.vdf does not exist.", 0);
     return "{}".to_owned();
@@ -15,7 +15,7 @@
  * You should have received a copy of the GNU General Public License
  * along with this program. If not, see <https://www.gnu.org/licenses/>
  */
-import { GridTypes, type ChangedPath, type LogoPinPositions, type SGDBGame, type SGDBImage } from "@types";
+import { GridTypes, type ChangedPath, type LogoPinPositions, type SGDBGame, type SGDBImage, type ShortcutsWriteError } from "@types";
 import { restartApp } from "@utils";
 import { createTippy } from "svelte-tippy";
 import { get } from "svelte/store";
@@ -119,7 +119,7 @@ export class AppController {
     
     if ((changedPaths as any).error !== undefined) {
       get(showErrorSnackbar)({ message: "Changes failed." });
-      LogController.log("Changes failed.");
+      LogController.log(`Changes failed: ${(changedPaths as ShortcutsWriteError).error}`);
     } else {
       for (const changedPath of (changedPaths as ChangedPath[])) {
         const originalPath = unfilteredCache[changedPath.appId]?.[changedPath.gridType] ?? "";
@@ -4,7 +4,7 @@ import { fetch } from "@tauri-apps/plugin-http";
 import { exit } from "@tauri-apps/plugin-process";
 import { get } from "svelte/store";
 
-import { activeUserId, appLibraryCache, isOnline, manualSteamGames, needsSteamKey, nonSteamGames, originalAppLibraryCache, originalLogoPositions, originalSteamShortcuts, requestTimeoutLength, showErrorSnackbar, steamGames, steamKey, steamLogoPositions, steamShortcuts, unfilteredLibraryCache } from "@stores/AppState";
+import { activeUserId, appLibraryCache, appTypes, isOnline, manualSteamGames, needsSteamKey, nonSteamGames, originalAppLibraryCache, originalLogoPositions, originalSteamShortcuts, requestTimeoutLength, showErrorSnackbar, steamGames, steamKey, steamLogoPositions, steamShortcuts, unfilteredLibraryCache } from "@stores/AppState";
 
 import { LogController } from "./utils/LogController";
 import { RustInterop } from "./utils/RustInterop";
@@ -68,27 +68,22 @@ export class SteamController {
     // LogController.log("Loading games from appinfo.vdf...");
     const installedAppIds: string[] = await RustInterop.getInstalledAppIds();
 
-    const vdf: AppInfoVdf = await RustInterop.readAppinfoVdf();
+    const vdf: AppInfoVdf = await RustInterop.readAppinfoVdf(get(appTypes));
+
+    if (vdf.error !== undefined) {
+      // * Steam betas can ship appinfo.vdf versions we don't support yet, so show why it failed.
+      get(showErrorSnackbar)({ message: `Failed to read appinfo.vdf: ${vdf.error}` });
+      return [];
+    }
 
     return vdf.entries
-      .filter((entry) => !!entry && !!(entry.common))
-      .filter((entry: any) => ids.includes(entry.appid.toString()))
+      .filter((entry) => ids.includes(entry.appid.toString()))
       .map((entry) => {
-        const commonData = entry.common!;
-        const libraryAssets = commonData.library_assets_full;
-        
         return {
           appid: entry.appid,
           // eslint-disable-next-line no-control-regex
-          name: typeof commonData.name === "string" ? commonData.name.replace(/[^\x00-\x7F]/g, "") : commonData.name.toString(),
-          gridInfo: {
-            icon: commonData.icon ? (commonData.icon + ".jpg") : "",
-            capsule: libraryAssets?.library_capsule?.image?.english ?? "",
-            wideCapsule: libraryAssets?.library_header?.image?.english ?? commonData.header_image?.english ?? "",
-            hero: libraryAssets?.library_hero?.image?.english ?? "",
-            logo: libraryAssets?.library_logo?.image?.english ?? "",
-          },
-          type: commonData.type ?? "Game",
+          name: entry.name.replace(/[^\x00-\x7F]/g, ""),
+          type: entry.type,
           installed: installedAppIds.includes(entry.appid.toString())
         };
       }).sort((gameA: GameStruct, gameB: GameStruct) => gameA.name.localeCompare(gameB.name));
@@ -181,10 +176,16 @@ export class SteamController {
       await exit(0);
     }
 
-    const [ shortcuts, steamApps ] = await Promise.all([
+    const [ shortcutsRes, steamApps ] = await Promise.all([
       RustInterop.readShortcutsVdf(userId.toString()),
       SteamController.getSteamApps(),
     ]);
+
+    let shortcuts = shortcutsRes;
+    if (shortcutsRes.error !== undefined) {
+      get(showErrorSnackbar)({ message: "Failed to read shortcuts.vdf" });
+      shortcuts = {};
+    }
     
     originalSteamShortcuts.set(structuredClone(Object.values(shortcuts)));
     steamShortcuts.set(Object.values(shortcuts));
@@ -17,7 +17,7 @@
  */
 import { steamInstallPath } from "@stores/AppState";
 import { invoke } from "@tauri-apps/api/core";
-import type { ChangedPath, CleanConflict, GameStruct, LibraryCacheEntry, SteamShortcut, SteamUser } from "@types";
+import type { ChangedPath, CleanConflict, GameStruct, LibraryCacheEntry, Shortcut, ShortcutsWriteError, SteamShortcut, SteamUser } from "@types";
 import { get } from "svelte/store";
 
 /**
@@ -150,17 +150,17 @@ export class RustInterop {
    * Gets the user's cache data..
    * @param activeUserId The id of the active user.
    * @param shortcutIds The list of shortcut ids.
-   * @param steamApps The loaded steamApps
+   * @param steamApps The loaded steamApps. Their library images are looked up from appinfo.vdf in the user's Steam language.
    * @returns A promise resolving to the user's cache data.
    */
   static async getCacheData(activeUserId: string, shortcutIds: string[], steamApps: GameStruct[]): Promise<[Record<string, LibraryCacheEntry>, Record<string, LibraryCacheEntry>, string[]]> {
-    const appsMap = Object.fromEntries(steamApps.map((app) => [ app.appid, app.gridInfo ]));
-    return await invoke<[Record<string, LibraryCacheEntry>, Record<string, LibraryCacheEntry>, string[]]>("get_cache_data", { steamPath: RustInterop.steamPath, steamActiveUserId: activeUserId, shortcutIds, steamApps: appsMap });
+    const steamAppIds = steamApps.map((app) => app.appid.toString());
+    return await invoke<[Record<string, LibraryCacheEntry>, Record<string, LibraryCacheEntry>, string[]]>("get_cache_data", { steamPath: RustInterop.steamPath, steamActiveUserId: activeUserId, shortcutIds, steamAppIds });
   }
 
   /**
    * Gets a list of steam users on this computer.
-   * @returns A promise resolving to the list of steam users on this computer.
+   * @returns A promise resolving to the steam users on this computer, keyed by their 64 bit ids.
    */
   static async getSteamUsers(): Promise<Record<string, SteamUser>> {
     return JSON.parse(await invoke<string>("get_steam_users", { steamPath: RustInterop.steamPath }));
@@ -199,11 +199,12 @@ export class RustInterop {
   }
 
   /**
-   * Reads the current user's apps from the appinfo.vdf file.
-   * @returns A promise resolving to the contents of the appinfo.vdf file.
+   * Reads the summaries of the current user's apps from the appinfo.vdf file.
+   * @param appTypes The app types to include, like "game" or "application".
+   * @returns A promise resolving to the summaries of the apps with those types.
    */
-  static async readAppinfoVdf(): Promise<any> {
-    return JSON.parse(await invoke<string>("read_appinfo_vdf", { steamPath: RustInterop.steamPath }));
+  static async readAppinfoVdf(appTypes: string[]): Promise<any> {
+    return JSON.parse(await invoke<string>("read_appinfo_vdf", { steamPath: RustInterop.steamPath, appTypes: appTypes }));
   }
 
   /**
@@ -243,7 +244,7 @@ export class RustInterop {
    * @param originalShortcutIcons The map of shortcutIds to original icons.
    * @returns A promise resolving to a string of serialized changed tuples.
    */
-  static async saveChanges(activeUserId: string, currentArt: Record<string, LibraryCacheEntry>, originalArt: Record<string, LibraryCacheEntry>, shortcuts: SteamShortcut[], shortcutIcons: Record<string, string>, originalShortcutIcons: Record<string, string>, changedLogoPositions: Record<string, string>): Promise<ChangedPath[] | { error: string }> {
+  static async saveChanges(activeUserId: string, currentArt: Record<string, LibraryCacheEntry>, originalArt: Record<string, LibraryCacheEntry>, shortcuts: SteamShortcut[], shortcutIcons: Record<string, string>, originalShortcutIcons: Record<string, string>, changedLogoPositions: Record<string, string>): Promise<ChangedPath[] | ShortcutsWriteError> {
     const shortcutsObj = {
       "shortcuts": { ...shortcuts }
     }
@@ -255,9 +256,9 @@ export class RustInterop {
    * Writes changes to the steam shortcuts.
    * @param activeUserId The id of the active user.
    * @param shortcuts The list of shortcuts.
-   * @returns A promise resolving to true if the write was successful.
+   * @returns A promise resolving to true if the write was successful, or the error and any validation problems if it wasn't.
    */
-  static async writeShortcuts(activeUserId: string, shortcuts: SteamShortcut[]): Promise<boolean> {
+  static async writeShortcuts(activeUserId: string, shortcuts: SteamShortcut[]): Promise<true | ShortcutsWriteError> {
     const shortcutsObj = {
       "shortcuts": { ...shortcuts }
     }
@@ -265,6 +266,73 @@ export class RustInterop {
     return JSON.parse(res);
   }
 
+  /**
+   * Gets the current user's shortcuts.
+   * @param activeUserId The id of the active user.
+   * @returns A promise resolving to the user's shortcuts.
+   */
+  static async getShortcuts(activeUserId: string): Promise<Shortcut[] | { error: string }> {
+    return JSON.parse(await invoke<string>("get_shortcuts", { steamPath: RustInterop.steamPath, steamActiveUserId: activeUserId }));
+  }
+
+  /**
+   * Adds a shortcut to the current user's shortcuts.vdf file.
+   * @param activeUserId The id of the active user.
+   * @param shortcut The shortcut to add. If its appid is 0, it gets the one Steam would give it.
+   * @returns A promise resolving to the user's shortcuts after adding it.
+   */
+  static async addShortcut(activeUserId: string, shortcut: Shortcut): Promise<Shortcut[] | ShortcutsWriteError> {
+    return JSON.parse(await invoke<string>("add_shortcut", { steamPath: RustInterop.steamPath, steamActiveUserId: activeUserId, shortcut: shortcut }));
+  }
+
+  /**
+   * Updates a shortcut in the current user's shortcuts.vdf file, matching it by appid.
+   * @param activeUserId The id of the active user.
+   * @param shortcut The updated shortcut.
+   * @returns A promise resolving to the user's shortcuts after updating it.
+   */
+  static async updateShortcut(activeUserId: string, shortcut: Shortcut): Promise<Shortcut[] | ShortcutsWriteError> {
+    return JSON.parse(await invoke<string>("update_shortcut", { steamPath: RustInterop.steamPath, steamActiveUserId: activeUserId, shortcut: shortcut }));
+  }
+
+  /**
+   * Removes a shortcut from the current user's shortcuts.vdf file.
+   * @param activeUserId The id of the active user.
+   * @param appid The appid of the shortcut to remove.
+   * @returns A promise resolving to the user's shortcuts after removing it.
+   */
+  static async removeShortcut(activeUserId: string, appid: number): Promise<Shortcut[] | ShortcutsWriteError> {
+    return JSON.parse(await invoke<string>("remove_shortcut", { steamPath: RustInterop.steamPath, steamActiveUserId: activeUserId, appid: appid }));
+  }
+
+  /**
+   * Renumbers the shortcuts in the current user's shortcuts.vdf file, so their indexes have no gaps.
+   * @param activeUserId The id of the active user.
+   * @returns A promise resolving to the user's shortcuts.
+   */
+  static async renumberShortcuts(activeUserId: string): Promise<Shortcut[] | ShortcutsWriteError> {
+    return JSON.parse(await invoke<string>("renumber_shortcuts", { steamPath: RustInterop.steamPath, steamActiveUserId: activeUserId }));
+  }
+
+  /**
+   * Gets the backups of the current user's shortcuts.vdf file, which are made each time it is written.
+   * @param activeUserId The id of the active user.
+   * @returns A promise resolving to the file names of the backups, newest first.
+   */
+  static async getShortcutsBackups(activeUserId: string): Promise<string[]> {
+    return JSON.parse(await invoke<string>("get_shortcuts_backups", { steamPath: RustInterop.steamPath, steamActiveUserId: activeUserId }));
+  }
+
+  /**
+   * Restores the current user's shortcuts.vdf file from one of its backups.
+   * @param activeUserId The id of the active user.
+   * @param backupName The file name of the backup to restore.
+   * @returns A promise resolving to the restored shortcuts.
+   */
+  static async restoreShortcutsBackup(activeUserId: string, backupName: string): Promise<Shortcut[] | ShortcutsWriteError> {
+    return JSON.parse(await invoke<string>("restore_shortcuts_backup", { steamPath: RustInterop.steamPath, steamActiveUserId: activeUserId, backupName: backupName }));
+  }
+
   /**
    * Downloads a file to the provided destination from a given url.
    * @param gridUrl The url of the grid to download.
@@ -20,7 +20,7 @@ import { activeUserId, appTypes, cacheSelectedGrids, customGameNames, dbFilters,
 import { path } from "@tauri-apps/api";
 import * as fs from "@tauri-apps/plugin-fs";
 import { exit } from "@tauri-apps/plugin-process";
-import type { GridTypes, Settings, SteamUser } from "@types";
+import type { GridTypes, Settings } from "@types";
 import { findSteamPath, restartApp } from "@utils";
 import { get, type Unsubscriber } from "svelte/store";
 import { DialogController } from "./DialogController";
@@ -239,7 +239,6 @@ export class SettingsController {
    */
   private static async loadUserSettings(): Promise<string> {
     const users = await RustInterop.getSteamUsers();
-    const cleanedUsers: { [id: string]: SteamUser } = {};
 
     if (Object.keys(users).length === 0) {
       await DialogController.message(
@@ -252,30 +251,10 @@ export class SettingsController {
       await exit(0);
     }
 
-    // ? need to clean the data here bc props can vary in terms of case
-    for (const [ id, user ] of Object.entries(users)) {
-      const userKeys = Object.keys(user);
-      // @ts-expect-error since we're mapping from user's keys, it should always work.
-      const lowerCaseUser = Object.fromEntries(userKeys.map((key: string) => [ key.toLowerCase(), user[key] ]));
-
-      cleanedUsers[id] = {
-        id64: lowerCaseUser.id64,
-        id32: lowerCaseUser.id32,
-        AccountName: lowerCaseUser.accountname,
-        PersonaName: lowerCaseUser.personaname,
-        RememberPassword: lowerCaseUser.rememberpassword,
-        WantsOfflineMode: lowerCaseUser.wantsofflinemode,
-        SkipOfflineModeWarning: lowerCaseUser.skipofflinemodewarning,
-        AllowAutoLogin: lowerCaseUser.allowautologin,
-        MostRecent: lowerCaseUser.mostrecent,
-        Timestamp: lowerCaseUser.timestamp
-      }
-    }
-
-    steamUsers.set(cleanedUsers);
+    steamUsers.set(users);
 
-    const usersList = Object.values(cleanedUsers);
-    const activeUser = usersList.find((user) => user.MostRecent === "1") ?? usersList[0];
+    const usersList = Object.values(users);
+    const activeUser = usersList.find((user) => user.MostRecent) ?? usersList[0];
     activeUserId.set(parseInt(activeUser.id32));
 
     return activeUser.id32;
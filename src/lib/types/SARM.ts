@@ -1,19 +1,10 @@
 export type GameStruct = {
   appid: number,
   name: string,
-  gridInfo?: GameStructGridInfo
   type: string
   installed: boolean
 }
 
-export type GameStructGridInfo = {
-    icon: string,
-    capsule: string,
-    wideCapsule: string,
-    hero: string,
-    logo: string,
-  }
-
 export enum GridTypes {
   CAPSULE="Capsule",
   WIDE_CAPSULE="Wide Capsule",
@@ -39,48 +39,55 @@ export type SteamShortcut = {
   }
 }
 
-export type SteamUser = {
-  id64: string,
-  id32: string,
-  AccountName: string,
-  PersonaName: string,
-  RememberPassword: string,
-  WantsOfflineMode: string,
-  SkipOfflineModeWarning: string,
-  AllowAutoLogin: string,
-  MostRecent: string,
-  Timestamp: string
-}
-
-export type CommonLibraryImage = {
-  english?: string,
+export type Shortcut = {
+  appid: number,
+  appName: string,
+  exe: string,
+  startDir: string,
+  icon: string,
+  launchOptions: string,
+  isHidden: boolean,
+  allowOverlay: boolean,
+  openVR: boolean,
+  tags: string[],
+  lastPlayTime: number,
 }
 
-export type CommonLibraryAsset = {
-  image: CommonLibraryImage,
+export type ShortcutsProblem = {
+  kind: "missingShortcuts" | "unexpectedKey" | "notAMap" | "unexpectedIndex" | "missingKey" | "wrongType" | "duplicateAppid",
+  index?: string,
+  key?: string,
+  expected?: string,
+  actual?: string,
+  appid?: number,
+  original?: string,
 }
 
-export type CommonLibraryInfo = {
-  library_capsule?: CommonLibraryAsset,
-  library_header?: CommonLibraryAsset,
-  header_image?: CommonLibraryAsset,
-  library_hero?: CommonLibraryAsset,
-  library_logo?: CommonLibraryAsset,
+export type ShortcutsWriteError = {
+  error: string,
+  problems?: ShortcutsProblem[],
 }
 
-export type AppInfoCommonProps = {
-  library_assets_full?: CommonLibraryInfo
-  name: string | any,
-  type?: string,
-  icon?: string,
-  header_image?: CommonLibraryImage,
+export type SteamUser = {
+  id64: string,
+  id32: string,
+  AccountName: string,
+  PersonaName: string,
+  MostRecent: boolean,
+  RememberPassword: boolean,
+  Timestamp: number,
+  WantsOfflineMode: boolean,
+  SkipOfflineModeWarning: boolean,
+  AllowAutoLogin: boolean,
 }
 
-export type AppInfo = {
+export type AppSummary = {
   appid: number,
-  common?: AppInfoCommonProps
+  name: string,
+  type: string,
 }
 
 export type AppInfoVdf = {
-    entries: AppInfo[]
+    entries: AppSummary[],
+    error?: string
 }
\ No newline at end of file